@@ -29,68 +29,86 @@ pub struct AuthConfig {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct AuthConfigEntry {
     pub host: Box<str>,
-    pub user: Box<str>,
-    pub password: Box<str>,
+    pub auth: Option<Auth>,
+    pub client_cert: Option<ClientCert>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Auth {
+    Basic { user: Box<str>, password: Box<str> },
+    Bearer(Box<str>),
+}
+
+/// TLS client certificate and private key (PEM) for mutual TLS
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ClientCert {
+    pub cert: PathBuf,
+    pub key: PathBuf,
 }
 
 impl FromStr for AuthConfigEntry {
     type Err = AuthConfigError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let entry = s
-            .split_ascii_whitespace()
-            .filter(|x| !x.starts_with("#"))
-            .collect::<Vec<_>>();
+        let mut entry = s.split_ascii_whitespace();
 
         let mut host = None;
         let mut login = None;
         let mut password = None;
-
-        for (i, c) in entry.iter().enumerate() {
-            if *c == "machine" {
-                let Some(h) = entry.get(i + 1) else {
-                    return Err(AuthConfigError::MissingEntry("machine"));
-                };
-
-                host = Some(h);
-                continue;
-            }
-
-            if *c == "login" {
-                let Some(l) = entry.get(i + 1) else {
-                    return Err(AuthConfigError::MissingEntry("login"));
-                };
-
-                login = Some(l);
-                continue;
-            }
-
-            if *c == "password" {
-                let Some(p) = entry.get(i + 1) else {
-                    return Err(AuthConfigError::MissingEntry("password"));
-                };
-
-                password = Some(p);
-                continue;
-            }
+        // oma 扩展的字段，apt 不会读取 oma 自己的 auth.conf.d
+        let mut token = None;
+        let mut cert = None;
+        let mut key = None;
+
+        // 只在关键字的位置识别关键字，值（如密码）可以与关键字同名
+        while let Some(c) = entry.next() {
+            let (name, field) = match c {
+                "machine" => ("machine", &mut host),
+                "login" => ("login", &mut login),
+                "password" => ("password", &mut password),
+                "token" => ("token", &mut token),
+                "cert" => ("cert", &mut cert),
+                "key" => ("key", &mut key),
+                c if c.starts_with('#') => break,
+                _ => continue,
+            };
+
+            let Some(v) = entry.next() else {
+                return Err(AuthConfigError::MissingEntry(name));
+            };
+
+            *field = Some(v);
         }
 
         let Some(host) = host else {
             return Err(AuthConfigError::MissingEntry("machine"));
         };
 
-        let Some(login) = login else {
-            return Err(AuthConfigError::MissingEntry("login"));
+        let auth = match (login, password, token) {
+            (_, _, Some(token)) => Some(Auth::Bearer(token.into())),
+            (Some(login), Some(password), None) => Some(Auth::Basic {
+                user: login.into(),
+                password: password.into(),
+            }),
+            (Some(_), None, None) => return Err(AuthConfigError::MissingEntry("password")),
+            (None, Some(_), None) => return Err(AuthConfigError::MissingEntry("login")),
+            (None, None, None) => None,
         };
 
-        let Some(password) = password else {
-            return Err(AuthConfigError::MissingEntry("password"));
-        };
+        // 证书和私钥可以放在同一个 PEM 文件里
+        let client_cert = cert.map(|cert| ClientCert {
+            cert: cert.into(),
+            key: key.unwrap_or(cert).into(),
+        });
+
+        if auth.is_none() && client_cert.is_none() {
+            return Err(AuthConfigError::MissingEntry("login"));
+        }
 
         Ok(Self {
-            host: (*host).into(),
-            user: (*login).into(),
-            password: (*password).into(),
+            host: host.into(),
+            auth,
+            client_cert,
         })
     }
 }
@@ -109,7 +127,15 @@ impl AuthConfig {
         }
 
         let p = sysroot.as_ref().join("etc/apt/auth.conf.d");
-        Self::from_path(p)
+        let mut config = Self::from_path(p)?;
+
+        // oma 专用的配置，支持 token 及客户端证书
+        let oma = sysroot.as_ref().join("etc/oma/auth.conf.d");
+        if oma.is_dir() {
+            config.inner.extend(Self::from_path(oma)?.inner);
+        }
+
+        Ok(config)
     }
 
     pub fn from_path(p: impl AsRef<Path>) -> Result<Self, AuthConfigError> {
@@ -201,15 +227,58 @@ machine esm.ubuntu.com/infra/ubuntu/ login bearer password qaq  # ubuntu-pro-cli
             inner: vec![
                 AuthConfigEntry {
                     host: "esm.ubuntu.com/apps/ubuntu/".into(),
-                    user: "bearer".into(),
-                    password: "qaq".into(),
+                    auth: Some(Auth::Basic {
+                        user: "bearer".into(),
+                        password: "qaq".into(),
+                    }),
+                    client_cert: None,
                 },
                 AuthConfigEntry {
                     host: "esm.ubuntu.com/infra/ubuntu/".into(),
-                    user: "bearer".into(),
-                    password: "qaq".into(),
+                    auth: Some(Auth::Basic {
+                        user: "bearer".into(),
+                        password: "qaq".into(),
+                    }),
+                    client_cert: None,
                 },
             ]
         }
     );
 }
+
+#[test]
+fn test_config_parser_token_and_cert() {
+    let config = r#"machine artifacts.example.com/debian/ token s3cr3t
+machine secure.example.com cert /etc/oma/certs/client.pem key /etc/oma/certs/client.key
+machine both.example.com token s3cr3t cert /etc/oma/certs/both.pem
+"#;
+
+    let config = AuthConfig::from_str(config).unwrap();
+
+    assert_eq!(config.inner[0].auth, Some(Auth::Bearer("s3cr3t".into())));
+    assert_eq!(config.inner[1].auth, None);
+    assert_eq!(
+        config.inner[1].client_cert,
+        Some(ClientCert {
+            cert: "/etc/oma/certs/client.pem".into(),
+            key: "/etc/oma/certs/client.key".into(),
+        })
+    );
+    assert_eq!(
+        config.inner[2].client_cert.as_ref().map(|x| &x.key),
+        Some(&PathBuf::from("/etc/oma/certs/both.pem"))
+    );
+
+    assert!(AuthConfigEntry::from_str("machine example.com login foo").is_err());
+
+    // 值与关键字同名
+    let entry = AuthConfigEntry::from_str("machine example.com login token password key").unwrap();
+    assert_eq!(
+        entry.auth,
+        Some(Auth::Basic {
+            user: "token".into(),
+            password: "key".into(),
+        })
+    );
+    assert_eq!(entry.client_cert, None);
+}
@@ -41,6 +41,8 @@ packages-can-be-upgrade = { $len } package(s) can be upgraded
 packages-can-be-removed = { $len } package(s) can be removed
 comma = { ", " }
 successfully-refresh-with-tips = Successfully refreshed the package database. { $s }
+refresh-report-title = Changes in repositories:
+refresh-report-source = { $source }: { $added } new, { $removed } removed, { $updated } updated
 successfully-refresh = Successfully refreshed the package database. System is up to date.
 no-candidate-ver = Current version for { $pkg } is not available from the repository.
 pkg-is-not-installed = Unable to mark package { $pkg }, as it is not yet installed.
@@ -98,13 +100,70 @@ colon = :
 invalid-filename = Invalid file name: { $name }.
 checksum-mismatch-retry = Checksum verification failed for { $c }. Retrying { $retry } times ...
 can-not-get-source-next-url = Failed to download { $e }. Retrying using the next available mirror ...
+download-retry-wait = Failed to download: { $e }. Retrying in { $secs }s ({ $retry }) ...
 checksum-mismatch = Checksum verification failed for file { $filename }.
+download-too-slow = Download speed of { $filename } stayed below { $limit }/s for { $secs }s.
+failed-to-load-client-cert = Failed to load TLS client certificate { $path }.
+mirror-lists-unavailable = Failed to read mirror lists, packages from mirror+ sources may not be downloaded: { $reason }
+failed-to-open-progress-fd = Failed to open the progress output file descriptor.
 # db
 invalid-url = Invalid URL { $url }.
 can-not-parse-date = BUG: Failed to parse the Date field to the RFC2822 format. Please report this issue at https://github.com/AOSC-Dev/oma.
 can-not-parse-valid-until = BUG: Failed to parse the Valid-Until field in the RFC2822 format. Please report this issue at https://github.com/AOSC-Dev/oma.
 earlier-signature = InRelease file { $filename } is invalid: System time is earlier than the enclosed signature timestamp.
 expired-signature = InRelease file { $filename } is invalid: The enclosed signature has already expired.
+release-rollback = Release file { $filename } is older than the last accepted one (dated { $date }, last accepted { $last }). The mirror may be stale or under attack. Use `oma refresh --allow-release-rollback` if this downgrade is intentional.
+refresh-no-matching-source = No source matches { $source }.
+refresh-invalid-snapshot = Invalid snapshot time: { $time }. Use a format like 2026-09-01T00:00Z.
+refresh-using-snapshot = Using archive snapshot at { $time }, run `oma refresh --no-snapshot` to return to the live archive.
+refresh-snapshot-not-applied = Archive snapshot at { $time } is only used by `oma refresh`, refreshing against the live archive.
+refresh-verifying = Verifying local repository metadata ...
+refresh-verify-ok = All { $count } repository metadata files are intact.
+refresh-verify-bad-signature = Unable to verify the signature of { $path }: { $reason }
+refresh-verify-mismatch = { $path } does not match the checksum in its Release file.
+refresh-verify-missing = { $path } is listed in its Release file but missing.
+refresh-verify-failed = { $count } repository metadata file(s) failed verification. Run `oma refresh --verify-only --remove-broken` to remove them, then `oma refresh` to download them again.
+refresh-verify-removed = Removed { $count } broken repository metadata file(s). Run `oma refresh` to download them again.
+repo-build-done = Generated repository with { $count } package(s) for { $archs } in { $path }.
+repo-build-signed = The repository is signed, trust the key in /etc/apt/keyrings and use it with: { $line }
+repo-build-unsigned = The repository is not signed, use it with: { $line }
+repo-no-package = No .deb package found in { $path }.
+repo-bad-deb = Failed to read package { $path }.
+repo-sign-failed = Failed to sign the Release file.
+keys-not-found = No trusted key matches { $key }.
+keys-no-key-in-file = No OpenPGP key found in { $path }.
+keys-file-exists = { $path } already exists, use --name to choose another file name.
+keys-invalid-name = Invalid key file name { $name }, it must not start with a dot or contain slashes.
+keys-added = Imported key { $fingerprint } to { $path }.
+keys-added-tips = To trust this key only for specific sources, run `oma sources set <URI> --signed-by { $path }`.
+keys-not-system = { $path } is not managed by oma, please remove the key from it manually.
+keys-still-used = Key { $fingerprint } is still used by: { $sources }. These sources will fail to verify after removing it.
+keys-still-used-skipped = Key { $fingerprint } was not removed, use --force to remove it anyway.
+keys-removed = Removed key { $fingerprint } from { $path }.
+keys-expired = Key { $fingerprint } has expired.
+keys-expiring = Key { $fingerprint } will expire on { $date }.
+keys-fingerprint = Fingerprint
+keys-user-id = User ID
+keys-created = Created
+keys-expires = Expires
+keys-status = Status
+keys-file = File
+keys-trusted-by-default = Trusted by default
+keys-used-by = Used by
+keys-yes = yes
+keys-no = no
+keys-never = never
+keys-status-valid = valid
+keys-status-expired = expired
+keys-status-revoked = revoked
+keys-status-invalid = invalid
+verify-bad-key = Signing key { $fingerprint } is rejected by the signature policy: { $reason }
+verify-bad-signature = Signature made by { $fingerprint } is rejected by the signature policy: { $reason }
+verify-signature-too-old = Signature made by { $fingerprint } is { $age } days old, exceeding the limit of { $max } days.
+refresh-key-expiring = The key { $fingerprint } used to sign { $source } will expire on { $date }. Please obtain a new key from the repository maintainer before then.
+deb-not-signed = Package { $path } is not signed.
+deb-bad-signature = Signature of package { $path } is invalid: { $reason }
+deb-signature-verified = Package { $path } is signed by { $fingerprint }.
 inrelease-sha256-empty = No checksums value available in InRelease.
 inrelease-checksum-can-not-parse = InRelease file is invalid: Failed to parse file { $p }.
 inrelease-parse-unsupported-file-type = BUG: InRelease parser has encountered an unsupported file format. Please report this issue at https://github.com/AOSC-Dev/oma.
@@ -119,6 +178,7 @@ contents-may-not-be-accurate-1 = The local package contents database has not bee
 contents-may-not-be-accurate-2 = Use the `oma refresh' command to refresh the contents database.
 execute-ripgrep-failed = Failed to execute `rg'.
 searching = Searching ...
+no-appstream-metadata = No AppStream metadata found. Install the appstream package or set `appstream = true' in /etc/oma.toml, then run `oma refresh`.
 search-with-result-count = Searching, found { $count } results so far ...
 contents-entry-missing-path-list = BUG: oma failed to parse the entry { $entry } in the local package contents database. Please report this issue at https://github.com/AOSC-Dev/oma.
 rg-non-zero = `rg' returned an error.
@@ -236,3 +296,11 @@ tum-1 = oma has found { $updates } updates available for your system.
 tum-1-with-security =  oma has found { $updates } updates available for your system ({ $security } security updates are marked in { $security_str }).
 tum-2 = In order to fulfill the operations you specified, oma will need to perform the following changes to your system components. The details are as follows.Details of the component changes for this operation are as follows.
 security = security update(s)
+
+# sources
+sources-missing-components = Components must be specified for repository { $uri } (only flat repositories may omit them).
+sources-invalid-name = Invalid sources file name: { $name }.
+sources-no-match = No source entry matches { $uri }.
+sources-file-exists = { $path } already exists, refusing to overwrite it.
+sources-updated = Successfully updated sources configuration.
+sources-embedded-key = (embedded key)
@@ -41,6 +41,8 @@ packages-can-be-upgrade = 有 { $len } 个可升级的软件包
 packages-can-be-removed = 有 { $len } 个可删除的软件包
 comma = ，
 successfully-refresh-with-tips = 成功刷新本机软件包数据库。{ $s }
+refresh-report-title = 软件源变更：
+refresh-report-source = { $source }：新增 { $added } 个，移除 { $removed } 个，更新 { $updated } 个
 successfully-refresh = 成功刷新本机软件包数据库。系统各软件包均为最新。
 no-candidate-ver = 无法从软件包仓库中获取当前版本的软件包 { $pkg } 。
 pkg-is-not-installed = 无法标记软件包 { $pkg } 的属性，因为该软件包尚未安装。
@@ -95,13 +97,70 @@ colon = ：
 invalid-filename = 文件名 { $name } 无效。
 checksum-mismatch-retry = 文件 { $c } 完整性验证失败，正在重试第 { $retry } 次 ...
 can-not-get-source-next-url = 无法下载文件：{ $e }，将使用下一个镜像源重试 ...
+download-retry-wait = 下载失败：{ $e }，将在 { $secs } 秒后重试（第 { $retry } 次）...
 checksum-mismatch = 文件 { $filename } 完整性验证失败。
+download-too-slow = 文件 { $filename } 的下载速度在 { $secs } 秒内低于 { $limit }/s。
+failed-to-load-client-cert = 无法加载 TLS 客户端证书 { $path }。
+mirror-lists-unavailable = 无法读取镜像列表，可能无法从 mirror+ 源下载软件包：{ $reason }
+failed-to-open-progress-fd = 无法打开进度输出文件描述符。
 # db
 invalid-url = URL { $url } 无效。
 can-not-parse-date = BUG：无法将 Date 值转换为 RFC2822 格式，请于 https://github.com/AOSC-Dev/oma 报告问题。
 can-not-parse-valid-until = BUG：无法将 Valid-Until 值转换为 RFC2822 格式，请于 https://github.com/AOSC-Dev/oma 报告问题。
 earlier-signature = InRelease 文件 { $filename } 无效：系统时间早于内联签名时间戳。
 expired-signature = InRelease 文件 { $filename } 无效：内联签名已过期。
+release-rollback = Release 文件 { $filename } 比上次接受的版本更旧（日期为 { $date }，上次接受的日期为 { $last }）。镜像源可能已过期或遭到攻击。如确需降级，请使用 `oma refresh --allow-release-rollback`。
+refresh-no-matching-source = 没有与 { $source } 匹配的软件源。
+refresh-invalid-snapshot = 无效的快照时间：{ $time }。请使用类似 2026-09-01T00:00Z 的格式。
+refresh-using-snapshot = 正在使用 { $time } 的软件源快照，运行 `oma refresh --no-snapshot` 以恢复使用最新的软件源。
+refresh-snapshot-not-applied = { $time } 的软件源快照仅用于 `oma refresh`，本次将从最新的软件源刷新。
+refresh-verifying = 正在校验本地软件源元数据 ...
+refresh-verify-ok = 全部 { $count } 个软件源元数据文件均完好。
+refresh-verify-bad-signature = 无法验证 { $path } 的签名：{ $reason }
+refresh-verify-mismatch = { $path } 与其 Release 文件中的校验和不符。
+refresh-verify-missing = { $path } 在 Release 文件中列出但不存在。
+refresh-verify-failed = { $count } 个软件源元数据文件未通过校验。请运行 `oma refresh --verify-only --remove-broken` 删除这些文件，再运行 `oma refresh` 重新下载。
+refresh-verify-removed = 已删除 { $count } 个损坏的软件源元数据文件。请运行 `oma refresh` 重新下载。
+repo-build-done = 已在 { $path } 生成包含 { $count } 个软件包的软件源（{ $archs }）。
+repo-build-signed = 软件源已签名，请将公钥添加到 /etc/apt/keyrings 中并使用：{ $line }
+repo-build-unsigned = 软件源未签名，请使用：{ $line }
+repo-no-package = 未在 { $path } 中找到 .deb 软件包。
+repo-bad-deb = 无法读取软件包 { $path }。
+repo-sign-failed = 无法签名 Release 文件。
+keys-not-found = 没有与 { $key } 匹配的受信任密钥。
+keys-no-key-in-file = 未在 { $path } 中找到 OpenPGP 密钥。
+keys-file-exists = { $path } 已存在，请使用 --name 指定其他文件名。
+keys-invalid-name = 无效的密钥文件名 { $name }，文件名不能以点开头或包含斜杠。
+keys-added = 已将密钥 { $fingerprint } 导入到 { $path }。
+keys-added-tips = 如需仅为特定软件源信任此密钥，请运行 `oma sources set <URI> --signed-by { $path }`。
+keys-not-system = { $path } 不由 oma 管理，请手动从中删除该密钥。
+keys-still-used = 密钥 { $fingerprint } 仍被以下软件源使用：{ $sources }。删除后这些软件源将无法通过验证。
+keys-still-used-skipped = 未删除密钥 { $fingerprint }，如仍要删除请使用 --force。
+keys-removed = 已从 { $path } 中删除密钥 { $fingerprint }。
+keys-expired = 密钥 { $fingerprint } 已过期。
+keys-expiring = 密钥 { $fingerprint } 将于 { $date } 过期。
+keys-fingerprint = 指纹
+keys-user-id = 用户 ID
+keys-created = 创建时间
+keys-expires = 过期时间
+keys-status = 状态
+keys-file = 文件
+keys-trusted-by-default = 默认信任
+keys-used-by = 使用该密钥的源
+keys-yes = 是
+keys-no = 否
+keys-never = 永不过期
+keys-status-valid = 有效
+keys-status-expired = 已过期
+keys-status-revoked = 已吊销
+keys-status-invalid = 无效
+verify-bad-key = 签名密钥 { $fingerprint } 不符合签名策略：{ $reason }
+verify-bad-signature = { $fingerprint } 的签名不符合签名策略：{ $reason }
+verify-signature-too-old = { $fingerprint } 的签名已有 { $age } 天，超过了 { $max } 天的限制。
+refresh-key-expiring = 用于签名 { $source } 的密钥 { $fingerprint } 将于 { $date } 过期，请在此之前向软件源维护者获取新密钥。
+deb-not-signed = 软件包 { $path } 未签名。
+deb-bad-signature = 软件包 { $path } 的签名无效：{ $reason }
+deb-signature-verified = 软件包 { $path } 已由 { $fingerprint } 签名。
 inrelease-sha256-empty = InRelease 中未找到校验和。
 inrelease-checksum-can-not-parse = InRelease 文件无效：无法解析文件：{ $p }。
 inrelease-parse-unsupported-file-type = BUG：解析器不支持该 InRelease 文件的格式，请于 https://github.com/AOSC-Dev/oma 报告问题。
@@ -116,6 +175,7 @@ contents-may-not-be-accurate-1 = 本机软件包内容数据库文件已超过
 contents-may-not-be-accurate-2 = 请使用 `oma refresh' 命令刷新该数据库。
 execute-ripgrep-failed = 无法执行 `rg' 命令。
 searching = 正在搜索 ...
+no-appstream-metadata = 未找到 AppStream 元数据。请安装 appstream 软件包或在 /etc/oma.toml 中设置 `appstream = true'，然后运行 `oma refresh`。
 search-with-result-count = 正在搜索，已找到 { $count } 个结果 ...
 contents-entry-missing-path-list = BUG：oma 无法解析本机软件包内容数据库中的条目 { $entry }，请于 https://github.com/AOSC-Dev/oma 报告问题。
 rg-non-zero = `rg' 报错退出。
@@ -230,3 +290,11 @@ tum-1 = oma 找到了 { $updates } 个可用系统更新。
 tum-1-with-security = oma 找到了 { $updates } 个关键系统更新（含 { $security } 个 { $security_str })。
 tum-2 = 根据您指定的操作，oma 还需要对系统组件执行若干变更，变更详情如下。
 security = 安全更新
+
+# sources
+sources-missing-components = 必须为源 { $uri } 指定组件（仅平铺源可以省略）。
+sources-invalid-name = 软件源文件名无效：{ $name }。
+sources-no-match = 未找到与 { $uri } 匹配的软件源。
+sources-file-exists = { $path } 已存在，拒绝覆盖。
+sources-updated = 已成功更新软件源配置。
+sources-embedded-key = （内嵌公钥）
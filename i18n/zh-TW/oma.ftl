@@ -41,6 +41,8 @@ packages-can-be-upgrade = 有 { $len } 個可升級的軟體套件
 packages-can-be-removed = 有 { $len } 個可移除的軟體套件
 comma = ，
 successfully-refresh-with-tips = 成功重新整理本機軟體套件資料庫。{ $s }
+refresh-report-title = 軟體源變更：
+refresh-report-source = { $source }：新增 { $added } 個，移除 { $removed } 個，更新 { $updated } 個
 successfully-refresh = 成功重新整理本機軟體套件資料庫。系統各軟體套件均為最新。
 no-candidate-ver = 無法從軟體套件資料庫中取得目前版本的軟體套件 { $pkg } 。
 pkg-is-not-installed = 無法標記軟體套件 { $pkg } 的屬性，因為該軟體套件尚未安裝。
@@ -94,13 +96,70 @@ colon = ：
 invalid-filename = 檔案名 { $name } 無效。
 checksum-mismatch-retry = 檔案 { $c } 完整性驗證失敗，正在重試第 { $retry } 次……
 can-not-get-source-next-url = 無法下載檔案：{ $e }，將使用下一個鏡像源重試……
+download-retry-wait = 下載失敗：{ $e }，將在 { $secs } 秒後重試（第 { $retry } 次）……
 checksum-mismatch = 檔案 { $filename } 完整性驗證失敗。
+download-too-slow = 檔案 { $filename } 的下載速度在 { $secs } 秒內低於 { $limit }/s。
+failed-to-load-client-cert = 無法載入 TLS 用戶端憑證 { $path }。
+mirror-lists-unavailable = 無法讀取鏡像列表，可能無法從 mirror+ 源下載軟體包：{ $reason }
+failed-to-open-progress-fd = 無法開啟進度輸出檔案描述元。
 # db
 invalid-url = URL { $url } 無效。
 can-not-parse-date = BUG：無法將 Date 值轉換為 RFC2822 格式，請於 https://github.com/AOSC-Dev/oma 報告問題。
 can-not-parse-valid-until = BUG：無法將 Valid-Until 值轉換為 RFC2822 格式，請於 https://github.com/AOSC-Dev/oma 報告問題。
 earlier-signature = InRelease 檔案 { $filename } 無效：系統時間早於內附簽章時間戳。
 expired-signature = InRelease 檔案 { $filename } 無效：內附簽章已過期。
+release-rollback = Release 檔案 { $filename } 比上次接受的版本更舊（日期為 { $date }，上次接受的日期為 { $last }）。鏡像源可能已過期或遭到攻擊。如確需降級，請使用 `oma refresh --allow-release-rollback`。
+refresh-no-matching-source = 沒有與 { $source } 相符的軟體源。
+refresh-invalid-snapshot = 無效的快照時間：{ $time }。請使用類似 2026-09-01T00:00Z 的格式。
+refresh-using-snapshot = 正在使用 { $time } 的軟體源快照，執行 `oma refresh --no-snapshot` 以恢復使用最新的軟體源。
+refresh-snapshot-not-applied = { $time } 的軟體源快照僅用於 `oma refresh`，本次將從最新的軟體源重新整理。
+refresh-verifying = 正在校驗本機軟體源中繼資料……
+refresh-verify-ok = 全部 { $count } 個軟體源中繼資料檔案均完好。
+refresh-verify-bad-signature = 無法驗證 { $path } 的簽章：{ $reason }
+refresh-verify-mismatch = { $path } 與其 Release 檔案中的校驗和不符。
+refresh-verify-missing = { $path } 在 Release 檔案中列出但不存在。
+refresh-verify-failed = { $count } 個軟體源中繼資料檔案未通過校驗。請執行 `oma refresh --verify-only --remove-broken` 刪除這些檔案，再執行 `oma refresh` 重新下載。
+refresh-verify-removed = 已刪除 { $count } 個損壞的軟體源中繼資料檔案。請執行 `oma refresh` 重新下載。
+repo-build-done = 已在 { $path } 產生包含 { $count } 個軟體包的軟體源（{ $archs }）。
+repo-build-signed = 軟體源已簽署，請將公鑰新增到 /etc/apt/keyrings 中並使用：{ $line }
+repo-build-unsigned = 軟體源未簽署，請使用：{ $line }
+repo-no-package = 未在 { $path } 中找到 .deb 軟體包。
+repo-bad-deb = 無法讀取軟體包 { $path }。
+repo-sign-failed = 無法簽署 Release 檔案。
+keys-not-found = 沒有與 { $key } 相符的受信任金鑰。
+keys-no-key-in-file = 未在 { $path } 中找到 OpenPGP 金鑰。
+keys-file-exists = { $path } 已存在，請使用 --name 指定其他檔案名稱。
+keys-invalid-name = 無效的金鑰檔案名稱 { $name }，檔案名稱不能以點開頭或包含斜線。
+keys-added = 已將金鑰 { $fingerprint } 匯入到 { $path }。
+keys-added-tips = 如需僅為特定軟體源信任此金鑰，請執行 `oma sources set <URI> --signed-by { $path }`。
+keys-not-system = { $path } 不由 oma 管理，請手動從中刪除該金鑰。
+keys-still-used = 金鑰 { $fingerprint } 仍被以下軟體源使用：{ $sources }。刪除後這些軟體源將無法通過驗證。
+keys-still-used-skipped = 未刪除金鑰 { $fingerprint }，如仍要刪除請使用 --force。
+keys-removed = 已從 { $path } 中刪除金鑰 { $fingerprint }。
+keys-expired = 金鑰 { $fingerprint } 已過期。
+keys-expiring = 金鑰 { $fingerprint } 將於 { $date } 過期。
+keys-fingerprint = 指紋
+keys-user-id = 使用者 ID
+keys-created = 建立時間
+keys-expires = 過期時間
+keys-status = 狀態
+keys-file = 檔案
+keys-trusted-by-default = 預設信任
+keys-used-by = 使用該金鑰的源
+keys-yes = 是
+keys-no = 否
+keys-never = 永不過期
+keys-status-valid = 有效
+keys-status-expired = 已過期
+keys-status-revoked = 已撤銷
+keys-status-invalid = 無效
+verify-bad-key = 簽署金鑰 { $fingerprint } 不符合簽章原則：{ $reason }
+verify-bad-signature = { $fingerprint } 的簽章不符合簽章原則：{ $reason }
+verify-signature-too-old = { $fingerprint } 的簽章已有 { $age } 天，超過了 { $max } 天的限制。
+refresh-key-expiring = 用於簽署 { $source } 的金鑰 { $fingerprint } 將於 { $date } 過期，請在此之前向軟體源維護者取得新金鑰。
+deb-not-signed = 軟體包 { $path } 未簽署。
+deb-bad-signature = 軟體包 { $path } 的簽章無效：{ $reason }
+deb-signature-verified = 軟體包 { $path } 已由 { $fingerprint } 簽署。
 inrelease-sha256-empty = InRelease 中未找到雜湊值。
 inrelease-checksum-can-not-parse = InRelease 檔案無效：無法解析檔案 { $p }。
 inrelease-parse-unsupported-file-type = BUG：解析器不支援該 InRelease 檔案的格式，請於 https://github.com/AOSC-Dev/oma 報告問題。
@@ -115,6 +174,7 @@ contents-may-not-be-accurate-1 = 本機軟體套件內容資料庫檔案已超
 contents-may-not-be-accurate-2 = 請使用 `oma refresh' 指令重新整理該資料庫。
 execute-ripgrep-failed = 無法執行 `rg' 指令。
 searching = 正在搜尋……
+no-appstream-metadata = 找不到 AppStream 中繼資料。請安裝 appstream 套件或在 /etc/oma.toml 中設定 `appstream = true'，然後執行 `oma refresh`。
 search-with-result-count = 正在搜尋，已找到 { $count } 個結果……
 contents-entry-missing-path-list = BUG：oma 無法解析本機軟體套件內容資料庫中的條目 { $entry }，請於 https://github.com/AOSC-Dev/oma 報告問題。
 rg-non-zero = `rg' 報錯結束。
@@ -226,3 +286,11 @@ tui-continue-tips = 請按 [c] 繼續。
 dpkg-triggers-only-a-non-zero = `dpkg --triggers-only -a` 返回了錯誤。
 success = 操作已成功完成。
 topics-unchanged = oma 在退出測試庫時遇到錯誤，因此未儲存測試庫設定狀態。
+
+# sources
+sources-missing-components = 必須為源 { $uri } 指定元件（僅平鋪源可以省略）。
+sources-invalid-name = 軟體源檔案名稱無效：{ $name }。
+sources-no-match = 未找到與 { $uri } 相符的軟體源。
+sources-file-exists = { $path } 已存在，拒絕覆寫。
+sources-updated = 已成功更新軟體源設定。
+sources-embedded-key = （內嵌公鑰）
@@ -0,0 +1,87 @@
+use reqwest::{Client, RequestBuilder};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum HttpAuth {
+    Basic { user: Box<str>, password: Box<str> },
+    Bearer(Box<str>),
+}
+
+impl HttpAuth {
+    pub fn apply(&self, req: RequestBuilder) -> RequestBuilder {
+        match self {
+            HttpAuth::Basic { user, password } => req.basic_auth(user, Some(password)),
+            HttpAuth::Bearer(token) => req.bearer_auth(token),
+        }
+    }
+}
+
+/// Clients carrying a TLS client certificate, used for urls starting with their prefix
+#[derive(Debug, Clone, Default)]
+pub struct TlsClients {
+    inner: Vec<(Box<str>, Client)>,
+}
+
+impl TlsClients {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `prefix` is url without scheme, like `repo.example.com/debian`
+    pub fn insert(&mut self, prefix: impl Into<Box<str>>, client: Client) {
+        let prefix: Box<str> = prefix.into();
+        self.inner
+            .push((prefix.trim_end_matches('/').into(), client));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Client of `url`, the longest matching prefix wins
+    pub fn find(&self, url: &str) -> Option<&Client> {
+        let url = url
+            .strip_prefix("http://")
+            .or_else(|| url.strip_prefix("https://"))
+            .unwrap_or(url);
+
+        self.inner
+            .iter()
+            .filter(|(prefix, _)| {
+                url.strip_prefix(prefix.as_ref())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, client)| client)
+    }
+}
+
+#[test]
+fn test_tls_clients_find() {
+    let mut clients = TlsClients::new();
+    clients.insert("secure.example.com/debian", Client::new());
+
+    assert!(clients
+        .find("https://secure.example.com/debian/pool/f/fish.deb")
+        .is_some());
+    assert!(clients.find("https://deb.debian.org/debian").is_none());
+    assert!(clients
+        .find("https://secure.example.com/debian-security/dists")
+        .is_none());
+
+    clients.insert("secure.example.com/", Client::new());
+    clients.insert("secure.example.com/debian/private/", Client::new());
+
+    let found = |url| clients.find(url).map(|x| x as *const Client);
+    assert_eq!(
+        found("https://secure.example.com/debian/private/pool/f/fish.deb"),
+        Some(&clients.inner[2].1 as *const Client)
+    );
+    assert_eq!(
+        found("https://secure.example.com/debian/dists/stable/InRelease"),
+        Some(&clients.inner[0].1 as *const Client)
+    );
+    assert_eq!(
+        found("https://secure.example.com/debian-security/dists"),
+        Some(&clients.inner[1].1 as *const Client)
+    );
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use reqwest::Url;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, SemaphorePermit};
+use tracing::debug;
+
+/// Minimum time between two concurrency adjustments
+const SAMPLE_WINDOW: Duration = Duration::from_secs(2);
+
+/// Limit how many connections are opened to the same host
+pub(crate) struct HostLimiter {
+    max_per_host: usize,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl HostLimiter {
+    pub fn new(max_per_host: usize) -> Self {
+        Self {
+            max_per_host: max_per_host.max(1),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn acquire(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        let host = Url::parse(url).ok()?.host_str()?.to_string();
+
+        let sem = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_per_host)))
+            .clone();
+
+        sem.acquire_owned().await.ok()
+    }
+}
+
+/// Adjust the number of concurrent downloads by observed throughput and errors
+pub(crate) struct AdaptiveConcurrency {
+    sem: Semaphore,
+    limit: AtomicUsize,
+    // 降低并发时无法收回已发出的许可，待其归还时再丢弃
+    debt: AtomicUsize,
+    max: usize,
+    sample: Mutex<Sample>,
+}
+
+struct Sample {
+    time: Instant,
+    bytes: u64,
+    speed: f64,
+}
+
+impl AdaptiveConcurrency {
+    pub fn new(init: usize, max: usize) -> Self {
+        let max = max.max(1);
+        let init = init.clamp(1, max);
+
+        Self {
+            sem: Semaphore::new(init),
+            limit: AtomicUsize::new(init),
+            debt: AtomicUsize::new(0),
+            max,
+            sample: Mutex::new(Sample {
+                time: Instant::now(),
+                bytes: 0,
+                speed: 0.0,
+            }),
+        }
+    }
+
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.sem.acquire().await.unwrap()
+    }
+
+    /// Return a permit and record result of the finished download
+    pub fn release(&self, permit: SemaphorePermit<'_>, ok: bool, downloaded: u64) {
+        let pay_debt = self
+            .debt
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| x.checked_sub(1))
+            .is_ok();
+
+        if pay_debt {
+            permit.forget();
+        } else {
+            drop(permit);
+        }
+
+        if !ok {
+            // 出错时减半，尽快减轻镜像负担
+            let limit = self.limit.load(Ordering::SeqCst);
+            self.decrease(limit - (limit / 2).max(1));
+            return;
+        }
+
+        let mut sample = self.sample.lock().unwrap();
+        let elapsed = sample.time.elapsed();
+
+        if elapsed < SAMPLE_WINDOW {
+            return;
+        }
+
+        let speed = downloaded.saturating_sub(sample.bytes) as f64 / elapsed.as_secs_f64();
+
+        if speed > sample.speed * 1.1 {
+            self.increase();
+        } else if speed < sample.speed * 0.8 {
+            self.decrease(1);
+        }
+
+        *sample = Sample {
+            time: Instant::now(),
+            bytes: downloaded,
+            speed,
+        };
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::SeqCst)
+    }
+
+    fn increase(&self) {
+        let res = self
+            .limit
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| {
+                (x < self.max).then_some(x + 1)
+            });
+
+        if res.is_ok() {
+            // 先抵消尚未偿还的许可，否则新增一个许可
+            let paid = self
+                .debt
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| x.checked_sub(1))
+                .is_ok();
+
+            if !paid {
+                self.sem.add_permits(1);
+            }
+
+            debug!("Download concurrency increased to {}", self.limit());
+        }
+    }
+
+    fn decrease(&self, n: usize) {
+        let res = self
+            .limit
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| {
+                let new = x.saturating_sub(n).max(1);
+                (new != x).then_some(new)
+            });
+
+        if let Ok(old) = res {
+            let n = old - self.limit();
+
+            // 优先收回空闲的许可
+            let mut rest = n;
+            while rest > 0 {
+                match self.sem.try_acquire() {
+                    Ok(permit) => {
+                        permit.forget();
+                        rest -= 1;
+                    }
+                    Err(_) => break,
+                }
+            }
+
+            self.debt.fetch_add(rest, Ordering::SeqCst);
+
+            debug!("Download concurrency decreased to {}", self.limit());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AdaptiveConcurrency;
+
+    #[tokio::test]
+    async fn test_adaptive_concurrency() {
+        let c = AdaptiveConcurrency::new(4, 8);
+
+        let a = c.acquire().await;
+        let b = c.acquire().await;
+
+        // 出错后并发减半，空闲的许可直接收回
+        c.release(a, false, 0);
+        assert_eq!(c.limit(), 2);
+        assert_eq!(c.sem.available_permits(), 1);
+
+        c.release(b, false, 0);
+        assert_eq!(c.limit(), 1);
+        assert_eq!(c.sem.available_permits(), 1);
+
+        c.increase();
+        assert_eq!(c.limit(), 2);
+        assert_eq!(c.sem.available_permits(), 2);
+    }
+}
@@ -1,4 +1,11 @@
-use crate::{CompressFile, DownloadSource, Event};
+use crate::{
+    auth::{HttpAuth, TlsClients},
+    concurrency::{AdaptiveConcurrency, HostLimiter},
+    journal::DownloadJournal,
+    limiter::SpeedMonitor,
+    retry::{retry_after, RetryPolicy},
+    CompressFile, DownloadSource, Event, LowSpeedLimit, RateLimiter,
+};
 use std::{
     fs::Permissions,
     future::Future,
@@ -11,16 +18,17 @@ use std::{
 
 use async_compression::futures::bufread::{BzDecoder, GzipDecoder, XzDecoder, ZstdDecoder};
 use bon::Builder;
-use futures::{io::BufReader, AsyncRead, TryStreamExt};
+use futures::{future::try_join_all, io::BufReader, AsyncRead, StreamExt, TryStreamExt};
 use oma_utils::url_no_escape::url_no_escape;
 use reqwest::{
-    header::{HeaderValue, ACCEPT_RANGES, CONTENT_LENGTH, RANGE},
-    Client, Method, RequestBuilder,
+    header::{HeaderValue, ACCEPT_RANGES, CONTENT_LENGTH, IF_RANGE, RANGE},
+    Client, Method, RequestBuilder, StatusCode,
 };
 use tokio::{
     fs::{self, File},
     io::{AsyncReadExt as _, AsyncSeekExt, AsyncWriteExt},
-    time::timeout,
+    sync::{OwnedSemaphorePermit, SemaphorePermit},
+    time::{sleep, timeout},
 };
 
 use tokio_util::compat::{FuturesAsyncReadCompatExt, TokioAsyncReadCompatExt};
@@ -33,12 +41,26 @@ pub(crate) struct SingleDownloader<'a> {
     client: &'a Client,
     pub entry: &'a DownloadEntry,
     progress: (usize, usize),
-    retry_times: usize,
+    retry_policy: &'a RetryPolicy,
     msg: Option<String>,
     download_list_index: usize,
     file_type: CompressFile,
     set_permission: Option<u32>,
     timeout: Duration,
+    segments: usize,
+    segment_min_size: u64,
+    rate_limiter: Option<&'a RateLimiter>,
+    low_speed_limit: Option<LowSpeedLimit>,
+    host_limiter: &'a HostLimiter,
+    concurrency: &'a AdaptiveConcurrency,
+    adaptive: bool,
+    tls_clients: Option<&'a TlsClients>,
+}
+
+/// Permits held by a single connection
+struct Connection<'a> {
+    _host: Option<OwnedSemaphorePermit>,
+    permit: SemaphorePermit<'a>,
 }
 
 impl<'a> SingleDownloader<'a> {
@@ -63,8 +85,13 @@ impl<'a> SingleDownloader<'a> {
                         .await
                 }
                 DownloadSourceType::Local(as_symlink) => {
-                    self.download_local(global_progress, c, *as_symlink, callback)
-                        .await
+                    let conn = self.connect(None).await;
+                    let res = self
+                        .download_local(global_progress, c, *as_symlink, callback)
+                        .await;
+                    self.disconnect(conn, res.is_ok(), global_progress);
+
+                    res
                 }
             };
 
@@ -101,7 +128,7 @@ impl<'a> SingleDownloader<'a> {
         &self,
         global_progress: &AtomicU64,
         source: &DownloadSource,
-        auth: &Option<(Box<str>, Box<str>)>,
+        auth: &Option<HttpAuth>,
         callback: &F,
     ) -> DownloadResult<Summary>
     where
@@ -111,36 +138,62 @@ impl<'a> SingleDownloader<'a> {
         let mut times = 1;
         let mut allow_resume = self.entry.allow_resume;
         loop {
-            match self
-                .http_download(global_progress, allow_resume, source, auth, callback)
-                .await
-            {
-                Ok(s) => {
-                    return Ok(s);
+            let mut conn = Some(self.connect(Some(&source.url)).await);
+
+            let res = self
+                .http_download(
+                    global_progress,
+                    allow_resume,
+                    source,
+                    auth,
+                    &mut conn,
+                    callback,
+                )
+                .await;
+
+            if let Some(conn) = conn {
+                self.disconnect(conn, res.is_ok(), global_progress);
+            }
+
+            let e = match res {
+                Ok(s) => return Ok(s),
+                Err(e) => e,
+            };
+
+            // 续传得到的文件校验失败，可能是本地的部分文件有误，不续传再试一次
+            // 否则 checksum 不匹配说明镜像上的文件本身有问题，直接换下一个镜像
+            if let DownloadError::ChecksumMismatch(ref filename) = e {
+                if !allow_resume {
+                    return Err(e);
                 }
-                Err(e) => match e {
-                    DownloadError::ChecksumMismatch(ref filename) => {
-                        if self.retry_times == times {
-                            return Err(e);
-                        }
-
-                        if times > 1 {
-                            callback(Event::ChecksumMismatch {
-                                index: self.download_list_index,
-                                filename: filename.into(),
-                                times,
-                            })
-                            .await;
-                        }
-
-                        times += 1;
-                        allow_resume = false;
-                    }
-                    e => {
-                        return Err(e);
-                    }
-                },
+
+                callback(Event::ChecksumMismatch {
+                    index: self.download_list_index,
+                    filename: filename.into(),
+                    times,
+                })
+                .await;
+
+                allow_resume = false;
+                continue;
             }
+
+            let Some(wait) = self.retry_policy.delay(times, &e) else {
+                return Err(e);
+            };
+
+            debug!("{} download failed: {e}, retry after {wait:?}", source.url);
+
+            callback(Event::RetryWait {
+                index: self.download_list_index,
+                err: e.to_string(),
+                times,
+                wait,
+            })
+            .await;
+
+            sleep(wait).await;
+            times += 1;
         }
     }
 
@@ -149,7 +202,8 @@ impl<'a> SingleDownloader<'a> {
         global_progress: &AtomicU64,
         allow_resume: bool,
         source: &DownloadSource,
-        auth: &Option<(Box<str>, Box<str>)>,
+        auth: &Option<HttpAuth>,
+        conn: &mut Option<Connection<'a>>,
         callback: &F,
     ) -> DownloadResult<Summary>
     where
@@ -221,6 +275,7 @@ impl<'a> SingleDownloader<'a> {
                         self.entry.filename
                     );
 
+                    DownloadJournal::remove(&file).await;
                     callback(Event::ProgressDone(self.download_list_index)).await;
 
                     return Ok(Summary {
@@ -256,7 +311,7 @@ impl<'a> SingleDownloader<'a> {
         })
         .await;
 
-        let req = self.build_request_with_basic_auth(&source.url, Method::HEAD, auth);
+        let req = self.build_request_with_auth(&source.url, Method::HEAD, auth);
 
         let resp_head = match timeout(self.timeout, req.send()).await {
             Ok(Ok(resp)) => resp,
@@ -278,7 +333,7 @@ impl<'a> SingleDownloader<'a> {
         // 看看头是否有 ACCEPT_RANGES 这个变量
         // 如果有，而且值不为 none，则可以断点续传
         // 反之，则不能断点续传
-        let mut can_resume = match head.get(ACCEPT_RANGES) {
+        let can_resume = match head.get(ACCEPT_RANGES) {
             Some(x) if x == "none" => false,
             Some(_) => true,
             None => false,
@@ -302,39 +357,103 @@ impl<'a> SingleDownloader<'a> {
 
         debug!("File total size is: {total_size}");
 
-        let mut req = self.build_request_with_basic_auth(&source.url, Method::GET, auth);
+        // 只有日志记录的 URL、校验器及 checksum 与服务器一致时才续传
+        // 否则已下载的部分可能属于另一个版本的文件
+        let remote = DownloadJournal::new(&source.url, head, self.entry.hash.as_ref());
+        let journal = if dest.is_some() {
+            DownloadJournal::load(&file).await
+        } else {
+            None
+        };
 
-        if can_resume && allow_resume {
-            // 如果已存在的文件大小大于或等于要下载的文件，则重置文件大小，重新下载
-            // 因为已经走过一次 chekcusm 了，函数走到这里，则说明肯定文件完整性不对
-            if total_size <= file_size {
-                debug!("Exist file size is reset to 0, because total size <= exist file size");
-                let gp = global_progress.load(Ordering::SeqCst);
-                global_progress.store(gp.saturating_sub(file_size), Ordering::SeqCst);
-                callback(Event::GlobalProgressSet(
-                    global_progress.load(Ordering::SeqCst),
-                ))
-                .await;
-                file_size = 0;
-                can_resume = false;
+        let mut resume = can_resume
+            && allow_resume
+            && self.file_type == CompressFile::Nothing
+            && journal.as_ref().is_some_and(|x| x.can_resume_with(&remote));
+
+        // 如果已存在的文件大小大于或等于要下载的文件，则重置文件大小，重新下载
+        // 因为已经走过一次 chekcusm 了，函数走到这里，则说明肯定文件完整性不对
+        if resume && total_size <= file_size {
+            debug!("Exist file size is reset to 0, because total size <= exist file size");
+            resume = false;
+        }
+
+        debug!("Resume from journal? {resume}");
+
+        if !resume && dest.is_some() {
+            global_progress.fetch_sub(file_size, Ordering::SeqCst);
+            callback(Event::GlobalProgressSet(
+                global_progress.load(Ordering::SeqCst),
+            ))
+            .await;
+            dest = None;
+            validator = None;
+            file_size = 0;
+        }
+
+        // 文件足够大且服务器支持 Range 请求，则将文件切分为多段并发下载
+        // 但若本地存在可续传的部分文件，则仍然走单连接续传
+        if !resume && self.can_segment(can_resume && resp_head.status().is_success(), total_size) {
+            DownloadJournal::remove(&file).await;
+            callback(Event::ProgressDone(self.download_list_index)).await;
+
+            // 各分段自行获取连接许可
+            if let Some(conn) = conn.take() {
+                self.disconnect(conn, true, global_progress);
             }
 
+            return self
+                .segmented_download(global_progress, source, total_size, msg, callback)
+                .await;
+        }
+
+        let mut req = self.build_request_with_auth(&source.url, Method::GET, auth);
+
+        if resume {
             // 发送 RANGE 的头，传入的是已经下载的文件的大小
             debug!("oma will set header range as bytes={file_size}-");
             req = req.header(RANGE, format!("bytes={}-", file_size));
-        }
 
-        debug!("Can resume? {can_resume}");
+            // 若服务器上的文件在 HEAD 之后又发生了变化，则服务器会返回整个文件
+            if let Some(v) = journal.as_ref().and_then(|x| x.if_range()) {
+                req = req.header(IF_RANGE, v);
+            }
+        }
 
         let resp = req.send().await.map_err(DownloadError::ReqwestError)?;
 
         if let Err(e) = resp.error_for_status_ref() {
             callback(Event::ProgressDone(self.download_list_index)).await;
-            return Err(DownloadError::ReqwestError(e));
+
+            return Err(match retry_after(&resp) {
+                Some(retry_after) => DownloadError::RateLimited {
+                    source: e,
+                    retry_after,
+                },
+                None => DownloadError::ReqwestError(e),
+            });
         } else {
             callback(Event::ProgressDone(self.download_list_index)).await;
         }
 
+        if resume && resp.status() != StatusCode::PARTIAL_CONTENT {
+            debug!(
+                "Server returned {} instead of partial content, will download {} from start",
+                resp.status(),
+                self.entry.filename
+            );
+
+            global_progress.fetch_sub(file_size, Ordering::SeqCst);
+            callback(Event::GlobalProgressSet(
+                global_progress.load(Ordering::SeqCst),
+            ))
+            .await;
+            resume = false;
+            dest = None;
+            validator = None;
+            file_size = 0;
+        }
+
         callback(Event::NewProgressBar {
             index: self.download_list_index,
             msg,
@@ -360,76 +479,84 @@ impl<'a> SingleDownloader<'a> {
         };
 
         let mut self_progress = 0;
-        let mut dest = if !can_resume || !allow_resume {
-            // 如果不能 resume，则使用创建模式
-            debug!(
-                "oma will open file: {} as create mode.",
-                self.entry.filename
-            );
-
-            let f = match File::create(&file).await {
-                Ok(f) => f,
-                Err(e) => {
-                    callback(Event::ProgressDone(self.download_list_index)).await;
-                    return Err(DownloadError::IOError(self.entry.filename.to_string(), e));
-                }
-            };
+        let mut dest = match dest {
+            Some(dest) if resume => {
+                debug!(
+                    "oma will re use opened dest file for {}",
+                    self.entry.filename
+                );
+                self_progress += file_size;
 
-            if let Err(e) = f.set_len(0).await {
-                callback(Event::ProgressDone(self.download_list_index)).await;
-                return Err(DownloadError::IOError(self.entry.filename.to_string(), e));
+                dest
             }
+            _ => {
+                debug!(
+                    "oma will open file: {} as create mode.",
+                    self.entry.filename
+                );
 
-            self.set_permission(&f).await?;
-
-            f
-        } else if let Some(dest) = dest {
-            debug!(
-                "oma will re use opened dest file for {}",
-                self.entry.filename
-            );
-            self_progress += file_size;
-
-            dest
-        } else {
-            debug!(
-                "oma will open file: {} as create mode.",
-                self.entry.filename
-            );
+                let f = match File::create(&file).await {
+                    Ok(f) => f,
+                    Err(e) => {
+                        callback(Event::ProgressDone(self.download_list_index)).await;
+                        return Err(DownloadError::IOError(self.entry.filename.to_string(), e));
+                    }
+                };
 
-            let f = match File::create(&file).await {
-                Ok(f) => f,
-                Err(e) => {
+                if let Err(e) = f.set_len(0).await {
                     callback(Event::ProgressDone(self.download_list_index)).await;
                     return Err(DownloadError::IOError(self.entry.filename.to_string(), e));
                 }
-            };
-
-            if let Err(e) = f.set_len(0).await {
-                callback(Event::ProgressDone(self.download_list_index)).await;
-                return Err(DownloadError::IOError(self.entry.filename.to_string(), e));
-            }
 
-            self.set_permission(&f).await?;
+                self.set_permission(&f).await?;
 
-            f
+                f
+            }
         };
 
-        if can_resume && allow_resume {
+        if resume {
             // 把文件指针移动到末尾
             debug!("oma will seek file: {} to end", self.entry.filename);
             if let Err(e) = dest.seek(SeekFrom::End(0)).await {
                 callback(Event::ProgressDone(self.download_list_index)).await;
                 return Err(DownloadError::IOError(self.entry.filename.to_string(), e));
             }
+        } else if can_resume
+            && allow_resume
+            && self.entry.hash.is_some()
+            && self.file_type == CompressFile::Nothing
+        {
+            // 记录此部分文件对应的远端文件，供中断后的下一次运行续传
+            if let Err(e) = remote.save(&file).await {
+                debug!("Failed to write download journal: {e}");
+            }
         }
+
         // 下载！
         debug!("Start download!");
 
-        let bytes_stream = source
-            .bytes_stream()
-            .map_err(|e| io::Error::new(ErrorKind::Other, e))
-            .into_async_read();
+        let rate_limiter = self.rate_limiter;
+        let received = AtomicU64::new(0);
+        let throttled = AtomicU64::new(0);
+        let throttled_ref = &throttled;
+        let bytes_stream = Box::pin(
+            source
+                .bytes_stream()
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))
+                .inspect_ok(|chunk| {
+                    received.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                })
+                .and_then(|chunk| async move {
+                    // 限速作用于网络上传输的字节，而非解压后的字节
+                    if let Some(limiter) = rate_limiter {
+                        let wait = limiter.acquire(chunk.len() as u64).await;
+                        throttled_ref.fetch_add(wait.as_nanos() as u64, Ordering::Relaxed);
+                    }
+
+                    Ok(chunk)
+                }),
+        )
+        .into_async_read();
 
         let reader: &mut (dyn AsyncRead + Unpin + Send) = match self.file_type {
             CompressFile::Xz => &mut XzDecoder::new(BufReader::new(bytes_stream)),
@@ -442,6 +569,7 @@ impl<'a> SingleDownloader<'a> {
         let mut reader = reader.compat();
 
         let mut buf = vec![0u8; 8 * 1024];
+        let mut monitor = self.low_speed_limit.map(SpeedMonitor::new);
 
         loop {
             let size = match timeout(self.timeout, reader.read(&mut buf[..])).await {
@@ -466,6 +594,15 @@ impl<'a> SingleDownloader<'a> {
                 break;
             }
 
+            // 按网络上实际收到的字节计算速度，而非解压后的字节
+            if let Some(ref mut m) = monitor {
+                m.exclude(Duration::from_nanos(throttled.swap(0, Ordering::Relaxed)));
+                if !m.update(received.swap(0, Ordering::Relaxed)) {
+                    callback(Event::ProgressDone(self.download_list_index)).await;
+                    return Err(self.low_speed_error(m.limit()));
+                }
+            }
+
             if let Err(e) = dest.write_all(&buf[..size]).await {
                 callback(Event::ProgressDone(self.download_list_index)).await;
                 return Err(DownloadError::IOError(self.entry.filename.to_string(), e));
@@ -505,6 +642,7 @@ impl<'a> SingleDownloader<'a> {
                 debug!("{self_progress}");
 
                 global_progress.fetch_sub(self_progress, Ordering::SeqCst);
+                DownloadJournal::remove(&file).await;
 
                 callback(Event::GlobalProgressSet(
                     global_progress.load(Ordering::SeqCst),
@@ -519,6 +657,104 @@ impl<'a> SingleDownloader<'a> {
             debug!("checksum success: {}", self.entry.filename);
         }
 
+        DownloadJournal::remove(&file).await;
+        callback(Event::ProgressDone(self.download_list_index)).await;
+
+        Ok(Summary {
+            filename: self.entry.filename.clone(),
+            wrote: true,
+            count: self.download_list_index,
+            context: self.msg.clone(),
+        })
+    }
+
+    fn can_segment(&self, can_resume: bool, total_size: u64) -> bool {
+        // 压缩文件需要边下边解压，无法分段
+        self.segments > 1
+            && can_resume
+            && self.file_type == CompressFile::Nothing
+            && total_size >= self.segment_min_size
+    }
+
+    /// Download file as multiple byte ranges concurrently (http)
+    async fn segmented_download<F, Fut>(
+        &self,
+        global_progress: &AtomicU64,
+        source: &DownloadSource,
+        total_size: u64,
+        msg: String,
+        callback: &F,
+    ) -> DownloadResult<Summary>
+    where
+        F: Fn(Event) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let file = self.entry.dir.join(&*self.entry.filename);
+
+        // 当前源优先，其余 http 源用于分摊各分段
+        let mut mirrors = vec![source];
+        mirrors.extend(self.entry.source.iter().filter(|x| {
+            x.url != source.url && matches!(x.source_type, DownloadSourceType::Http { .. })
+        }));
+
+        let ranges = split_ranges(total_size, self.segments);
+
+        debug!(
+            "{} will be downloaded as {} segments from {} mirror(s)",
+            self.entry.filename,
+            ranges.len(),
+            mirrors.len()
+        );
+
+        // 预先分配文件大小，各分段直接写入各自的偏移
+        let f = File::create(&file)
+            .await
+            .map_err(|e| DownloadError::IOError(self.entry.filename.to_string(), e))?;
+
+        f.set_len(total_size)
+            .await
+            .map_err(|e| DownloadError::IOError(self.entry.filename.to_string(), e))?;
+
+        self.set_permission(&f).await?;
+        drop(f);
+
+        callback(Event::NewProgressBar {
+            index: self.download_list_index,
+            msg,
+            size: total_size,
+        })
+        .await;
+
+        let self_progress = AtomicU64::new(0);
+
+        let res = try_join_all(ranges.into_iter().enumerate().map(|(i, range)| {
+            self.download_segment(
+                &mirrors,
+                i,
+                range,
+                &file,
+                global_progress,
+                &self_progress,
+                callback,
+            )
+        }))
+        .await;
+
+        let res = match res {
+            Ok(_) => self.checksum_segmented_file(&file).await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = res {
+            global_progress.fetch_sub(self_progress.load(Ordering::SeqCst), Ordering::SeqCst);
+            callback(Event::GlobalProgressSet(
+                global_progress.load(Ordering::SeqCst),
+            ))
+            .await;
+            callback(Event::ProgressDone(self.download_list_index)).await;
+            return Err(e);
+        }
+
         callback(Event::ProgressDone(self.download_list_index)).await;
 
         Ok(Summary {
@@ -529,6 +765,231 @@ impl<'a> SingleDownloader<'a> {
         })
     }
 
+    /// Download one byte range, retrying on the same mirror by the retry policy and then
+    /// failing over to the next mirror from where it stopped
+    #[allow(clippy::too_many_arguments)]
+    async fn download_segment<F, Fut>(
+        &self,
+        mirrors: &[&DownloadSource],
+        segment: usize,
+        (start, end): (u64, u64),
+        file: &Path,
+        global_progress: &AtomicU64,
+        self_progress: &AtomicU64,
+        callback: &F,
+    ) -> DownloadResult<()>
+    where
+        F: Fn(Event) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let mut pos = start;
+        let mut last_err = None;
+
+        for n in 0..mirrors.len() {
+            let source = mirrors[(segment + n) % mirrors.len()];
+            let DownloadSourceType::Http { auth } = &source.source_type else {
+                continue;
+            };
+
+            let mut times = 1;
+
+            loop {
+                let conn = self.connect(Some(&source.url)).await;
+
+                let res = self
+                    .fetch_range(
+                        source,
+                        auth,
+                        &mut pos,
+                        end,
+                        file,
+                        global_progress,
+                        self_progress,
+                        callback,
+                    )
+                    .await;
+
+                self.disconnect(conn, res.is_ok(), global_progress);
+
+                let e = match res {
+                    Ok(()) => return Ok(()),
+                    Err(e) => e,
+                };
+
+                // 重试时从 pos 继续下载剩余部分
+                let Some(wait) = self.retry_policy.delay(times, &e) else {
+                    debug!(
+                        "{} segment {segment} failed on {}: {e}, trying next url.",
+                        self.entry.filename, source.url
+                    );
+                    last_err = Some(e);
+                    break;
+                };
+
+                debug!(
+                    "{} segment {segment} failed on {}: {e}, retry after {wait:?}",
+                    self.entry.filename, source.url
+                );
+
+                callback(Event::RetryWait {
+                    index: self.download_list_index,
+                    err: e.to_string(),
+                    times,
+                    wait,
+                })
+                .await;
+
+                sleep(wait).await;
+                times += 1;
+            }
+        }
+
+        Err(last_err.unwrap_or(DownloadError::EmptySources))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn fetch_range<F, Fut>(
+        &self,
+        source: &DownloadSource,
+        auth: &Option<HttpAuth>,
+        pos: &mut u64,
+        end: u64,
+        file: &Path,
+        global_progress: &AtomicU64,
+        self_progress: &AtomicU64,
+        callback: &F,
+    ) -> DownloadResult<()>
+    where
+        F: Fn(Event) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let io_err = |e| DownloadError::IOError(self.entry.filename.to_string(), e);
+
+        debug!("oma will set header range as bytes={}-{end}", *pos);
+
+        let req = self
+            .build_request_with_auth(&source.url, Method::GET, auth)
+            .header(RANGE, format!("bytes={}-{end}", *pos));
+
+        let resp = match timeout(self.timeout, req.send()).await {
+            Ok(resp) => resp
+                .and_then(|x| x.error_for_status())
+                .map_err(DownloadError::ReqwestError)?,
+            Err(e) => return Err(io_err(io::Error::new(ErrorKind::TimedOut, e))),
+        };
+
+        // 服务器忽略了 Range 头并返回了整个文件
+        if resp.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(io_err(io::Error::new(
+                ErrorKind::Unsupported,
+                format!("{} does not honor range requests", source.url),
+            )));
+        }
+
+        let mut dest = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(file)
+            .await
+            .map_err(io_err)?;
+
+        dest.seek(SeekFrom::Start(*pos)).await.map_err(io_err)?;
+
+        let mut stream = resp.bytes_stream();
+        let mut monitor = self.low_speed_limit.map(SpeedMonitor::new);
+
+        while *pos <= end {
+            let chunk = match timeout(self.timeout, stream.next()).await {
+                Ok(Some(Ok(chunk))) => chunk,
+                Ok(Some(Err(e))) => return Err(DownloadError::ReqwestError(e)),
+                Ok(None) => break,
+                Err(e) => return Err(io_err(io::Error::new(ErrorKind::TimedOut, e))),
+            };
+
+            let throttled = match self.rate_limiter {
+                Some(limiter) => limiter.acquire(chunk.len() as u64).await,
+                None => Duration::ZERO,
+            };
+
+            if let Some(ref mut m) = monitor {
+                m.exclude(throttled);
+                if !m.update(chunk.len() as u64) {
+                    return Err(self.low_speed_error(m.limit()));
+                }
+            }
+
+            // 防止服务器返回超出请求范围的数据覆盖其他分段
+            let remaining = (end + 1 - *pos) as usize;
+            let chunk = &chunk[..chunk.len().min(remaining)];
+            let size = chunk.len() as u64;
+
+            dest.write_all(chunk).await.map_err(io_err)?;
+
+            *pos += size;
+
+            callback(Event::ProgressInc {
+                index: self.download_list_index,
+                size,
+            })
+            .await;
+
+            self_progress.fetch_add(size, Ordering::SeqCst);
+            global_progress.fetch_add(size, Ordering::SeqCst);
+            callback(Event::GlobalProgressSet(
+                global_progress.load(Ordering::SeqCst),
+            ))
+            .await;
+        }
+
+        dest.shutdown().await.map_err(io_err)?;
+
+        if *pos <= end {
+            return Err(io_err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("segment ended at {} before {end}", *pos),
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Segments are written out of order, so checksum the joined file afterwards
+    async fn checksum_segmented_file(&self, file: &Path) -> DownloadResult<()> {
+        let Some(hash) = &self.entry.hash else {
+            return Ok(());
+        };
+
+        let mut f = File::open(file)
+            .await
+            .map_err(|e| DownloadError::IOError(self.entry.filename.to_string(), e))?;
+
+        let mut v = hash.get_validator();
+        let mut buf = vec![0; 64 * 1024];
+
+        loop {
+            let read_count = f
+                .read(&mut buf[..])
+                .await
+                .map_err(|e| DownloadError::IOError(self.entry.filename.to_string(), e))?;
+
+            if read_count == 0 {
+                break;
+            }
+
+            v.update(&buf[..read_count]);
+        }
+
+        if !v.finish() {
+            debug!("checksum fail: {}", self.entry.filename);
+            return Err(DownloadError::ChecksumMismatch(
+                self.entry.filename.to_string(),
+            ));
+        }
+
+        debug!("checksum success: {}", self.entry.filename);
+
+        Ok(())
+    }
+
     async fn set_permission(&self, f: &File) -> Result<(), DownloadError> {
         if let Some(mode) = self.set_permission {
             debug!("Setting {} permission to {:#o}", self.entry.filename, mode);
@@ -552,20 +1013,55 @@ impl<'a> SingleDownloader<'a> {
         Ok(())
     }
 
-    fn build_request_with_basic_auth(
+    fn build_request_with_auth(
         &self,
         url: &str,
         method: Method,
-        auth: &Option<(Box<str>, Box<str>)>,
+        auth: &Option<HttpAuth>,
     ) -> RequestBuilder {
-        let mut req = self.client.request(method, url);
+        let client = self
+            .tls_clients
+            .and_then(|x| x.find(url))
+            .unwrap_or(self.client);
+
+        let req = client.request(method, url);
 
-        if let Some((user, password)) = auth {
-            debug!("auth user: {}", user);
-            req = req.basic_auth(user, Some(password));
+        match auth {
+            Some(auth) => auth.apply(req),
+            None => req,
         }
+    }
+
+    /// Wait for a connection slot
+    ///
+    /// The host permit is taken first, so a task waiting for a busy host does not
+    /// hold a global permit that downloads from other hosts could use.
+    async fn connect(&self, url: Option<&str>) -> Connection<'a> {
+        let host = match url {
+            Some(url) => self.host_limiter.acquire(url).await,
+            None => None,
+        };
+
+        Connection {
+            _host: host,
+            permit: self.concurrency.acquire().await,
+        }
+    }
+
+    /// Return a connection slot and record its result for adaptive concurrency
+    fn disconnect(&self, conn: Connection<'a>, ok: bool, global_progress: &AtomicU64) {
+        if self.adaptive {
+            self.concurrency
+                .release(conn.permit, ok, global_progress.load(Ordering::SeqCst));
+        }
+    }
 
-        req
+    fn low_speed_error(&self, limit: LowSpeedLimit) -> DownloadError {
+        DownloadError::LowSpeed(
+            self.entry.filename.to_string(),
+            limit.bytes_per_sec,
+            limit.time.as_secs(),
+        )
     }
 
     fn progress_msg(&self) -> String {
@@ -709,3 +1205,26 @@ impl<'a> SingleDownloader<'a> {
         })
     }
 }
+
+/// Split `0..total_size` into at most `segments` inclusive byte ranges
+fn split_ranges(total_size: u64, segments: usize) -> Vec<(u64, u64)> {
+    let chunk = total_size.div_ceil(segments.max(1) as u64).max(1);
+
+    (0..total_size)
+        .step_by(chunk as usize)
+        .map(|start| (start, (start + chunk).min(total_size) - 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::split_ranges;
+
+    #[test]
+    fn test_split_ranges() {
+        assert_eq!(split_ranges(10, 3), vec![(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(split_ranges(9, 3), vec![(0, 2), (3, 5), (6, 8)]);
+        assert_eq!(split_ranges(2, 4), vec![(0, 0), (1, 1)]);
+        assert_eq!(split_ranges(0, 4), vec![]);
+    }
+}
@@ -0,0 +1,189 @@
+use std::path::{Path, PathBuf};
+
+use reqwest::{
+    header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    RequestBuilder,
+};
+use tracing::debug;
+
+use crate::checksum::Checksum;
+
+/// Sidecar record of which remote file a (partial) download belongs to
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DownloadJournal {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub hash: Option<String>,
+}
+
+impl DownloadJournal {
+    pub fn new(url: &str, headers: &HeaderMap, hash: Option<&Checksum>) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .map(|x| x.to_string())
+        };
+
+        Self {
+            url: url.to_string(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            hash: hash.map(|x| x.to_string()),
+        }
+    }
+
+    pub fn path(file: &Path) -> PathBuf {
+        let mut name = file.file_name().unwrap_or_default().to_os_string();
+        name.push(".journal");
+
+        file.with_file_name(name)
+    }
+
+    pub async fn load(file: &Path) -> Option<Self> {
+        let s = tokio::fs::read_to_string(Self::path(file)).await.ok()?;
+        let res = Self::parse(&s);
+
+        debug!("Loaded download journal of {}: {res:?}", file.display());
+
+        res
+    }
+
+    pub async fn save(&self, file: &Path) -> std::io::Result<()> {
+        tokio::fs::write(Self::path(file), self.to_string()).await
+    }
+
+    pub async fn remove(file: &Path) {
+        let path = Self::path(file);
+
+        if path.exists() {
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                debug!("Failed to remove {}: {e}", path.display());
+            }
+        }
+    }
+
+    pub fn has_validator(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+
+    /// Make `req` a conditional request, server will reply 304 if file is not modified
+    pub fn conditional(&self, req: RequestBuilder) -> RequestBuilder {
+        let req = match &self.etag {
+            Some(etag) => req.header(IF_NONE_MATCH, etag),
+            None => req,
+        };
+
+        match &self.last_modified {
+            Some(last_modified) => req.header(IF_MODIFIED_SINCE, last_modified),
+            None => req,
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        let mut res = Self::default();
+
+        for line in s.lines() {
+            let Some((k, v)) = line.split_once(": ") else {
+                continue;
+            };
+
+            let v = v.trim().to_string();
+
+            match k {
+                "URL" => res.url = v,
+                "ETag" => res.etag = Some(v),
+                "Last-Modified" => res.last_modified = Some(v),
+                "Hash" => res.hash = Some(v),
+                _ => continue,
+            }
+        }
+
+        if res.url.is_empty() {
+            return None;
+        }
+
+        Some(res)
+    }
+
+    /// Whether a partial file recorded by this journal may be continued from `remote`
+    pub fn can_resume_with(&self, remote: &Self) -> bool {
+        if self.url != remote.url || self.hash != remote.hash {
+            return false;
+        }
+
+        // 服务器给出了校验器时必须与记录一致，避免拼接不同版本的文件
+        match (&self.etag, &remote.etag) {
+            (Some(a), Some(b)) => return a == b,
+            (Some(_), None) | (None, Some(_)) => return false,
+            (None, None) => {}
+        }
+
+        self.last_modified == remote.last_modified
+    }
+
+    /// Value for `If-Range`, weak ETag is not allowed there
+    pub fn if_range(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|x| !x.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+impl std::fmt::Display for DownloadJournal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "URL: {}", self.url)?;
+
+        if let Some(etag) = &self.etag {
+            writeln!(f, "ETag: {etag}")?;
+        }
+
+        if let Some(last_modified) = &self.last_modified {
+            writeln!(f, "Last-Modified: {last_modified}")?;
+        }
+
+        if let Some(hash) = &self.hash {
+            writeln!(f, "Hash: {hash}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DownloadJournal;
+
+    #[test]
+    fn test_journal() {
+        let journal = DownloadJournal {
+            url: "https://repo.aosc.io/debs/pool/stable/main/f/fish_3.7.1_amd64.deb".to_string(),
+            etag: Some("\"66b1c7a4-5e1c2d\"".to_string()),
+            last_modified: Some("Tue, 06 Aug 2024 06:58:12 GMT".to_string()),
+            hash: None,
+        };
+
+        assert_eq!(
+            DownloadJournal::parse(&journal.to_string()),
+            Some(journal.clone())
+        );
+        assert!(journal.can_resume_with(&journal));
+        assert_eq!(journal.if_range(), Some("\"66b1c7a4-5e1c2d\""));
+
+        let changed = DownloadJournal {
+            etag: Some("\"66b1c7a4-5e1c2e\"".to_string()),
+            ..journal.clone()
+        };
+
+        assert!(!journal.can_resume_with(&changed));
+
+        let weak = DownloadJournal {
+            etag: Some("W/\"66b1c7a4\"".to_string()),
+            ..journal
+        };
+
+        assert_eq!(weak.if_range(), Some("Tue, 06 Aug 2024 06:58:12 GMT"));
+    }
+}
@@ -2,13 +2,26 @@ use std::{cmp::Ordering, path::PathBuf, sync::atomic::AtomicU64, time::Duration}
 
 use bon::{builder, Builder};
 use checksum::Checksum;
+use concurrency::{AdaptiveConcurrency, HostLimiter};
 use download::SingleDownloader;
 use futures::{Future, StreamExt};
 
 use reqwest::Client;
 
+mod auth;
 pub mod checksum;
+mod concurrency;
 mod download;
+mod journal;
+mod limiter;
+pub mod mirrorlist;
+mod retry;
+
+pub use auth::{HttpAuth, TlsClients};
+pub use journal::DownloadJournal;
+pub use limiter::{LowSpeedLimit, RateLimiter};
+pub use mirrorlist::{MirrorLists, MirrorType};
+pub use retry::RetryPolicy;
 
 pub use reqwest;
 
@@ -20,6 +33,11 @@ pub enum DownloadError {
     IOError(String, std::io::Error),
     #[error(transparent)]
     ReqwestError(reqwest::Error),
+    #[error("{source}, retry after {}s", retry_after.as_secs())]
+    RateLimited {
+        source: reqwest::Error,
+        retry_after: Duration,
+    },
     #[error(transparent)]
     ChecksumError(#[from] crate::checksum::ChecksumError),
     #[error("Failed to open local source file {0}: {1}")]
@@ -28,6 +46,8 @@ pub enum DownloadError {
     InvalidURL(String),
     #[error("download source list is empty")]
     EmptySources,
+    #[error("Download speed of {0} stayed below {1} bytes/s for {2}s")]
+    LowSpeed(String, u64, u64),
 }
 
 pub type DownloadResult<T> = std::result::Result<T, DownloadError>;
@@ -123,7 +143,7 @@ pub struct DownloadSource {
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum DownloadSourceType {
-    Http { auth: Option<(Box<str>, Box<str>)> },
+    Http { auth: Option<HttpAuth> },
     Local(bool),
 }
 
@@ -174,6 +194,12 @@ pub enum Event {
         index: usize,
         err: String,
     },
+    RetryWait {
+        index: usize,
+        err: String,
+        times: usize,
+        wait: Duration,
+    },
     DownloadDone {
         index: usize,
         msg: Box<str>,
@@ -188,8 +214,8 @@ pub struct DownloadManager<'a> {
     download_list: Vec<DownloadEntry>,
     #[builder(default = 4)]
     threads: usize,
-    #[builder(default = 3)]
-    retry_times: usize,
+    #[builder(default)]
+    retry_policy: RetryPolicy,
     #[builder(skip = AtomicU64::new(0))]
     global_progress: AtomicU64,
     #[builder(default)]
@@ -197,8 +223,31 @@ pub struct DownloadManager<'a> {
     set_permission: Option<u32>,
     #[builder(default = Duration::from_secs(15))]
     timeout: Duration,
+    /// Split large files into this many byte ranges and download them concurrently
+    #[builder(default = 1)]
+    segments: usize,
+    /// Files smaller than this size will not be split into segments
+    #[builder(default = DEFAULT_SEGMENT_MIN_SIZE)]
+    segment_min_size: u64,
+    /// Bandwidth limiter shared by all download tasks
+    rate_limiter: Option<&'a RateLimiter>,
+    /// Give up a source if it is too slow, then try the next one
+    low_speed_limit: Option<LowSpeedLimit>,
+    /// Adjust concurrency between 1 and `max_threads` by observed throughput and errors
+    #[builder(default)]
+    adaptive: bool,
+    #[builder(default = 16)]
+    max_threads: usize,
+    /// Maximum connections opened to a single host at the same time
+    #[builder(default = DEFAULT_MAX_PER_HOST)]
+    max_per_host: usize,
+    /// Clients with TLS client certificate for specific hosts
+    tls_clients: Option<&'a TlsClients>,
 }
 
+pub const DEFAULT_SEGMENT_MIN_SIZE: u64 = 32 * 1024 * 1024;
+pub const DEFAULT_MAX_PER_HOST: usize = 4;
+
 #[derive(Debug)]
 pub struct Summary {
     pub filename: String,
@@ -214,8 +263,30 @@ impl<'a> DownloadManager<'a> {
         F: Fn(Event) -> Fut,
         Fut: Future<Output = ()>,
     {
+        let file_download_source = self
+            .download_list
+            .iter()
+            .filter(|x| {
+                x.source
+                    .iter()
+                    .any(|x| matches!(x.source_type, DownloadSourceType::Local { .. }))
+            })
+            .count();
+
+        let http_download_source = self.download_list.len() - file_download_source;
+
+        let (thread, max) = if file_download_source >= http_download_source {
+            (1, 1)
+        } else if self.adaptive {
+            (self.threads, self.max_threads)
+        } else {
+            (self.threads, self.threads)
+        };
+
+        let concurrency = AdaptiveConcurrency::new(thread, max);
+        let host_limiter = HostLimiter::new(self.max_per_host);
         let mut tasks = Vec::new();
-        let mut list = vec![];
+
         for (i, c) in self.download_list.iter().enumerate() {
             let msg = c.msg.clone();
             let single = SingleDownloader::builder()
@@ -224,42 +295,30 @@ impl<'a> DownloadManager<'a> {
                 .download_list_index(i)
                 .entry(c)
                 .progress((i + 1, self.download_list.len()))
-                .retry_times(self.retry_times)
+                .retry_policy(&self.retry_policy)
                 .file_type(c.file_type)
                 .maybe_set_permission(self.set_permission)
                 .timeout(self.timeout)
+                .segments(self.segments)
+                .segment_min_size(self.segment_min_size)
+                .maybe_rate_limiter(self.rate_limiter)
+                .maybe_low_speed_limit(self.low_speed_limit)
+                .host_limiter(&host_limiter)
+                .concurrency(&concurrency)
+                .adaptive(self.adaptive)
+                .maybe_tls_clients(self.tls_clients)
                 .build();
 
-            list.push(single);
-        }
-
-        let file_download_source = list
-            .iter()
-            .filter(|x| {
-                x.entry
-                    .source
-                    .iter()
-                    .any(|x| matches!(x.source_type, DownloadSourceType::Local { .. }))
-            })
-            .count();
-
-        let http_download_source = list.len() - file_download_source;
+            let callback = &callback;
 
-        for single in list {
-            tasks.push(single.try_download(&self.global_progress, &callback));
+            tasks.push(single.try_download(&self.global_progress, callback));
         }
 
-        let thread = if file_download_source >= http_download_source {
-            1
-        } else {
-            self.threads
-        };
-
         if self.total_size != 0 {
             callback(Event::NewGlobalProgressBar(self.total_size)).await;
         }
 
-        let stream = futures::stream::iter(tasks).buffer_unordered(thread);
+        let stream = futures::stream::iter(tasks).buffer_unordered(max);
         let res = stream.collect::<Vec<_>>().await;
         callback(Event::AllDone).await;
 
@@ -0,0 +1,137 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::time::sleep_until;
+
+/// Token-bucket style bandwidth limiter shared by all download tasks
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Wait until `bytes` may be consumed without exceeding the limit, returns how long it waited
+    pub async fn acquire(&self, bytes: u64) -> Duration {
+        let start = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            // 每个调用者预约一段时间片，时间片长度与字节数成正比
+            let start = (*next).max(now);
+            *next = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+
+            start
+        };
+
+        let wait = start.saturating_duration_since(Instant::now());
+
+        if !wait.is_zero() {
+            sleep_until(start.into()).await;
+        }
+
+        wait
+    }
+}
+
+/// Abort a source if its speed stays below `bytes_per_sec` for `time`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LowSpeedLimit {
+    pub bytes_per_sec: u64,
+    pub time: Duration,
+}
+
+pub(crate) struct SpeedMonitor {
+    limit: LowSpeedLimit,
+    since: Instant,
+    bytes: u64,
+}
+
+impl SpeedMonitor {
+    pub fn new(limit: LowSpeedLimit) -> Self {
+        Self {
+            limit,
+            since: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    pub fn limit(&self) -> LowSpeedLimit {
+        self.limit
+    }
+
+    /// Do not count `time` spent waiting for the rate limiter as slow transfer
+    pub fn exclude(&mut self, time: Duration) {
+        self.since += time;
+    }
+
+    /// Record received bytes, return `false` if the last window was too slow
+    pub fn update(&mut self, bytes: u64) -> bool {
+        self.bytes += bytes;
+
+        let elapsed = self.since.elapsed();
+        if elapsed < self.limit.time {
+            return true;
+        }
+
+        let ok = self.bytes as f64 >= self.limit.bytes_per_sec as f64 * elapsed.as_secs_f64();
+
+        // 开始下一个统计窗口
+        self.since = Instant::now();
+        self.bytes = 0;
+
+        ok
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{LowSpeedLimit, RateLimiter, SpeedMonitor};
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let limiter = RateLimiter::new(1000);
+        let start = Instant::now();
+
+        for _ in 0..4 {
+            limiter.acquire(100).await;
+        }
+
+        // 前三次等待 100ms * 3，最后一次在第 300ms 时放行
+        assert!(start.elapsed() >= Duration::from_millis(290));
+    }
+
+    #[test]
+    fn test_speed_monitor() {
+        let mut monitor = SpeedMonitor::new(LowSpeedLimit {
+            bytes_per_sec: 1000,
+            time: Duration::from_millis(100),
+        });
+
+        assert!(monitor.update(10));
+        std::thread::sleep(Duration::from_millis(110));
+        assert!(!monitor.update(10));
+
+        std::thread::sleep(Duration::from_millis(110));
+        assert!(monitor.update(1000));
+
+        // 限速等待的时间不计入统计窗口
+        std::thread::sleep(Duration::from_millis(110));
+        monitor.exclude(Duration::from_millis(110));
+        assert!(monitor.update(0));
+    }
+}
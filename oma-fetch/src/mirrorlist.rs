@@ -0,0 +1,216 @@
+use std::{borrow::Cow, str::FromStr};
+
+use reqwest::Client;
+use tracing::debug;
+
+use crate::{DownloadError, DownloadResult};
+
+/// Which kind of files a mirror in the mirror list serves (`type:` annotation)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorType {
+    /// Release files and indexes
+    Index,
+    /// Packages
+    Deb,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirrorListEntry {
+    pub url: String,
+    /// Lower value is tried first, mirrors without priority are tried last
+    pub priority: Option<u32>,
+    /// `None` means this mirror serves all kinds of files
+    pub mirror_type: Option<MirrorType>,
+}
+
+/// Mirror list used by the apt `mirror` method
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorList {
+    entries: Vec<MirrorListEntry>,
+}
+
+impl FromStr for MirrorList {
+    type Err = DownloadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut entries = vec![];
+
+        for line in s.lines() {
+            let line = line.split_once('#').map(|x| x.0).unwrap_or(line).trim();
+
+            let mut fields = line.split_ascii_whitespace();
+            let Some(url) = fields.next() else {
+                continue;
+            };
+
+            let mut entry = MirrorListEntry {
+                url: url.to_string(),
+                priority: None,
+                mirror_type: None,
+            };
+
+            for field in fields {
+                match field.split_once(':') {
+                    Some(("priority", v)) => {
+                        entry.priority = Some(
+                            v.parse()
+                                .map_err(|_| DownloadError::InvalidURL(line.to_string()))?,
+                        );
+                    }
+                    Some(("type", "index")) => entry.mirror_type = Some(MirrorType::Index),
+                    Some(("type", "deb")) => entry.mirror_type = Some(MirrorType::Deb),
+                    _ => debug!("Unsupported mirror list annotation: {field}"),
+                }
+            }
+
+            entries.push(entry);
+        }
+
+        Ok(Self { entries })
+    }
+}
+
+impl MirrorList {
+    /// Mirrors serving `ty`, ordered by priority
+    pub fn mirrors(&self, ty: MirrorType) -> Vec<&MirrorListEntry> {
+        let mut res = self
+            .entries
+            .iter()
+            .filter(|x| x.mirror_type.is_none_or(|t| t == ty))
+            .collect::<Vec<_>>();
+
+        res.sort_by_key(|x| x.priority.unwrap_or(u32::MAX));
+
+        res
+    }
+}
+
+/// Whether `url` is using the apt `mirror` method
+pub fn is_mirror_list(url: &str) -> bool {
+    url.starts_with("mirror+") || url.starts_with("mirror:")
+}
+
+/// Location of the mirror list file, e.g. `mirror+file:/etc/apt/mirrors.txt` => `file:/etc/apt/mirrors.txt`
+pub fn mirror_list_location(uri: &str) -> Option<Cow<'_, str>> {
+    if let Some(uri) = uri.strip_prefix("mirror+") {
+        Some(Cow::Borrowed(uri))
+    } else {
+        uri.strip_prefix("mirror:")
+            .map(|x| Cow::Owned(format!("http:{x}")))
+    }
+}
+
+/// Mirror lists used by sources, keyed by source uri (e.g. `mirror+file:/etc/apt/mirrors.txt`)
+#[derive(Debug, Clone, Default)]
+pub struct MirrorLists {
+    inner: Vec<(String, MirrorList)>,
+}
+
+impl MirrorLists {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn insert(&mut self, uri: &str, list: MirrorList) {
+        self.inner
+            .push((uri.trim_end_matches('/').to_string(), list));
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        let uri = uri.trim_end_matches('/');
+        self.inner.iter().any(|(x, _)| x == uri)
+    }
+
+    /// Read the mirror list of source `uri` from local file or remote server
+    pub async fn fetch(&mut self, client: &Client, uri: &str) -> DownloadResult<()> {
+        if self.contains(uri) {
+            return Ok(());
+        }
+
+        let location = mirror_list_location(uri.trim_end_matches('/'))
+            .ok_or_else(|| DownloadError::InvalidURL(uri.to_string()))?;
+
+        debug!("Reading mirror list: {location}");
+
+        let s = if let Some(path) = location.strip_prefix("file:") {
+            let path = path.strip_prefix("//").unwrap_or(path);
+            tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| DownloadError::IOError(path.to_string(), e))?
+        } else {
+            client
+                .get(location.as_ref())
+                .send()
+                .await
+                .and_then(|x| x.error_for_status())
+                .map_err(DownloadError::ReqwestError)?
+                .text()
+                .await
+                .map_err(DownloadError::ReqwestError)?
+        };
+
+        self.insert(uri, s.parse()?);
+
+        Ok(())
+    }
+
+    /// Expand `url` under a mirror list source into urls on each mirror, ordered by priority
+    pub fn expand(&self, url: &str, ty: MirrorType) -> Option<Vec<String>> {
+        let (path, list) = self.inner.iter().find_map(|(uri, list)| {
+            let path = url.strip_prefix(uri.as_str())?;
+            (path.is_empty() || path.starts_with('/')).then(|| (path.trim_start_matches('/'), list))
+        })?;
+
+        Some(
+            list.mirrors(ty)
+                .into_iter()
+                .map(|x| format!("{}/{}", x.url.trim_end_matches('/'), path))
+                .collect(),
+        )
+    }
+}
+
+#[test]
+fn test_mirror_list() {
+    let list: MirrorList = "# comment
+http://mirror-b.example.com/debian\tpriority:2
+http://mirror-a.example.com/debian/\tpriority:1\ttype:index
+
+http://mirror-c.example.com/debian\ttype:deb
+"
+    .parse()
+    .unwrap();
+
+    let mut lists = MirrorLists::new();
+    lists.insert("mirror+file:/etc/apt/mirrors.txt", list);
+
+    assert_eq!(
+        lists.expand(
+            "mirror+file:/etc/apt/mirrors.txt/dists/stable/InRelease",
+            MirrorType::Index
+        ),
+        Some(vec![
+            "http://mirror-a.example.com/debian/dists/stable/InRelease".to_string(),
+            "http://mirror-b.example.com/debian/dists/stable/InRelease".to_string(),
+        ])
+    );
+
+    assert_eq!(
+        lists.expand(
+            "mirror+file:/etc/apt/mirrors.txt/pool/main/f/fish.deb",
+            MirrorType::Deb
+        ),
+        Some(vec![
+            "http://mirror-b.example.com/debian/pool/main/f/fish.deb".to_string(),
+            "http://mirror-c.example.com/debian/pool/main/f/fish.deb".to_string(),
+        ])
+    );
+
+    assert!(lists
+        .expand("mirror+file:/etc/apt/mirrors.txt.bak/pool", MirrorType::Deb)
+        .is_none());
+}
@@ -0,0 +1,138 @@
+use std::{
+    io::ErrorKind,
+    time::{Duration, SystemTime},
+};
+
+use bon::Builder;
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+
+use crate::DownloadError;
+
+/// How many times and how long to wait before retrying a download on the same mirror
+#[derive(Debug, Clone, Builder)]
+pub struct RetryPolicy {
+    #[builder(default = 3)]
+    pub retry_times: usize,
+    #[builder(default = Duration::from_secs(1))]
+    pub base_delay: Duration,
+    /// Upper bound of a single wait, mirrors asking for longer `Retry-After` will be skipped
+    #[builder(default = Duration::from_secs(60))]
+    pub max_delay: Duration,
+    #[builder(default = true)]
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `times` (start from 1), `None` means do not retry
+    pub fn delay(&self, times: usize, err: &DownloadError) -> Option<Duration> {
+        if times > self.retry_times || !is_transient(err) {
+            return None;
+        }
+
+        if let DownloadError::RateLimited { retry_after, .. } = err {
+            return (*retry_after <= self.max_delay).then_some(*retry_after);
+        }
+
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << (times - 1).min(16))
+            .min(self.max_delay);
+
+        if !self.jitter {
+            return Some(delay);
+        }
+
+        // 一半固定一半随机，避免多个任务同时重试
+        let half = delay / 2;
+        Some(half + half.mul_f64(fastrand::f64()))
+    }
+}
+
+/// Whether retrying on the same mirror may succeed
+fn is_transient(err: &DownloadError) -> bool {
+    match err {
+        DownloadError::RateLimited { .. } => true,
+        DownloadError::ReqwestError(e) => match e.status() {
+            Some(status) => is_transient_status(status),
+            None => !e.is_builder() && !e.is_redirect(),
+        },
+        DownloadError::IOError(_, e) => matches!(
+            e.kind(),
+            ErrorKind::TimedOut
+                | ErrorKind::BrokenPipe
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::UnexpectedEof
+                | ErrorKind::Interrupted
+        ),
+        _ => false,
+    }
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
+/// Parse `Retry-After` header, it can be seconds or a HTTP date
+pub(crate) fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{self, ErrorKind},
+        time::Duration,
+    };
+
+    use super::RetryPolicy;
+    use crate::DownloadError;
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy::builder()
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5))
+            .retry_times(4)
+            .jitter(false)
+            .build();
+
+        let timeout = DownloadError::IOError(
+            "fish.deb".to_string(),
+            io::Error::new(ErrorKind::TimedOut, "timeout"),
+        );
+
+        assert_eq!(policy.delay(1, &timeout), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(3, &timeout), Some(Duration::from_secs(4)));
+        assert_eq!(policy.delay(4, &timeout), Some(Duration::from_secs(5)));
+        assert_eq!(policy.delay(5, &timeout), None);
+
+        let mismatch = DownloadError::ChecksumMismatch("fish.deb".to_string());
+        assert_eq!(policy.delay(1, &mismatch), None);
+
+        let denied = DownloadError::IOError(
+            "fish.deb".to_string(),
+            io::Error::new(ErrorKind::PermissionDenied, "denied"),
+        );
+        assert_eq!(policy.delay(1, &denied), None);
+    }
+}
@@ -4,7 +4,7 @@ use apt_auth_config::AuthConfig;
 use flume::unbounded;
 use oma_fetch::{reqwest::ClientBuilder, Event};
 use oma_pm::{
-    apt::{AptConfig, DownloadConfig, OmaApt, OmaAptArgs, OmaAptError},
+    apt::{AptConfig, DownloadConfig, NetworkOptions, OmaApt, OmaAptArgs, OmaAptError},
     matches::PackagesMatcher,
 };
 
@@ -40,7 +40,9 @@ fn main() -> Result<(), OmaAptError> {
         &client,
         pkgs.0,
         DownloadConfig {
-            network_thread: None,
+            network: NetworkOptions::default(),
+            tls_clients: None,
+            mirror_lists: None,
             download_dir: Some(Path::new("test")),
             auth: &AuthConfig::system("/").unwrap(),
         },
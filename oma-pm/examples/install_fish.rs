@@ -5,7 +5,7 @@ use flume::unbounded;
 use oma_apt::util::{get_apt_progress_string, terminal_height, terminal_width};
 use oma_fetch::{reqwest::ClientBuilder, Event};
 use oma_pm::{
-    apt::{AptConfig, OmaApt, OmaAptArgs, OmaAptError, SummarySort},
+    apt::{AptConfig, NetworkOptions, OmaApt, OmaAptArgs, OmaAptError, SummarySort},
     matches::PackagesMatcher,
     progress::InstallProgressManager,
     CommitNetworkConfig,
@@ -120,7 +120,9 @@ fn main() -> Result<(), OmaAptError> {
         &op,
         &client,
         CommitNetworkConfig {
-            network_thread: None,
+            network: NetworkOptions::default(),
+            tls_clients: None,
+            mirror_lists: None,
             auth_config: &AuthConfig::system("/").unwrap(),
         },
         |event| async {
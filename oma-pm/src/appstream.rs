@@ -0,0 +1,298 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+};
+
+use oma_utils::lang::{env_lang, resolve_languages};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// An application from DEP-11 (AppStream) metadata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppInfo {
+    /// AppStream component ID, e.g. `org.gimp.GIMP`
+    pub id: String,
+    pub package: String,
+    pub name: String,
+    pub summary: Option<String>,
+    pub categories: Vec<String>,
+    pub keywords: Vec<String>,
+    pub desktop_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dep11Component {
+    #[serde(rename = "ID")]
+    id: Option<String>,
+    #[serde(rename = "Package")]
+    package: Option<String>,
+    #[serde(rename = "Name", default)]
+    name: HashMap<String, String>,
+    #[serde(rename = "Summary", default)]
+    summary: HashMap<String, String>,
+    #[serde(rename = "Categories", default)]
+    categories: Vec<String>,
+    #[serde(rename = "Keywords", default)]
+    keywords: HashMap<String, Vec<String>>,
+    #[serde(rename = "Launchable", default)]
+    launchable: HashMap<String, Vec<String>>,
+}
+
+/// Applications from all DEP-11 metadata in apt lists directory
+#[derive(Debug, Default)]
+pub struct AppStreamIndex {
+    apps: Vec<AppInfo>,
+}
+
+impl AppStreamIndex {
+    /// Read all DEP-11 metadata in `lists_dir`
+    pub fn load(lists_dir: impl AsRef<Path>) -> Self {
+        Self::read(lists_dir.as_ref(), |_| true, |_| true, |_| false)
+    }
+
+    /// Read applications of `packages` only, this is much faster than [`AppStreamIndex::load`]
+    ///
+    /// Only DEP-11 metadata of the components which `index_files` (Packages indexes the
+    /// packages come from) belong to are read, and reading stops once every package is found.
+    pub fn load_packages(
+        lists_dir: impl AsRef<Path>,
+        packages: &[&str],
+        index_files: &[&str],
+    ) -> Self {
+        // xxx_dists_bookworm_main_binary-amd64_Packages => xxx_dists_bookworm_main_
+        let prefixes = index_files
+            .iter()
+            .filter_map(|x| Path::new(x).file_name()?.to_str())
+            .filter_map(|x| x.find("_binary-").map(|i| &x[..=i]))
+            .collect::<Vec<_>>();
+
+        Self::read(
+            lists_dir.as_ref(),
+            |name| prefixes.iter().any(|x| name.starts_with(x)),
+            |doc| {
+                doc.lines().any(|line| {
+                    line.strip_prefix("Package: ")
+                        .is_some_and(|x| packages.contains(&x.trim()))
+                })
+            },
+            |apps| {
+                packages
+                    .iter()
+                    .all(|p| apps.iter().any(|x| x.package == *p))
+            },
+        )
+    }
+
+    fn read(
+        lists_dir: &Path,
+        file_filter: impl Fn(&str) -> bool,
+        filter: impl Fn(&str) -> bool,
+        done: impl Fn(&[AppInfo]) -> bool,
+    ) -> Self {
+        let Ok(dir) = fs::read_dir(lists_dir) else {
+            return Self::default();
+        };
+
+        let langs = resolve_languages(&[], &env_lang());
+        let langs = langs.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+
+        let mut apps = vec![];
+
+        for entry in dir.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if !name.contains("_dep11_Components-") || !file_filter(&name) {
+                continue;
+            }
+
+            let text = match read_metadata(&entry.path()) {
+                Ok(text) => text,
+                Err(e) => {
+                    debug!("Failed to read {name}: {e}");
+                    continue;
+                }
+            };
+
+            apps.extend(parse_dep11(&text, &langs, &filter));
+
+            if done(&apps) {
+                break;
+            }
+        }
+
+        apps.sort_by(|a, b| a.name.cmp(&b.name));
+        apps.dedup();
+
+        Self { apps }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.apps.is_empty()
+    }
+
+    /// Applications provided by package `pkg`
+    pub fn apps_by_package(&self, pkg: &str) -> Vec<&AppInfo> {
+        self.apps.iter().filter(|x| x.package == pkg).collect()
+    }
+
+    /// Search applications by name, summary, keywords, categories and ID, every word in
+    /// `query` must match
+    pub fn search(&self, query: &str) -> Vec<&AppInfo> {
+        let query = query.to_lowercase();
+        let words = query.split_whitespace().collect::<Vec<_>>();
+
+        if words.is_empty() {
+            return vec![];
+        }
+
+        let mut res = self
+            .apps
+            .iter()
+            .filter_map(|app| {
+                let name = app.name.to_lowercase();
+                let haystack = [
+                    name.as_str(),
+                    &app.summary.as_deref().unwrap_or_default().to_lowercase(),
+                    &app.keywords.join(" ").to_lowercase(),
+                    &app.categories.join(" ").to_lowercase(),
+                    &app.id.to_lowercase(),
+                ]
+                .join("\n");
+
+                if !words.iter().all(|w| haystack.contains(w)) {
+                    return None;
+                }
+
+                let score = if name == query {
+                    3
+                } else if name.contains(&query) {
+                    2
+                } else if words.iter().all(|w| name.contains(w)) {
+                    1
+                } else {
+                    0
+                };
+
+                Some((score, app))
+            })
+            .collect::<Vec<_>>();
+
+        res.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+        res.into_iter().map(|x| x.1).collect()
+    }
+}
+
+fn read_metadata(path: &Path) -> io::Result<String> {
+    let f = File::open(path)?;
+    let mut reader: Box<dyn Read> = match path.extension().and_then(|x| x.to_str()) {
+        Some("gz") => Box::new(flate2::read::GzDecoder::new(f)),
+        Some("xz") => Box::new(xz2::read::XzDecoder::new(f)),
+        Some("zst") => Box::new(zstd::Decoder::new(f)?),
+        _ => Box::new(f),
+    };
+
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+
+    Ok(text)
+}
+
+fn parse_dep11(text: &str, langs: &[&str], filter: impl Fn(&str) -> bool) -> Vec<AppInfo> {
+    let mut res = vec![];
+
+    // 第一个文档是 DEP-11 文件头，没有 ID 和 Package，会在下面被跳过
+    for doc in text.split("\n---\n") {
+        if !filter(doc) {
+            continue;
+        }
+
+        let mut component: Dep11Component = match serde_norway::from_str(doc) {
+            Ok(c) => c,
+            Err(e) => {
+                debug!("Failed to parse DEP-11 component: {e}");
+                continue;
+            }
+        };
+
+        let (Some(id), Some(package)) = (component.id, component.package) else {
+            continue;
+        };
+
+        let Some(name) = localized(&component.name, langs) else {
+            continue;
+        };
+
+        res.push(AppInfo {
+            id,
+            package,
+            name,
+            summary: localized(&component.summary, langs),
+            categories: component.categories,
+            keywords: localized(&component.keywords, langs).unwrap_or_default(),
+            desktop_ids: component
+                .launchable
+                .remove("desktop-id")
+                .unwrap_or_default(),
+        });
+    }
+
+    res
+}
+
+/// Pick the value of the first matching language, fallback to `C`
+fn localized<T: Clone>(map: &HashMap<String, T>, langs: &[&str]) -> Option<T> {
+    langs
+        .iter()
+        .chain(&["C"])
+        .find_map(|lang| map.get(*lang))
+        .cloned()
+}
+
+#[test]
+fn test_parse_dep11() {
+    let text = "---
+File: DEP-11
+Version: '0.12'
+Origin: debian-bookworm-main
+---
+Type: desktop-application
+ID: org.gimp.GIMP
+Package: gimp
+Name:
+  C: GNU Image Manipulation Program
+  zh_CN: GNU 图像处理程序
+Summary:
+  C: Create images and edit photographs
+Categories:
+- Graphics
+- 2DGraphics
+Keywords:
+  C:
+  - photo
+  - image editor
+Launchable:
+  desktop-id:
+  - gimp.desktop
+---
+Type: console-application
+ID: org.example.broken
+Package: broken
+Name: [
+";
+
+    let apps = parse_dep11(text, &["zh_CN", "zh"], |_| true);
+    assert_eq!(apps.len(), 1);
+    assert_eq!(apps[0].name, "GNU 图像处理程序");
+    assert_eq!(apps[0].desktop_ids, vec!["gimp.desktop".to_string()]);
+
+    let index = AppStreamIndex {
+        apps: parse_dep11(text, &["en"], |_| true),
+    };
+
+    assert_eq!(index.search("Image Editor")[0].package, "gimp");
+    assert!(index.search("image audio").is_empty());
+    assert_eq!(index.apps_by_package("gimp").len(), 1);
+}
@@ -25,7 +25,10 @@ use oma_apt::{
     DepFlags, Package, PkgCurrentState, Version,
 };
 
-use oma_fetch::{checksum::ChecksumError, reqwest::Client, DownloadError, Event, Summary};
+use oma_fetch::{
+    checksum::ChecksumError, reqwest::Client, DownloadError, Event, LowSpeedLimit, MirrorLists,
+    RetryPolicy, Summary, TlsClients,
+};
 use oma_utils::{
     dpkg::{get_selections, is_hold, DpkgError},
     human_bytes::HumanBytes,
@@ -162,8 +165,23 @@ pub enum SummarySort {
     NoSort,
 }
 
-pub struct DownloadConfig<'a> {
+/// Download tuning shared by every network operation, `None` uses the downloader default
+#[derive(Debug, Clone, Default)]
+pub struct NetworkOptions {
     pub network_thread: Option<usize>,
+    /// Adjust threads by throughput up to this many, `None` keeps `network_thread` fixed
+    pub adaptive_threads: Option<usize>,
+    pub max_per_host: Option<usize>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub segments: Option<usize>,
+    pub limit_rate: Option<u64>,
+    pub low_speed_limit: Option<LowSpeedLimit>,
+}
+
+pub struct DownloadConfig<'a> {
+    pub network: NetworkOptions,
+    pub tls_clients: Option<&'a TlsClients>,
+    pub mirror_lists: Option<&'a MirrorLists>,
     pub download_dir: Option<&'a Path>,
     pub auth: &'a AuthConfig,
 }
@@ -6,13 +6,13 @@ use oma_apt::{
     progress::{AcquireProgress, InstallProgress},
     util::{apt_lock, apt_lock_inner, apt_unlock, apt_unlock_inner},
 };
-use oma_fetch::{reqwest::Client, DownloadError, Event, Summary};
+use oma_fetch::{reqwest::Client, DownloadError, Event, MirrorLists, Summary, TlsClients};
 use oma_pm_operation_type::{InstallEntry, OmaOperation};
 use std::io::Write;
 use tracing::debug;
 
 use crate::{
-    apt::{DownloadConfig, OmaApt, OmaAptError, OmaAptResult},
+    apt::{DownloadConfig, NetworkOptions, OmaApt, OmaAptError, OmaAptResult},
     dbus::change_status,
     download::download_pkgs,
     progress::{InstallProgressArgs, InstallProgressManager, OmaAptInstallProgress},
@@ -21,7 +21,9 @@ use crate::{
 const TIME_FORMAT: &str = "%H:%M:%S on %Y-%m-%d";
 
 pub struct CommitNetworkConfig<'a> {
-    pub network_thread: Option<usize>,
+    pub network: NetworkOptions,
+    pub tls_clients: Option<&'a TlsClients>,
+    pub mirror_lists: Option<&'a MirrorLists>,
     pub auth_config: &'a AuthConfig,
 }
 
@@ -80,7 +82,9 @@ impl<'a> DoInstall<'a> {
             }
 
             let config = DownloadConfig {
-                network_thread: self.config.network_thread,
+                network: self.config.network.clone(),
+                tls_clients: self.config.tls_clients,
+                mirror_lists: self.config.mirror_lists,
                 download_dir: Some(path),
                 auth: self.config.auth_config,
             };
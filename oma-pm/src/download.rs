@@ -1,14 +1,15 @@
 use std::{borrow::Cow, future::Future, path::Path};
 
+use apt_auth_config::Auth;
 use oma_console::console;
 use oma_fetch::{
     checksum::Checksum, reqwest::Client, DownloadEntry, DownloadError, DownloadManager,
-    DownloadSource, DownloadSourceType, Event, Summary,
+    DownloadSource, DownloadSourceType, Event, HttpAuth, MirrorType, RateLimiter, Summary,
 };
 use oma_pm_operation_type::InstallEntry;
 use tracing::debug;
 
-use crate::apt::{DownloadConfig, OmaAptError, OmaAptResult};
+use crate::apt::{DownloadConfig, NetworkOptions, OmaAptError, OmaAptResult};
 
 /// Download packages (inner)
 pub async fn download_pkgs<F, Fut>(
@@ -22,7 +23,18 @@ where
     Fut: Future<Output = ()>,
 {
     let DownloadConfig {
-        network_thread,
+        network:
+            NetworkOptions {
+                network_thread,
+                adaptive_threads,
+                max_per_host,
+                retry_policy,
+                segments,
+                limit_rate,
+                low_speed_limit,
+            },
+        tls_clients,
+        mirror_lists,
         download_dir,
         auth,
     } = config;
@@ -43,19 +55,32 @@ where
         let uris = entry.pkg_urls();
         let sources = uris
             .iter()
+            .flat_map(|x| {
+                // mirror+ 源的包需要展开为镜像列表中的各个镜像
+                match mirror_lists.and_then(|m| m.expand(x, MirrorType::Deb)) {
+                    Some(urls) => urls,
+                    None => vec![x.to_string()],
+                }
+            })
             .map(|x| {
                 let source_type = if x.starts_with("file:") {
                     DownloadSourceType::Local(false)
                 } else {
-                    let auth = auth.find_package_url(x);
+                    let auth = auth.find_package_url(&x);
 
                     DownloadSourceType::Http {
-                        auth: auth.map(|x| (x.user.to_owned(), x.password.to_owned())),
+                        auth: auth.and_then(|x| x.auth.as_ref()).map(|x| match x {
+                            Auth::Basic { user, password } => HttpAuth::Basic {
+                                user: user.clone(),
+                                password: password.clone(),
+                            },
+                            Auth::Bearer(token) => HttpAuth::Bearer(token.clone()),
+                        }),
                     }
                 };
 
                 DownloadSource {
-                    url: x.to_string(),
+                    url: x,
                     source_type,
                 }
             })
@@ -107,10 +132,20 @@ where
         download_list.push(download_entry);
     }
 
+    let rate_limiter = limit_rate.map(RateLimiter::new);
+
     let downloader = DownloadManager::builder()
         .client(client)
         .download_list(download_list)
         .maybe_threads(network_thread)
+        .adaptive(adaptive_threads.is_some())
+        .maybe_max_threads(adaptive_threads)
+        .maybe_max_per_host(max_per_host)
+        .maybe_retry_policy(retry_policy)
+        .maybe_segments(segments)
+        .maybe_rate_limiter(rate_limiter.as_ref())
+        .maybe_low_speed_limit(low_speed_limit)
+        .maybe_tls_clients(tls_clients)
         .total_size(total_size)
         .build();
 
@@ -1,3 +1,4 @@
+pub mod appstream;
 pub mod apt;
 pub mod matches;
 pub mod pkginfo;
@@ -12,7 +12,10 @@ use oma_utils::human_bytes::HumanBytes;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::apt::{OmaAptError, OmaAptResult};
+use crate::{
+    appstream::AppInfo,
+    apt::{OmaAptError, OmaAptResult},
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OmaDependency {
@@ -166,6 +169,9 @@ pub struct PackageInfo {
     pub apt_sources: Vec<AptSource>,
     pub description: String,
     pub short_description: String,
+    /// Applications from AppStream metadata provided by this package
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub apps: Vec<AppInfo>,
 }
 
 impl Display for PackageInfo {
@@ -180,6 +186,7 @@ impl Display for PackageInfo {
             download_size,
             apt_sources,
             description,
+            apps,
             ..
         } = self;
 
@@ -215,6 +222,17 @@ impl Display for PackageInfo {
 
             s
         })?;
+        for app in apps {
+            writeln!(f, "Application: {} ({})", app.name, app.id)?;
+
+            if !app.categories.is_empty() {
+                writeln!(f, "  Categories: {}", app.categories.join(", "))?;
+            }
+
+            if !app.desktop_ids.is_empty() {
+                writeln!(f, "  Desktop-ID: {}", app.desktop_ids.join(", "))?;
+            }
+        }
         writeln!(f, "description: {}", description)?;
 
         Ok(())
@@ -338,6 +356,7 @@ impl OmaPackage {
             apt_sources: pkg_files,
             description,
             short_description,
+            apps: vec![],
         })
     }
 
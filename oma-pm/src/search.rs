@@ -17,6 +17,7 @@ type IndexSet<T> = indexmap::IndexSet<T, RandomState>;
 type IndexMap<K, V> = indexmap::IndexMap<K, V, RandomState>;
 
 use crate::{
+    appstream::{AppInfo, AppStreamIndex},
     matches::has_dbg,
     pkginfo::{OmaPackage, PtrIsNone},
 };
@@ -117,6 +118,9 @@ pub struct SearchResult {
     pub dbg_package: bool,
     pub status: PackageStatus,
     pub is_base: bool,
+    /// Applications from AppStream metadata provided by this package
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub apps: Vec<AppInfo>,
 }
 
 pub struct IndiciumSearch<'a> {
@@ -309,6 +313,7 @@ impl<'a> IndiciumSearch<'a> {
             dbg_package: has_dbg,
             status,
             is_base,
+            apps: vec![],
         })
     }
 }
@@ -421,6 +426,7 @@ impl OmaSearch for StrSimSearch<'_> {
                     PackageStatus::Avail
                 },
                 is_base,
+                apps: vec![],
             });
         }
 
@@ -502,6 +508,7 @@ impl OmaSearch for TextSearch<'_> {
                             PackageStatus::Avail
                         },
                         is_base,
+                        apps: vec![],
                     })
                 }
             }
@@ -520,6 +527,76 @@ impl OmaSearch for TextSearch<'_> {
     }
 }
 
+/// Search applications in AppStream metadata, results are packages providing them
+pub struct AppStreamSearch<'a> {
+    cache: &'a Cache,
+    index: &'a AppStreamIndex,
+}
+
+impl<'a> AppStreamSearch<'a> {
+    pub fn new(cache: &'a Cache, index: &'a AppStreamIndex) -> Self {
+        Self { cache, index }
+    }
+}
+
+impl OmaSearch for AppStreamSearch<'_> {
+    fn search(&self, query: &str) -> OmaSearchResult<Vec<SearchResult>> {
+        let mut res: IndexMap<String, SearchResult> = IndexMap::with_hasher(RandomState::new());
+
+        for app in self.index.search(query) {
+            if let Some(entry) = res.get_mut(&app.package) {
+                entry.apps.push(app.clone());
+                continue;
+            }
+
+            let Some(pkg) = self.cache.get(&app.package) else {
+                continue;
+            };
+
+            let Some(cand) = pkg.candidate() else {
+                continue;
+            };
+
+            let upgrade = pkg.is_upgradable();
+            let name = pkg.fullname(true);
+
+            res.insert(
+                app.package.clone(),
+                SearchResult {
+                    desc: app.summary.clone().unwrap_or_else(|| {
+                        cand.summary()
+                            .unwrap_or_else(|| "No description".to_string())
+                    }),
+                    old_version: if upgrade {
+                        pkg.installed().map(|x| x.version().to_string())
+                    } else {
+                        None
+                    },
+                    new_version: cand.version().to_string(),
+                    full_match: app.name.eq_ignore_ascii_case(query),
+                    dbg_package: has_dbg(self.cache, &pkg, &cand),
+                    status: if upgrade {
+                        PackageStatus::Upgrade
+                    } else if pkg.is_installed() {
+                        PackageStatus::Installed
+                    } else {
+                        PackageStatus::Avail
+                    },
+                    is_base: name.ends_with("-base"),
+                    name,
+                    apps: vec![app.clone()],
+                },
+            );
+        }
+
+        if res.is_empty() {
+            return Err(OmaSearchError::NoResult(query.to_string()));
+        }
+
+        Ok(res.into_values().collect())
+    }
+}
+
 #[test]
 fn test() {
     use crate::test::TEST_LOCK;
@@ -1,7 +1,6 @@
 use std::{
     borrow::Cow,
     collections::{HashMap, VecDeque},
-    env,
     path::Path,
 };
 
@@ -9,6 +8,7 @@ use ahash::AHashMap;
 use aho_corasick::AhoCorasick;
 use oma_apt::config::{Config, ConfigTree};
 use oma_fetch::CompressFile;
+use oma_utils::lang::{env_lang, resolve_languages};
 
 use crate::{db::RefreshError, inrelease::ChecksumItem};
 
@@ -70,12 +70,28 @@ pub struct IndexTargetConfig<'a> {
     deb_src: Vec<(String, HashMap<String, String>)>,
     replacer: AhoCorasick,
     native_arch: &'a str,
+    /// `Acquire::CompressionTypes::Order`, empty means oma's default order
+    compression_order: Vec<String>,
+    /// Languages of `$(LANGUAGE)` targets, from `Acquire::Languages`
+    languages: Vec<String>,
 }
 
 impl<'a> IndexTargetConfig<'a> {
-    pub fn new(config: &Config, native_arch: &'a str) -> Self {
+    /// `appstream`: download DEP-11 metadata even if apt is not configured to
+    pub fn new(config: &Config, native_arch: &'a str, appstream: bool) -> Self {
+        let mut deb = get_index_target_tree(config, "Acquire::IndexTargets::deb");
+
+        // 没有安装 appstream 时 apt 不会配置 DEP-11，使用与其相同的默认配置
+        if appstream
+            && !get_tree(config, "Acquire::IndexTargets::deb")
+                .iter()
+                .any(|x| x.0.ends_with("::DEP-11"))
+        {
+            deb.push(dep11_index_target());
+        }
+
         Self {
-            deb: get_index_target_tree(config, "Acquire::IndexTargets::deb"),
+            deb,
             deb_src: get_index_target_tree(config, "Acquire::IndexTargets::deb-src"),
             replacer: AhoCorasick::new([
                 "$(ARCHITECTURE)",
@@ -85,6 +101,8 @@ impl<'a> IndexTargetConfig<'a> {
             ])
             .unwrap(),
             native_arch,
+            compression_order: config.find_vector("Acquire::CompressionTypes::Order"),
+            languages: resolve_languages(&config.find_vector("Acquire::Languages"), &env_lang()),
         }
     }
 
@@ -97,8 +115,6 @@ impl<'a> IndexTargetConfig<'a> {
         components: &[String],
     ) -> Result<Vec<ChecksumDownloadEntry>, RefreshError> {
         let key = if is_flat { "flatMetaKey" } else { "MetaKey" };
-        let lang = env::var("LANG").map(Cow::Owned).unwrap_or("C".into());
-        let langs = get_matches_language(&lang);
 
         let mut res_map: AHashMap<String, Vec<ChecksumDownloadEntry>> = AHashMap::new();
 
@@ -110,8 +126,22 @@ impl<'a> IndexTargetConfig<'a> {
 
         for c in checksums {
             for (template, config) in tree.iter().map(|x| (x.1.get(key), &x.1)) {
-                let template =
-                    template.ok_or_else(|| RefreshError::WrongConfigEntry(key.to_string()))?;
+                // 像 DEP-11 这样的 target 不支持 flat 源
+                let template = match template {
+                    Some(template) => template,
+                    None if is_flat => continue,
+                    None => return Err(RefreshError::WrongConfigEntry(key.to_string())),
+                };
+
+                // Acquire::Languages 为 none 时仍要下载与语言无关的索引
+                let langs = if template.contains("$(LANGUAGE)") {
+                    self.languages
+                        .iter()
+                        .map(|x| x.as_str())
+                        .collect::<Vec<_>>()
+                } else {
+                    vec![""]
+                };
 
                 for a in &*archs {
                     for comp in components {
@@ -147,13 +177,21 @@ impl<'a> IndexTargetConfig<'a> {
         let mut res = vec![];
 
         for (_, v) in &mut res_map {
-            v.sort_unstable_by(|a, b| {
-                compress_file(&a.item.name).cmp(&compress_file(&b.item.name))
+            v.sort_unstable_by_key(|x| {
+                compression_preference(&self.compression_order, &x.item.name)
             });
-            if v[0].item.size == 0 {
+            let selected = v.last().unwrap();
+
+            // 选中的文件为空，或未压缩的索引为空（即索引中没有内容）时跳过
+            if selected.item.size == 0
+                || v.iter().any(|x| {
+                    x.item.size == 0 && compress_file(&x.item.name) == CompressFile::Nothing
+                })
+            {
                 continue;
             }
-            res.push(v.last().unwrap().to_owned());
+
+            res.push(selected.to_owned());
         }
 
         Ok(res)
@@ -198,6 +236,26 @@ fn get_index_target_tree(config: &Config, key: &str) -> Vec<(String, HashMap<Str
         .collect::<Vec<_>>()
 }
 
+/// DEP-11 (AppStream) metadata target, same as `50appstream` shipped by appstream
+fn dep11_index_target() -> (String, HashMap<String, String>) {
+    let config = [
+        (
+            "MetaKey",
+            "$(COMPONENT)/dep11/Components-$(NATIVE_ARCHITECTURE).yml",
+        ),
+        ("ShortDescription", "Components-$(NATIVE_ARCHITECTURE)"),
+        ("KeepCompressed", "true"),
+    ];
+
+    (
+        "Acquire::IndexTargets::deb::DEP-11".to_string(),
+        config
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    )
+}
+
 #[derive(Debug, Clone)]
 pub struct ChecksumDownloadEntry {
     pub item: ChecksumItem,
@@ -205,20 +263,24 @@ pub struct ChecksumDownloadEntry {
     pub msg: String,
 }
 
-fn get_matches_language(env_lang: &str) -> Vec<&str> {
-    let mut langs = vec![];
-    let env_lang = env_lang.split_once('.').map(|x| x.0).unwrap_or(env_lang);
-
-    let lang = if env_lang == "C" { "en" } else { env_lang };
-
-    langs.push(lang);
+/// Higher is more preferred, types in `order` go first, then oma's default order
+fn compression_preference(order: &[String], name: &str) -> (usize, CompressFile) {
+    let compress = compress_file(name);
+    let ty = match compress {
+        CompressFile::Bz2 => "bz2",
+        CompressFile::Gzip => "gz",
+        CompressFile::Xz => "xz",
+        CompressFile::Zstd => "zst",
+        CompressFile::Nothing => "uncompressed",
+    };
 
-    // en_US.UTF-8 => en
-    if let Some((a, _)) = lang.split_once('_') {
-        langs.push(a);
-    }
+    let rank = order
+        .iter()
+        .position(|x| x == ty)
+        .map(|pos| order.len() - pos)
+        .unwrap_or(0);
 
-    langs
+    (rank, compress)
 }
 
 fn compress_file(name: &str) -> CompressFile {
@@ -233,10 +295,18 @@ fn compress_file(name: &str) -> CompressFile {
 }
 
 #[test]
-fn test_get_matches_language() {
-    assert_eq!(get_matches_language("C"), vec!["en"]);
-    assert_eq!(get_matches_language("zh_CN.UTF-8"), vec!["zh_CN", "zh"]);
-    assert_eq!(get_matches_language("en_US.UTF-8"), vec!["en_US", "en"]);
+fn test_compression_preference() {
+    let mut names = vec!["Packages.gz", "Packages.xz", "Packages", "Packages.zst"];
+
+    names.sort_by_key(|x| compression_preference(&[], x));
+    assert_eq!(names.last(), Some(&"Packages.zst"));
+
+    let order = vec!["gz".to_string(), "xz".to_string()];
+    names.sort_by_key(|x| compression_preference(&order, x));
+    assert_eq!(
+        names,
+        vec!["Packages", "Packages.zst", "Packages.xz", "Packages.gz"]
+    );
 }
 
 #[test]
@@ -3,15 +3,18 @@ use std::{
     borrow::Cow,
     collections::hash_map::Entry,
     fs::Permissions,
+    io::Cursor,
     os::{fd::AsRawFd, unix::fs::PermissionsExt},
     path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
 };
 
 use ahash::{AHashMap, HashSet};
 use aho_corasick::BuildError;
 use apt_auth_config::AuthConfig;
 use bon::{builder, Builder};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use futures::StreamExt;
 use nix::{
     errno::Errno,
@@ -33,17 +36,18 @@ use oma_fetch::{
         header::{HeaderValue, CONTENT_LENGTH},
         Client, Response,
     },
-    CompressFile, DownloadEntry, DownloadManager, DownloadResult, DownloadSource,
-    DownloadSourceType,
+    CompressFile, DownloadEntry, DownloadJournal, DownloadManager, DownloadResult,
+    DownloadSourceType, LowSpeedLimit, MirrorLists, MirrorType, RateLimiter, RetryPolicy,
+    TlsClients, DEFAULT_MAX_PER_HOST,
 };
 
 use oma_fetch::DownloadError;
+use oma_repo_verify::SignaturePolicies;
 
 #[cfg(feature = "aosc")]
 use oma_topics::TopicManager;
 
 use oma_utils::dpkg::dpkg_arch;
-#[cfg(feature = "aosc")]
 use reqwest::StatusCode;
 
 use sysinfo::{Pid, System};
@@ -58,10 +62,14 @@ use tracing::{debug, warn};
 use crate::{
     config::{ChecksumDownloadEntry, IndexTargetConfig},
     inrelease::{
-        file_is_compress, split_ext_and_filename, verify_inrelease, ChecksumItem, InRelease,
-        InReleaseChecksum, InReleaseError,
+        clearsigned_text, file_is_compress, split_ext_and_filename, verify_inrelease, ChecksumItem,
+        InRelease, InReleaseChecksum, InReleaseError,
     },
-    sourceslist::{sources_lists, OmaSourceEntry, OmaSourceEntryFrom},
+    pdiff::{apply_ed_patch, join_lines, split_lines, PDiffError, PDiffIndex},
+    release_date::ReleaseDates,
+    report::{IndexSnapshot, ReportSource, SourceReport},
+    snapshot::{Snapshot, DEFAULT_SNAPSHOT_ARCHIVES, DEFAULT_SNAPSHOT_URL},
+    sourceslist::{http_auth, sources_lists, Mirror, OmaSourceEntry, OmaSourceEntryFrom},
     util::DatabaseFilenameReplacer,
 };
 
@@ -106,6 +114,14 @@ pub enum RefreshError {
     DuplicateComponents(Box<str>, String),
     #[error("config entry has no key '{0}'")]
     WrongConfigEntry(String),
+    #[error(transparent)]
+    PDiffError(#[from] PDiffError),
+    #[error("Release file {0} is older than the last accepted one ({1} < {2})")]
+    ReleaseRollback(String, String, String),
+    #[error("No source matches {0}")]
+    NoMatchingSource(String),
+    #[error("Invalid snapshot time: {0}")]
+    InvalidSnapshot(String),
 }
 
 #[cfg(not(feature = "aosc"))]
@@ -147,6 +163,14 @@ pub enum RefreshError {
     DuplicateComponents(Box<str>, String),
     #[error("config entry has no key '{0}'")]
     WrongConfigEntry(String),
+    #[error(transparent)]
+    PDiffError(#[from] PDiffError),
+    #[error("Release file {0} is older than the last accepted one ({1} < {2})")]
+    ReleaseRollback(String, String, String),
+    #[error("No source matches {0}")]
+    NoMatchingSource(String),
+    #[error("Invalid snapshot time: {0}")]
+    InvalidSnapshot(String),
 }
 
 type Result<T> = std::result::Result<T, RefreshError>;
@@ -161,16 +185,66 @@ pub struct OmaRefresh<'a> {
     client: &'a Client,
     #[builder(skip)]
     flat_repo_no_release: Vec<usize>,
+    /// 刷新成功后保存的 release 文件 ETag/Last-Modified
+    #[builder(skip)]
+    release_journals: Vec<(String, DownloadJournal)>,
+    /// 服务器返回 304 的 release 文件
+    #[builder(skip)]
+    not_modified: HashSet<String>,
+    /// 刷新前本地 release 文件中各索引的 SHA256，用于判断索引是否变化
+    #[builder(skip)]
+    old_releases: AHashMap<String, Vec<ChecksumItem>>,
     #[cfg(feature = "aosc")]
     refresh_topics: bool,
     apt_config: &'a Config,
     topic_msg: &'a str,
     auth_config: &'a AuthConfig,
+    /// Bandwidth limit in bytes per second
+    limit_rate: Option<u64>,
+    /// Give up a mirror if it is too slow
+    low_speed_limit: Option<LowSpeedLimit>,
+    /// Adjust threads by throughput up to this many, `None` keeps `threads` fixed
+    adaptive_threads: Option<usize>,
+    /// Maximum connections opened to a single host at the same time
+    #[builder(default = DEFAULT_MAX_PER_HOST)]
+    max_per_host: usize,
+    /// How to retry a failed download on the same mirror
+    #[builder(default)]
+    retry_policy: RetryPolicy,
+    /// Clients with TLS client certificate for specific hosts
+    tls_clients: Option<&'a TlsClients>,
+    #[builder(skip)]
+    rate_limiter: Option<RateLimiter>,
+    /// Accept Release files older than the last accepted ones (intentional downgrade)
+    #[builder(default)]
+    allow_release_rollback: bool,
+    /// Only refresh sources matching any of these URIs, sources.list files or suites
+    #[builder(default)]
+    source_filter: Vec<String>,
+    /// Compare Packages indexes before and after refresh
+    #[builder(default)]
+    report: bool,
+    /// Resolve against the archive as it was at this time
+    snapshot: Option<Snapshot>,
+    /// Snapshot URL template, see [`DEFAULT_SNAPSHOT_URL`]
+    #[builder(default = DEFAULT_SNAPSHOT_URL.to_string())]
+    snapshot_url: String,
+    /// Only sources of these archives are resolved against the snapshot
+    #[builder(default = DEFAULT_SNAPSHOT_ARCHIVES.iter().map(|x| x.to_string()).collect())]
+    snapshot_archives: Vec<String>,
+    /// Rules to accept signatures, with per-source overrides
+    #[builder(default)]
+    signature_policy: SignaturePolicies,
+    /// Warn if a signing key expires within this duration
+    key_expiry_warning: Option<Duration>,
+    /// Download DEP-11 (AppStream) metadata even if apt is not configured to
+    #[builder(default)]
+    appstream: bool,
 }
 
 type SourceMap<'a> = AHashMap<String, Vec<OmaSourceEntry<'a>>>;
 
-fn get_apt_update_lock(download_dir: &Path) -> Result<()> {
+pub(crate) fn get_apt_update_lock(download_dir: &Path) -> Result<()> {
     let lock_path = download_dir.join("lock");
 
     let fd = open(
@@ -229,23 +303,56 @@ fn get_apt_update_lock(download_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Read mirror lists of all `mirror+` sources under `sysroot`
+pub async fn mirror_lists(client: &Client, sysroot: impl AsRef<Path>) -> Result<MirrorLists> {
+    let mut res = MirrorLists::new();
+
+    // 架构不影响源地址，这里无需获取
+    for i in sources_lists(sysroot, "")? {
+        if *i.from()? == OmaSourceEntryFrom::MirrorList {
+            res.fetch(client, i.url()).await?;
+        }
+    }
+
+    Ok(res)
+}
+
+#[derive(Debug)]
+enum ReleaseStatus {
+    /// 新下载的文件，带有可用于下次条件请求的信息
+    Updated(Option<DownloadJournal>),
+    /// 服务器返回 304，本地文件仍是最新的
+    NotModified(DownloadJournal),
+}
+
 #[derive(Debug)]
 pub enum Event {
     DownloadEvent(oma_fetch::Event),
     ScanningTopic,
     ClosingTopic(String),
-    TopicNotInMirror { topic: String, mirror: String },
+    TopicNotInMirror {
+        topic: String,
+        mirror: String,
+    },
     RunInvokeScript,
+    /// The key which signed `source` will expire soon
+    KeyExpiring {
+        source: String,
+        fingerprint: String,
+        expires: SystemTime,
+    },
     Done,
 }
 
 impl<'a> OmaRefresh<'a> {
-    pub async fn start<F, Fut>(mut self, callback: F) -> Result<()>
+    /// Refresh sources, returns what changed if `report` is enabled
+    pub async fn start<F, Fut>(mut self, callback: F) -> Result<Option<Vec<SourceReport>>>
     where
         F: Fn(Event) -> Fut,
         Fut: Future<Output = ()>,
     {
         let arch = dpkg_arch(&self.source)?;
+        self.rate_limiter = self.limit_rate.map(RateLimiter::new);
 
         self.update_db(sources_lists(&self.source, &arch)?, callback)
             .await
@@ -255,7 +362,7 @@ impl<'a> OmaRefresh<'a> {
         &mut self,
         mut sourcelist: Vec<OmaSourceEntry<'_>>,
         callback: F,
-    ) -> Result<()>
+    ) -> Result<Option<Vec<SourceReport>>>
     where
         F: Fn(Event) -> Fut,
         Fut: Future<Output = ()>,
@@ -282,10 +389,27 @@ impl<'a> OmaRefresh<'a> {
 
         detect_duplicate_repositories(&sourcelist)?;
 
+        if !self.source_filter.is_empty() {
+            sourcelist.retain(|x| self.source_filter.iter().any(|f| source_matches(x, f)));
+
+            if sourcelist.is_empty() {
+                return Err(RefreshError::NoMatchingSource(
+                    self.source_filter.join(", "),
+                ));
+            }
+        }
+
         self.set_auth(&mut sourcelist);
+        self.set_mirrors(&mut sourcelist).await?;
+        self.set_snapshot(&mut sourcelist)?;
 
         let mut download_list = vec![];
 
+        // 下载新的 release 文件之前记录旧的校验和，pdiff 只用于变化了的索引
+        if self.apt_config.bool("Acquire::PDiffs", true) {
+            self.old_releases = old_release_checksums(&self.download_dir).await;
+        }
+
         let replacer = DatabaseFilenameReplacer::new()?;
         let (release_results, source_map) = self
             .download_releases(&sourcelist, &replacer, &callback)
@@ -295,24 +419,93 @@ impl<'a> OmaRefresh<'a> {
 
         download_list.extend(release_results.iter().map(|x| x.to_string()));
 
-        let (tasks, total) = self
-            .collect_all_release_entry(release_results, &sourcelist, &replacer, &source_map)
+        for file_name in &release_results {
+            let journal = DownloadJournal::path(&self.download_dir.join(file_name));
+            download_list.push(journal.file_name().unwrap().to_string_lossy().to_string());
+        }
+
+        let mut release_dates = ReleaseDates::load(&self.source).await;
+
+        // 需要在 pdiff 修改索引文件之前读取
+        let snapshot = if self.report {
+            let mut sources = vec![];
+
+            for ose in &sourcelist {
+                let prefix = format!(
+                    "{}_",
+                    replacer.replace(ose.dist_path())?.trim_end_matches('_')
+                );
+
+                if sources.iter().any(|x: &ReportSource| x.prefix == prefix) {
+                    continue;
+                }
+
+                sources.push(ReportSource {
+                    prefix,
+                    dist_path: ose.dist_path().to_string(),
+                    suite: ose.suite().to_string(),
+                });
+            }
+
+            Some(IndexSnapshot::new(&self.download_dir, sources).await)
+        } else {
+            None
+        };
+
+        let (tasks, total, patched, unchanged) = self
+            .collect_all_release_entry(
+                release_results,
+                &sourcelist,
+                &replacer,
+                &source_map,
+                &mut release_dates,
+                &callback,
+            )
             .await?;
 
         for i in &tasks {
             download_list.push(i.filename.to_string());
         }
 
+        download_list.extend(patched.iter().cloned());
+        download_list.extend(unchanged);
+
+        // 只有整个刷新成功后才保存 ETag 等信息，避免下次刷新时跳过未下载完整的文件
+        for (file_name, _) in &self.release_journals {
+            DownloadJournal::remove(&self.download_dir.join(file_name)).await;
+        }
+
+        // 只刷新部分源时，仅清理属于这些源的文件
+        let prefixes = if self.source_filter.is_empty() {
+            None
+        } else {
+            Some(
+                sourcelist
+                    .iter()
+                    .map(|x| replacer.replace(x.dist_path()))
+                    .collect::<Result<Vec<_>>>()?,
+            )
+        };
+
         let download_dir = self.download_dir.clone();
         let remove_task =
-            tokio::spawn(async move { remove_unused_db(download_dir, download_list).await });
+            tokio::spawn(
+                async move { remove_unused_db(download_dir, download_list, prefixes).await },
+            );
 
         let dm = DownloadManager::builder()
             .client(self.client)
             .download_list(tasks)
             .threads(self.threads)
+            .adaptive(self.adaptive_threads.is_some())
+            .maybe_max_threads(self.adaptive_threads)
+            .max_per_host(self.max_per_host)
+            .retry_policy(self.retry_policy.clone())
             .set_permission(0o644)
             .total_size(total)
+            .maybe_rate_limiter(self.rate_limiter.as_ref())
+            .maybe_low_speed_limit(self.low_speed_limit)
+            .maybe_tls_clients(self.tls_clients)
             .build();
 
         let res = dm
@@ -323,8 +516,20 @@ impl<'a> OmaRefresh<'a> {
 
         let res = res.into_iter().collect::<DownloadResult<Vec<_>>>()?;
 
+        for (file_name, journal) in &self.release_journals {
+            if let Err(e) = journal.save(&self.download_dir.join(file_name)).await {
+                debug!("Failed to save journal of {file_name}: {e}");
+            }
+        }
+
+        release_dates.save().await?;
+
+        if let Some(snapshot) = &self.snapshot {
+            snapshot.save(&self.source, &self.snapshot_url).await?;
+        }
+
         // 有元数据更新才执行 success invoke
-        let should_run_invoke = res.iter().any(|x| x.wrote);
+        let should_run_invoke = !patched.is_empty() || res.iter().any(|x| x.wrote);
 
         // Finally, run success post invoke
         let _ = remove_task.await;
@@ -334,9 +539,14 @@ impl<'a> OmaRefresh<'a> {
             self.run_success_post_invoke().await;
         }
 
+        let report = match snapshot {
+            Some(snapshot) => Some(snapshot.report().await),
+            None => None,
+        };
+
         callback(Event::Done).await;
 
-        Ok(())
+        Ok(report)
     }
 
     fn set_auth(&self, sourcelist: &mut [OmaSourceEntry<'_>]) {
@@ -348,6 +558,95 @@ impl<'a> OmaRefresh<'a> {
         }
     }
 
+    /// 从快照下载 HTTP 源的元数据，本地保存的文件名不变
+    fn set_snapshot(&self, sourcelist: &mut [OmaSourceEntry<'_>]) -> Result<()> {
+        let Some(snapshot) = &self.snapshot else {
+            return Ok(());
+        };
+
+        for i in sourcelist {
+            if *i.from()? != OmaSourceEntryFrom::Http {
+                continue;
+            }
+
+            let Some(url) = snapshot.url(&self.snapshot_url, &self.snapshot_archives, i.url())
+            else {
+                debug!("{} is not in any snapshot archive, using it as is", i.url());
+                continue;
+            };
+
+            debug!("Snapshot of {}: {}", i.url(), url);
+            i.set_snapshot(url);
+        }
+
+        Ok(())
+    }
+
+    async fn set_mirrors(&self, sourcelist: &mut [OmaSourceEntry<'_>]) -> Result<()> {
+        let mut mirror_lists = MirrorLists::new();
+
+        for i in sourcelist {
+            if *i.from()? != OmaSourceEntryFrom::MirrorList {
+                continue;
+            }
+
+            mirror_lists.fetch(self.client, i.url()).await?;
+
+            let mirrors = mirror_lists
+                .expand(i.url(), MirrorType::Index)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|url| Mirror {
+                    auth: self.auth_config.find(&url).cloned(),
+                    url,
+                })
+                .collect::<Vec<_>>();
+
+            debug!("Mirrors of {}: {:?}", i.url(), mirrors);
+
+            i.set_mirrors(mirrors);
+        }
+
+        Ok(())
+    }
+
+    /// 拒绝比上次接受的 Release 文件更旧的元数据，防止降级攻击
+    fn check_rollback(
+        &self,
+        ose: &OmaSourceEntry<'_>,
+        inrelease: &InRelease,
+        inrelease_path: &Path,
+        release_dates: &mut ReleaseDates,
+    ) -> Result<()> {
+        let date = inrelease.date().map_err(|e| {
+            RefreshError::InReleaseParseError(inrelease_path.display().to_string(), e)
+        })?;
+        let timestamp = date.timestamp();
+
+        if let Some(last) = release_dates.get(ose.dist_path()) {
+            if timestamp < last {
+                if !self.allow_release_rollback {
+                    let last = DateTime::from_timestamp(last, 0).unwrap_or_default();
+
+                    return Err(RefreshError::ReleaseRollback(
+                        inrelease_path.display().to_string(),
+                        date.to_rfc2822(),
+                        last.to_rfc2822(),
+                    ));
+                }
+
+                warn!(
+                    "Accepting older Release file {} as requested",
+                    inrelease_path.display()
+                );
+            }
+        }
+
+        release_dates.insert(ose.dist_path(), timestamp);
+
+        Ok(())
+    }
+
     async fn run_success_post_invoke(&self) {
         let cmds = self
             .apt_config
@@ -404,7 +703,18 @@ impl<'a> OmaRefresh<'a> {
 
         for result in results {
             match result {
-                Ok((Some(file_name), index)) => {
+                Ok((Some(file_name), index, status)) => {
+                    match status {
+                        ReleaseStatus::Updated(Some(journal)) => {
+                            self.release_journals.push((file_name.clone(), journal));
+                        }
+                        ReleaseStatus::Updated(None) => {}
+                        ReleaseStatus::NotModified(journal) => {
+                            self.release_journals.push((file_name.clone(), journal));
+                            self.not_modified.insert(file_name.clone());
+                        }
+                    }
+
                     let source = sourcelist.get(index).unwrap();
                     match source_map.entry(file_name.to_string()) {
                         Entry::Occupied(mut occupied_entry) => {
@@ -416,7 +726,7 @@ impl<'a> OmaRefresh<'a> {
                         }
                     }
                 }
-                Ok((None, index)) => self.flat_repo_no_release.push(index),
+                Ok((None, index, _)) => self.flat_repo_no_release.push(index),
                 Err(e) => {
                     #[cfg(feature = "aosc")]
                     match e {
@@ -484,13 +794,13 @@ impl<'a> OmaRefresh<'a> {
         index: usize,
         total: usize,
         callback: &F,
-    ) -> Result<(Option<String>, usize)>
+    ) -> Result<(Option<String>, usize, ReleaseStatus)>
     where
         F: Fn(Event) -> Fut,
         Fut: Future<Output = ()>,
     {
         match source_index.from()? {
-            OmaSourceEntryFrom::Http => {
+            OmaSourceEntryFrom::Http | OmaSourceEntryFrom::MirrorList => {
                 let dist_path = source_index.dist_path();
 
                 let mut r = None;
@@ -505,46 +815,84 @@ impl<'a> OmaRefresh<'a> {
                 }))
                 .await;
 
-                for (index, file_name) in ["InRelease", "Release"].iter().enumerate() {
+                'outer: for (index, file_name) in ["InRelease", "Release"].iter().enumerate() {
                     let url = format!("{}/{}", dist_path, file_name);
-                    let request = self.request_get_builder(&url, source_index);
 
-                    let resp = request
-                        .send()
-                        .await
-                        .and_then(|resp| resp.error_for_status());
+                    let file = self.download_dir.join(replacer.replace(&url)?);
+                    // 快照中的文件比本地的旧，不能发送条件请求
+                    let journal = if file.is_file() && !source_index.is_snapshot() {
+                        DownloadJournal::load(&file)
+                            .await
+                            .filter(|journal| journal.url == url)
+                    } else {
+                        None
+                    };
 
-                    r = Some(resp);
+                    // mirror+ 源按优先级依次尝试各个镜像
+                    for source in source_index.download_sources(&url)? {
+                        if !matches!(source.source_type, DownloadSourceType::Http { .. }) {
+                            continue;
+                        }
 
-                    if r.as_ref().unwrap().is_ok() {
-                        u = Some(url);
-                        if index == 1 {
-                            is_release = true;
+                        let mut request = self.request_get_builder(&source.url, source_index);
+
+                        if let Some(journal) = &journal {
+                            request = journal.conditional(request);
+                        }
+
+                        let resp = request
+                            .send()
+                            .await
+                            .and_then(|resp| resp.error_for_status());
+
+                        r = Some(resp);
+
+                        if r.as_ref().unwrap().is_ok() {
+                            u = Some((url, source.url));
+                            if index == 1 {
+                                is_release = true;
+                            }
+                            break 'outer;
                         }
-                        break;
                     }
                 }
 
-                let r = r.unwrap();
+                let r = r.ok_or_else(|| RefreshError::NoInReleaseFile(dist_path.to_string()))?;
 
                 callback(Event::DownloadEvent(oma_fetch::Event::ProgressDone(index))).await;
 
                 if r.is_err() && source_index.is_flat() {
-                    return Ok((None, index));
+                    return Ok((None, index, ReleaseStatus::Updated(None)));
                 }
 
                 let resp = r?;
 
-                let url = u.unwrap();
+                let (url, real_url) = u.unwrap();
                 let file_name = replacer.replace(&url)?;
+                let file = self.download_dir.join(&file_name);
+
+                let status = if resp.status() == StatusCode::NOT_MODIFIED {
+                    debug!("{url} is not modified, reuse local file");
+
+                    match DownloadJournal::load(&file).await {
+                        Some(journal) => ReleaseStatus::NotModified(journal),
+                        None => return Err(RefreshError::NoInReleaseFile(url)),
+                    }
+                } else {
+                    let journal = DownloadJournal::new(&url, resp.headers(), None);
 
-                self.download_file(&file_name, resp, source_index, index, total, &callback)
-                    .await?;
+                    DownloadJournal::remove(&file).await;
+                    self.download_file(&file_name, resp, source_index, index, total, &callback)
+                        .await?;
+
+                    ReleaseStatus::Updated(journal.has_validator().then_some(journal))
+                };
 
                 if is_release && !source_index.trusted() {
                     let url = format!("{}/{}", dist_path, "Release.gpg");
 
-                    let request = self.request_get_builder(&url, source_index);
+                    let request =
+                        self.request_get_builder(&format!("{real_url}.gpg"), source_index);
                     let resp = request
                         .send()
                         .await
@@ -556,7 +904,7 @@ impl<'a> OmaRefresh<'a> {
                         .await?;
                 }
 
-                Ok((Some(file_name), index))
+                Ok((Some(file_name), index, status))
             }
             OmaSourceEntryFrom::Local => {
                 let dist_path_with_protocol = source_index.dist_path();
@@ -655,7 +1003,7 @@ impl<'a> OmaRefresh<'a> {
                 let name = name
                     .ok_or_else(|| RefreshError::NoInReleaseFile(source_index.url().to_string()))?;
 
-                Ok((Some(name), index))
+                Ok((Some(name), index, ReleaseStatus::Updated(None)))
             }
         }
     }
@@ -665,12 +1013,17 @@ impl<'a> OmaRefresh<'a> {
         url: &str,
         source_index: &OmaSourceEntry<'_>,
     ) -> reqwest::RequestBuilder {
-        let mut request = self.client.get(url);
-        if let Some(auth) = &source_index.auth {
-            request = request.basic_auth(&auth.user, Some(&auth.password))
-        }
+        let client = self
+            .tls_clients
+            .and_then(|x| x.find(url))
+            .unwrap_or(self.client);
 
-        request
+        let request = client.get(url);
+
+        match source_index.auth_for(url).and_then(http_auth) {
+            Some(auth) => auth.apply(request),
+            None => request,
+        }
     }
 
     async fn download_file<F, Fut>(
@@ -712,6 +1065,10 @@ impl<'a> OmaRefresh<'a> {
             })?;
 
         while let Some(chunk) = resp.chunk().await? {
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.acquire(chunk.len() as u64).await;
+            }
+
             callback(Event::DownloadEvent(oma_fetch::Event::ProgressInc {
                 index,
                 size: chunk.len() as u64,
@@ -732,18 +1089,29 @@ impl<'a> OmaRefresh<'a> {
         Ok(())
     }
 
-    async fn collect_all_release_entry(
+    #[allow(clippy::too_many_arguments)]
+    async fn collect_all_release_entry<F, Fut>(
         &self,
         all_inrelease: Vec<String>,
         sourcelist: &[OmaSourceEntry<'a>],
         replacer: &DatabaseFilenameReplacer,
         sources_map: &AHashMap<String, Vec<OmaSourceEntry<'a>>>,
-    ) -> Result<(Vec<DownloadEntry>, u64)> {
+        release_dates: &mut ReleaseDates,
+        callback: &F,
+    ) -> Result<(Vec<DownloadEntry>, u64, Vec<String>, Vec<String>)>
+    where
+        F: Fn(Event) -> Fut,
+        Fut: Future<Output = ()>,
+    {
         let mut total = 0;
         let mut tasks = vec![];
+        let mut patched = vec![];
+        let mut unchanged = vec![];
+        let pdiff = self.apt_config.bool("Acquire::PDiffs", true);
         debug!("all_inrelease: {:?}", all_inrelease);
 
-        let index_target_config = IndexTargetConfig::new(self.apt_config, &self.arch);
+        let index_target_config =
+            IndexTargetConfig::new(self.apt_config, &self.arch, self.appstream);
 
         let archs_from_file = fs::read_to_string("/var/lib/dpkg/arch")
             .await
@@ -752,6 +1120,7 @@ impl<'a> OmaRefresh<'a> {
         for file_name in all_inrelease {
             // 源数据确保是存在的，所以直接 unwrap
             let ose_list = sources_map.get(&file_name).unwrap();
+            let mut key_checked = false;
 
             for ose in ose_list {
                 debug!("Getted oma source entry: {:#?}", ose);
@@ -771,17 +1140,41 @@ impl<'a> OmaRefresh<'a> {
 
                 debug!("archs: {:?}", archs);
 
-                let inrelease = verify_inrelease(
+                let mut policy = self.signature_policy.get(ose.url()).clone();
+
+                // 快照中的签名必然是旧的
+                if ose.is_snapshot() {
+                    policy.max_signature_age = None;
+                }
+
+                let (inrelease, signers) = verify_inrelease(
                     &inrelease,
                     ose.signed_by(),
                     &self.source,
                     &inrelease_path,
                     ose.trusted(),
+                    &policy,
                 )
                 .map_err(|e| {
                     RefreshError::InReleaseParseError(inrelease_path.display().to_string(), e)
                 })?;
 
+                // 同一个 InRelease 文件只需要提醒一次
+                if let Some(window) = self.key_expiry_warning.filter(|_| !key_checked) {
+                    key_checked = true;
+                    let soon = SystemTime::now() + window;
+
+                    for info in signers.iter().filter(|x| x.expires_before(soon)) {
+                        callback(Event::KeyExpiring {
+                            source: format!("{} {}", ose.url(), ose.suite()),
+                            fingerprint: info.fingerprint.clone(),
+                            // expires_before 已确保有过期时间
+                            expires: info.expires.unwrap(),
+                        })
+                        .await;
+                    }
+                }
+
                 let inrelease = InRelease::new(&inrelease).map_err(|e| {
                     RefreshError::InReleaseParseError(inrelease_path.display().to_string(), e)
                 })?;
@@ -793,9 +1186,21 @@ impl<'a> OmaRefresh<'a> {
                         RefreshError::InReleaseParseError(inrelease_path.display().to_string(), e)
                     })?;
 
-                    inrelease.check_valid_until(&now).map_err(|e| {
+                    // 快照中的 Release 文件只需要在快照时间点有效
+                    let valid_at = self
+                        .snapshot
+                        .filter(|_| ose.is_snapshot())
+                        .map(|x| x.time())
+                        .unwrap_or(now);
+
+                    inrelease.check_valid_until(&valid_at).map_err(|e| {
                         RefreshError::InReleaseParseError(inrelease_path.display().to_string(), e)
                     })?;
+
+                    // 使用快照就是有意回到旧的元数据
+                    if !ose.is_snapshot() {
+                        self.check_rollback(ose, &inrelease, &inrelease_path, release_dates)?;
+                    }
                 }
 
                 let checksums = &inrelease
@@ -807,7 +1212,7 @@ impl<'a> OmaRefresh<'a> {
 
                 let mut handle = vec![];
 
-                let download_list = index_target_config.get_download_list(
+                let mut download_list = index_target_config.get_download_list(
                     checksums,
                     ose.is_source(),
                     ose.is_flat(),
@@ -815,6 +1220,60 @@ impl<'a> OmaRefresh<'a> {
                     ose.components(),
                 )?;
 
+                // release 文件未变化时，本地已有的索引文件也无需重新校验
+                if self.not_modified.contains(&file_name) {
+                    let mut rest = vec![];
+
+                    for c in download_list {
+                        let path = database_file_path(&c, ose.dist_path(), &inrelease);
+                        let name = replacer.replace(&path)?;
+
+                        if self.download_dir.join(&name).is_file() {
+                            unchanged.push(name);
+                        } else {
+                            rest.push(c);
+                        }
+                    }
+
+                    download_list = rest;
+                }
+
+                if pdiff {
+                    let old = self
+                        .old_releases
+                        .get(&file_name)
+                        .map(|x| x.as_slice())
+                        .unwrap_or_default();
+                    let inrelease = &inrelease;
+
+                    let results =
+                        futures::stream::iter(download_list.into_iter().map(|c| async move {
+                            let res = self.try_pdiff(&c, ose, inrelease, old, replacer).await;
+                            (c, res)
+                        }))
+                        .buffer_unordered(self.threads)
+                        .collect::<Vec<_>>()
+                        .await;
+
+                    let mut rest = vec![];
+
+                    for (c, res) in results {
+                        match res {
+                            Ok(Some(file_name)) => patched.push(file_name),
+                            Ok(None) => rest.push(c),
+                            Err(e) => {
+                                debug!(
+                                    "pdiff for {} failed: {e}, fallback to full download",
+                                    c.item.name
+                                );
+                                rest.push(c);
+                            }
+                        }
+                    }
+
+                    download_list = rest;
+                }
+
                 get_all_need_db_from_config(download_list, &mut total, checksums, &mut handle);
 
                 for i in &self.flat_repo_no_release {
@@ -839,8 +1298,254 @@ impl<'a> OmaRefresh<'a> {
             }
         }
 
-        Ok((tasks, total))
+        Ok((tasks, total, patched, unchanged))
+    }
+
+    /// 尝试通过 pdiff 把本地已有的索引文件更新到最新，成功则返回文件名
+    ///
+    /// `old` 为刷新前 release 文件中的校验和，索引未变化时不尝试 pdiff
+    async fn try_pdiff(
+        &self,
+        c: &ChecksumDownloadEntry,
+        ose: &OmaSourceEntry<'_>,
+        inrelease: &InRelease,
+        old: &[ChecksumItem],
+        replacer: &DatabaseFilenameReplacer,
+    ) -> Result<Option<String>> {
+        let (checksum_type, checksums) = inrelease.checksum_type_and_list();
+
+        if c.keep_compress
+            || !matches!(checksum_type, InReleaseChecksum::Sha256)
+            || matches!(ose.from()?, OmaSourceEntryFrom::Local)
+        {
+            return Ok(None);
+        }
+
+        let name = if file_is_compress(&c.item.name) {
+            split_ext_and_filename(&c.item.name).1
+        } else {
+            c.item.name.clone()
+        };
+
+        let index_name = format!("{name}.diff/Index");
+
+        let (Some(target), Some(index_item), Some(old)) = (
+            checksums.iter().find(|x| x.name == name),
+            checksums.iter().find(|x| x.name == index_name),
+            old.iter().find(|x| x.name == name),
+        ) else {
+            return Ok(None);
+        };
+
+        // 未变化的索引交给正常流程校验后跳过即可
+        if old.checksum == target.checksum {
+            return Ok(None);
+        }
+
+        let dist_url = ose.dist_path();
+        let url = |path: &str| {
+            if dist_url.ends_with('/') {
+                format!("{}{}", dist_url, path)
+            } else {
+                format!("{}/{}", dist_url, path)
+            }
+        };
+
+        let file_name = replacer.replace(&url(&name))?;
+        let local = self.download_dir.join(&file_name);
+
+        if !local.is_file() {
+            return Ok(None);
+        }
+
+        let target_hash = Checksum::from_sha256_str(&target.checksum)?;
+        // 本地文件应与旧 release 一致，若不一致打补丁后的校验会失败，回退到完整下载
+        let local_hash = Checksum::from_sha256_str(&old.checksum)?;
+
+        let index_dir = format!("{name}.diff");
+        let by_hash = |hash: &str| format!("{index_dir}/by-hash/SHA256/{hash}");
+
+        let index_url = if inrelease.acquire_by_hash() {
+            url(&by_hash(&index_item.checksum))
+        } else {
+            url(&index_name)
+        };
+
+        debug!("Getting pdiff index: {index_url}");
+
+        let mut index = None;
+
+        // mirror+ 源按优先级依次尝试各个镜像，使用快照时从快照下载
+        for source in ose.download_sources(&index_url)? {
+            if !matches!(source.source_type, DownloadSourceType::Http { .. }) {
+                continue;
+            }
+
+            match self
+                .request_get_builder(&source.url, ose)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+            {
+                Ok(resp) => {
+                    index = Some(resp.bytes().await?.to_vec());
+                    break;
+                }
+                Err(e) => debug!("Failed to get pdiff index from {}: {e}", source.url),
+            }
+        }
+
+        let Some(index) = index else {
+            return Ok(None);
+        };
+
+        let index_checksum = Checksum::from_sha256_str(&index_item.checksum)?;
+        if !index_checksum.cmp_read(Box::new(Cursor::new(index.clone())))? {
+            return Err(PDiffError::ChecksumMismatch(index_name).into());
+        }
+
+        let index = PDiffIndex::from_str(&String::from_utf8_lossy(&index))?;
+
+        let Some(patches) = index.patches_for(&local_hash) else {
+            debug!("{} is too old for pdiff", local.display());
+            return Ok(None);
+        };
+
+        // 补丁比完整文件还大时不如直接下载
+        let patch_size = patches.iter().map(|x| x.download.size).sum::<u64>();
+        if patches.is_empty() || patch_size >= c.item.size {
+            return Ok(None);
+        }
+
+        let partial = self.download_dir.join("partial");
+        fs::create_dir_all(&partial).await.map_err(|e| {
+            RefreshError::FailedToOperateDirOrFile(partial.display().to_string(), e)
+        })?;
+
+        let mut tasks = vec![];
+        let mut patch_files = vec![];
+
+        for p in &patches {
+            let download_url = if inrelease.acquire_by_hash() {
+                url(&by_hash(&p.download.checksum))
+            } else {
+                url(&format!("{index_dir}/{}", p.download.name))
+            };
+
+            let patch_file = replacer.replace(&url(&format!("{index_dir}/{}", p.patch.name)))?;
+            patch_files.push(partial.join(&patch_file));
+
+            tasks.push(
+                DownloadEntry::builder()
+                    .source(ose.download_sources(&download_url)?)
+                    .filename(patch_file)
+                    .dir(partial.clone())
+                    .allow_resume(false)
+                    .file_type(CompressFile::Gzip)
+                    .hash(Checksum::from_sha256_str(&p.patch.checksum)?)
+                    .build(),
+            );
+        }
+
+        debug!(
+            "Applying {} pdiff patches to {}",
+            tasks.len(),
+            local.display()
+        );
+
+        let res = DownloadManager::builder()
+            .client(self.client)
+            .download_list(tasks)
+            .threads(self.threads)
+            .max_per_host(self.max_per_host)
+            .retry_policy(self.retry_policy.clone())
+            .maybe_rate_limiter(self.rate_limiter.as_ref())
+            .maybe_low_speed_limit(self.low_speed_limit)
+            .maybe_tls_clients(self.tls_clients)
+            .build()
+            .start_download(|_| async {})
+            .await;
+
+        if let Err(e) = res.into_iter().collect::<DownloadResult<Vec<_>>>() {
+            for p in &patch_files {
+                fs::remove_file(p).await.ok();
+            }
+            return Err(e.into());
+        }
+
+        spawn_blocking(move || apply_pdiff_patches(&local, &patch_files, &target_hash)).await??;
+
+        Ok(Some(file_name))
+    }
+}
+
+/// SHA256 checksums of indexes in local release files, keyed by file name
+async fn old_release_checksums(download_dir: &Path) -> AHashMap<String, Vec<ChecksumItem>> {
+    let mut res = AHashMap::new();
+
+    let Ok(mut dir) = fs::read_dir(download_dir).await else {
+        return res;
+    };
+
+    while let Ok(Some(entry)) = dir.next_entry().await {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if !name.ends_with("InRelease") && !name.ends_with("_Release") {
+            continue;
+        }
+
+        // 这些文件在上次刷新时已经验证过签名
+        let Ok(text) = fs::read_to_string(entry.path()).await else {
+            continue;
+        };
+
+        let Ok(release) = InRelease::new(&clearsigned_text(&text)) else {
+            continue;
+        };
+
+        if let Ok((InReleaseChecksum::Sha256, list)) =
+            release.get_or_try_init_checksum_type_and_list()
+        {
+            res.insert(name, list.clone());
+        }
+    }
+
+    res
+}
+
+fn apply_pdiff_patches(local: &Path, patches: &[PathBuf], target: &Checksum) -> Result<()> {
+    let io_err =
+        |path: &Path, e| RefreshError::FailedToOperateDirOrFile(path.display().to_string(), e);
+
+    let res = (|| {
+        let mut lines = split_lines(&std::fs::read(local).map_err(|e| io_err(local, e))?);
+
+        for p in patches {
+            apply_ed_patch(&mut lines, &std::fs::read(p).map_err(|e| io_err(p, e))?)?;
+        }
+
+        // 不能用 with_extension，文件名中的点号之后的部分会被替换掉
+        let mut tmp = local.as_os_str().to_owned();
+        tmp.push(".pdiff");
+        let tmp = PathBuf::from(tmp);
+
+        std::fs::write(&tmp, join_lines(&lines)).map_err(|e| io_err(&tmp, e))?;
+
+        if !target.cmp_file(&tmp)? {
+            std::fs::remove_file(&tmp).ok();
+            return Err(PDiffError::ChecksumMismatch(local.display().to_string()).into());
+        }
+
+        std::fs::set_permissions(&tmp, Permissions::from_mode(0o644))
+            .and_then(|_| std::fs::rename(&tmp, local))
+            .map_err(|e| io_err(local, e))
+    })();
+
+    for p in patches {
+        std::fs::remove_file(p).ok();
     }
+
+    res
 }
 
 fn content_length(resp: &Response) -> u64 {
@@ -859,7 +1564,8 @@ fn content_length(resp: &Response) -> u64 {
     total_size
 }
 
-fn detect_duplicate_repositories(sourcelist: &[OmaSourceEntry<'_>]) -> Result<()> {
+/// Error if the same component of a repository is configured more than once
+pub fn detect_duplicate_repositories(sourcelist: &[OmaSourceEntry<'_>]) -> Result<()> {
     let mut map = AHashMap::new();
 
     for i in sourcelist {
@@ -930,15 +1636,31 @@ fn get_all_need_db_from_config(
     }
 }
 
-async fn remove_unused_db(download_dir: PathBuf, download_list: Vec<String>) -> Result<()> {
+/// Remove files not in `download_list`, only files starting with `prefixes` are removed if given
+async fn remove_unused_db(
+    download_dir: PathBuf,
+    download_list: Vec<String>,
+    prefixes: Option<Vec<String>>,
+) -> Result<()> {
     let mut download_dir = fs::read_dir(&download_dir)
         .await
         .map_err(|e| RefreshError::ReadDownloadDir(download_dir.display().to_string(), e))?;
 
+    let prefixes = prefixes.map(|x| {
+        x.iter()
+            .map(|p| format!("{}_", p.trim_end_matches('_')))
+            .collect::<Vec<_>>()
+    });
+
     while let Ok(Some(x)) = download_dir.next_entry().await {
+        let name = x.file_name().to_string_lossy().to_string();
+
         if x.path().is_file()
-            && !download_list.contains(&x.file_name().to_string_lossy().to_string())
-            && x.file_name() != "lock"
+            && !download_list.contains(&name)
+            && name != "lock"
+            && prefixes
+                .as_ref()
+                .is_none_or(|p| p.iter().any(|p| name.starts_with(p)))
         {
             debug!("Removing {:?}", x.file_name());
             if let Err(e) = fs::remove_file(x.path()).await {
@@ -950,6 +1672,19 @@ async fn remove_unused_db(download_dir: PathBuf, download_list: Vec<String>) ->
     Ok(())
 }
 
+/// Whether `ose` matches `filter` by URI, sources.list file or suite
+fn source_matches(ose: &OmaSourceEntry<'_>, filter: &str) -> bool {
+    if ose.url().trim_end_matches('/') == filter.trim_end_matches('/') || ose.suite() == filter {
+        return true;
+    }
+
+    ose.file().is_some_and(|file| {
+        file == Path::new(filter)
+            || file.file_name().is_some_and(|x| x == filter)
+            || file.file_stem().is_some_and(|x| x == filter)
+    })
+}
+
 fn download_flat_repo_no_release(
     source_index: &OmaSourceEntry,
     download_dir: &Path,
@@ -960,23 +1695,10 @@ fn download_flat_repo_no_release(
 
     let dist_url = source_index.dist_path();
 
-    let from = match source_index.from()? {
-        OmaSourceEntryFrom::Http => DownloadSourceType::Http {
-            auth: source_index
-                .auth
-                .as_ref()
-                .map(|auth| (auth.user.clone(), auth.password.clone())),
-        },
-        OmaSourceEntryFrom::Local => DownloadSourceType::Local(source_index.is_flat()),
-    };
-
     let download_url = format!("{}/Packages", dist_url);
     let file_path = format!("{}Packages", dist_url);
 
-    let sources = vec![DownloadSource {
-        url: download_url.clone(),
-        source_type: from,
-    }];
+    let sources = source_index.download_sources(&download_url)?;
 
     let task = DownloadEntry::builder()
         .source(sources)
@@ -993,6 +1715,29 @@ fn download_flat_repo_no_release(
     Ok(())
 }
 
+/// 索引文件在本地保存的路径（未经 DatabaseFilenameReplacer 转换）
+pub(crate) fn database_file_path(
+    c: &ChecksumDownloadEntry,
+    dist_url: &str,
+    inrelease: &InRelease,
+) -> String {
+    if c.keep_compress && inrelease.acquire_by_hash() {
+        return format!("{}/{}", dist_url, c.item.name);
+    }
+
+    let name = if c.keep_compress || !file_is_compress(&c.item.name) {
+        Cow::Borrowed(&c.item.name)
+    } else {
+        Cow::Owned(split_ext_and_filename(&c.item.name).1)
+    };
+
+    if dist_url.ends_with('/') {
+        format!("{}{}", dist_url, name)
+    } else {
+        format!("{}/{}", dist_url, name)
+    }
+}
+
 fn collect_download_task(
     c: &ChecksumDownloadEntry,
     source_index: &OmaSourceEntry,
@@ -1007,16 +1752,6 @@ fn collect_download_task(
 
     let dist_url = &source_index.dist_path();
 
-    let from = match source_index.from()? {
-        OmaSourceEntryFrom::Http => DownloadSourceType::Http {
-            auth: source_index
-                .auth
-                .as_ref()
-                .map(|auth| (auth.user.clone(), auth.password.clone())),
-        },
-        OmaSourceEntryFrom::Local => DownloadSourceType::Local(source_index.is_flat()),
-    };
-
     let not_compress_filename_before = if file_is_compress(&c.item.name) {
         Cow::Owned(split_ext_and_filename(&c.item.name).1)
     } else {
@@ -1053,22 +1788,9 @@ fn collect_download_task(
         format!("{}/{}", dist_url, c.item.name)
     };
 
-    let sources = vec![DownloadSource {
-        url: download_url.clone(),
-        source_type: from,
-    }];
+    let sources = source_index.download_sources(&download_url)?;
 
-    let file_path = if c.keep_compress {
-        if inrelease.acquire_by_hash() {
-            Cow::Owned(format!("{}/{}", dist_url, c.item.name))
-        } else {
-            Cow::Borrowed(&download_url)
-        }
-    } else if dist_url.ends_with('/') {
-        Cow::Owned(format!("{}{}", dist_url, not_compress_filename_before))
-    } else {
-        Cow::Owned(format!("{}/{}", dist_url, not_compress_filename_before))
-    };
+    let file_path = database_file_path(c, dist_url, inrelease);
 
     let task = DownloadEntry::builder()
         .source(sources)
@@ -1,7 +1,7 @@
 use chrono::{DateTime, FixedOffset, ParseError, Utc};
 use deb822_lossless::{FromDeb822, FromDeb822Paragraph, Paragraph};
 use oma_apt_sources_lists::Signature;
-use oma_repo_verify::verify_release;
+use oma_repo_verify::{keyring::CertInfo, verify_release, SignaturePolicy, VerifyError};
 use once_cell::sync::OnceCell;
 use std::{
     borrow::Cow,
@@ -126,17 +126,22 @@ impl InRelease {
         })
     }
 
-    pub fn check_date(&self, now: &DateTime<Utc>) -> Result<(), InReleaseError> {
+    /// `Date` field of the Release file
+    pub fn date(&self) -> Result<DateTime<FixedOffset>, InReleaseError> {
         let date = self
             .source
             .date
             .as_ref()
             .ok_or(InReleaseError::BadInReleaseData)?;
 
-        let date = parse_date(date).map_err(|e| {
+        parse_date(date).map_err(|e| {
             debug!("Parse data failed: {}", e);
             InReleaseError::BadInReleaseData
-        })?;
+        })
+    }
+
+    pub fn check_date(&self, now: &DateTime<Utc>) -> Result<(), InReleaseError> {
+        let date = self.date()?;
 
         if now < &date {
             return Err(InReleaseError::EarlierSignature);
@@ -196,6 +201,26 @@ impl FromStr for ChecksumItem {
     }
 }
 
+/// Signed text of a clearsigned message, or `s` itself if it is not clearsigned
+///
+/// The signature is not checked, only use this for files which were verified before.
+pub(crate) fn clearsigned_text(s: &str) -> Cow<'_, str> {
+    if !s.starts_with("-----BEGIN PGP SIGNED MESSAGE-----") {
+        return Cow::Borrowed(s);
+    }
+
+    let text = s
+        .lines()
+        .skip_while(|x| !x.is_empty())
+        .skip(1)
+        .take_while(|x| *x != "-----BEGIN PGP SIGNATURE-----")
+        .map(|x| x.strip_prefix("- ").unwrap_or(x))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Cow::Owned(text)
+}
+
 fn get_checksums_inner(checksum_str: &str) -> Result<Vec<ChecksumItem>, InReleaseError> {
     checksum_str
         .trim()
@@ -204,20 +229,23 @@ fn get_checksums_inner(checksum_str: &str) -> Result<Vec<ChecksumItem>, InReleas
         .collect::<Result<Vec<_>, InReleaseError>>()
 }
 
+/// Returns the signed content and the certs which signed it
 pub fn verify_inrelease<'a>(
     inrelease: &'a str,
     signed_by: &Option<Signature>,
     rootfs: impl AsRef<Path>,
     file: impl AsRef<Path>,
     trusted: bool,
-) -> Result<Cow<'a, str>, InReleaseError> {
+    policy: &SignaturePolicy,
+) -> Result<(Cow<'a, str>, Vec<CertInfo>), InReleaseError> {
     if inrelease.starts_with("-----BEGIN PGP SIGNED MESSAGE-----") {
-        Ok(Cow::Owned(oma_repo_verify::verify_inrelease(
-            inrelease, signed_by, rootfs,
-        )?))
+        let (text, signers) =
+            oma_repo_verify::verify_inrelease(inrelease, signed_by, rootfs, policy)?;
+
+        Ok((Cow::Owned(text), signers))
     } else {
         if trusted {
-            return Ok(Cow::Borrowed(inrelease));
+            return Ok((Cow::Borrowed(inrelease), vec![]));
         }
 
         let inrelease_path = file.as_ref();
@@ -240,12 +268,19 @@ pub fn verify_inrelease<'a>(
         let bytes =
             fs::read(pub_file).map_err(|e| InReleaseError::ReadGPG(e, file_name.to_string()))?;
 
-        verify_release(inrelease, &bytes, signed_by, rootfs).map_err(|e| {
-            debug!("{e}");
-            InReleaseError::NotTrusted
-        })?;
+        let signers =
+            verify_release(inrelease, &bytes, signed_by, rootfs, policy).map_err(|e| {
+                debug!("{e}");
+                match e {
+                    // 被签名策略拒绝时报告具体原因
+                    VerifyError::BadKey(..)
+                    | VerifyError::BadSignature(..)
+                    | VerifyError::SignatureTooOld(..) => InReleaseError::VerifyError(e),
+                    _ => InReleaseError::NotTrusted,
+                }
+            })?;
 
-        Ok(Cow::Borrowed(inrelease))
+        Ok((Cow::Borrowed(inrelease), signers))
     }
 }
 
@@ -383,3 +418,20 @@ fn test_checksum_parse() {
         }
     );
 }
+
+#[test]
+fn test_clearsigned_text() {
+    let signed = "-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA512\n\nOrigin: Debian\n- -dashed\nSHA256:\n e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855 0 main/binary-amd64/Packages\n-----BEGIN PGP SIGNATURE-----\n\nxxxx\n-----END PGP SIGNATURE-----\n";
+
+    assert_eq!(
+        clearsigned_text(signed),
+        "Origin: Debian\n-dashed\nSHA256:\n e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855 0 main/binary-amd64/Packages"
+    );
+
+    let signed = signed.replace("- -dashed\n", "");
+    let release = InRelease::new(&clearsigned_text(&signed)).unwrap();
+    let (_, checksums) = release.get_or_try_init_checksum_type_and_list().unwrap();
+    assert_eq!(checksums[0].name, "main/binary-amd64/Packages");
+
+    assert_eq!(clearsigned_text("Origin: Debian\n"), "Origin: Debian\n");
+}
@@ -1,5 +1,11 @@
 mod config;
 pub mod db;
 pub mod inrelease;
-mod sourceslist;
+mod pdiff;
+mod release_date;
+pub mod repo;
+pub mod report;
+pub mod snapshot;
+pub mod sourceslist;
 mod util;
+pub mod verify;
@@ -0,0 +1,262 @@
+use std::{num::ParseIntError, str::FromStr};
+
+use deb822_lossless::{FromDeb822, FromDeb822Paragraph, Paragraph};
+use oma_fetch::checksum::Checksum;
+
+use crate::inrelease::{ChecksumItem, InReleaseError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PDiffError {
+    #[error("Bad pdiff Index file")]
+    BadIndex,
+    #[error("Bad pdiff patch: {0}")]
+    BadPatch(String),
+    #[error("Checksum mismatch after applying pdiff: {0}")]
+    ChecksumMismatch(String),
+    #[error(transparent)]
+    InRelease(#[from] InReleaseError),
+}
+
+impl From<ParseIntError> for PDiffError {
+    fn from(value: ParseIntError) -> Self {
+        PDiffError::BadPatch(value.to_string())
+    }
+}
+
+#[derive(Debug, FromDeb822)]
+struct PDiffIndexEntry {
+    #[deb822(field = "SHA256-Current")]
+    current: Option<String>,
+    #[deb822(field = "SHA256-History")]
+    history: Option<String>,
+    #[deb822(field = "SHA256-Patches")]
+    patches: Option<String>,
+    #[deb822(field = "SHA256-Download")]
+    download: Option<String>,
+    #[deb822(field = "X-Patch-Precedence")]
+    patch_precedence: Option<String>,
+}
+
+/// `Packages.diff/Index` and friends
+#[derive(Debug)]
+pub struct PDiffIndex {
+    pub current: String,
+    /// 打补丁前文件的 checksum，name 为对应的补丁名
+    pub history: Vec<ChecksumItem>,
+    /// 解压后补丁的 checksum
+    pub patches: Vec<ChecksumItem>,
+    /// 压缩后补丁的 checksum
+    pub download: Vec<ChecksumItem>,
+    /// 每个补丁都可以直接把对应的历史版本更新到最新版本
+    pub merged: bool,
+}
+
+/// A patch to download and apply
+#[derive(Debug, PartialEq, Eq)]
+pub struct PDiffPatch<'a> {
+    pub patch: &'a ChecksumItem,
+    pub download: &'a ChecksumItem,
+}
+
+impl FromStr for PDiffIndex {
+    type Err = PDiffError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source: Paragraph = s.parse().map_err(|_| PDiffError::BadIndex)?;
+        let source: PDiffIndexEntry =
+            FromDeb822Paragraph::from_paragraph(&source).map_err(|_| PDiffError::BadIndex)?;
+
+        let current = source
+            .current
+            .as_deref()
+            .and_then(|x| x.split_ascii_whitespace().next())
+            .ok_or(PDiffError::BadIndex)?
+            .to_string();
+
+        let parse = |s: Option<&str>| {
+            s.ok_or(PDiffError::BadIndex)?
+                .trim()
+                .lines()
+                .map(|x| ChecksumItem::from_str(x).map_err(PDiffError::from))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(Self {
+            current,
+            history: parse(source.history.as_deref())?,
+            patches: parse(source.patches.as_deref())?,
+            download: parse(source.download.as_deref())?,
+            merged: source.patch_precedence.as_deref() == Some("merged"),
+        })
+    }
+}
+
+impl PDiffIndex {
+    /// Patches needed to update file with checksum `local` to current, returns `None` if
+    /// `local` is not in history.
+    pub fn patches_for(&self, local: &Checksum) -> Option<Vec<PDiffPatch<'_>>> {
+        let is_local = |hash: &str| Checksum::from_sha256_str(hash).is_ok_and(|x| &x == local);
+
+        if is_local(&self.current) {
+            return Some(vec![]);
+        }
+
+        let pos = self.history.iter().position(|x| is_local(&x.checksum))?;
+
+        let names = if self.merged {
+            &self.history[pos..=pos]
+        } else {
+            &self.history[pos..]
+        };
+
+        names
+            .iter()
+            .map(|h| {
+                let patch = self.patches.iter().find(|x| x.name == h.name)?;
+                let download = self
+                    .download
+                    .iter()
+                    .find(|x| x.name.strip_suffix(".gz") == Some(&h.name))?;
+
+                Some(PDiffPatch { patch, download })
+            })
+            .collect()
+    }
+}
+
+/// Apply an ed-style patch (as produced by `diff --ed`) to `lines`
+pub fn apply_ed_patch(lines: &mut Vec<Vec<u8>>, patch: &[u8]) -> Result<(), PDiffError> {
+    let mut patch = patch.split(|x| *x == b'\n');
+    // 最后一次插入的行，用于 `s/.//`
+    let mut current = None;
+
+    while let Some(cmd) = patch.next() {
+        if cmd.is_empty() {
+            continue;
+        }
+
+        let cmd = std::str::from_utf8(cmd)
+            .map_err(|_| PDiffError::BadPatch(String::from_utf8_lossy(cmd).to_string()))?;
+
+        match cmd {
+            // diff --ed 无法直接表示只有 "." 的行，会先写入 ".." 再用 s/.// 修正
+            "s/.//" => {
+                if let Some(line) = current.and_then(|x: usize| lines.get_mut(x)) {
+                    if line.first() == Some(&b'.') {
+                        line.remove(0);
+                    }
+                }
+                continue;
+            }
+            "w" | "q" => continue,
+            _ => {}
+        }
+
+        let bad_patch = || PDiffError::BadPatch(cmd.to_string());
+
+        let (range, op) = cmd.split_at(cmd.len() - 1);
+        let (start, end) = match range.split_once(',') {
+            Some((start, end)) => (start.parse::<usize>()?, end.parse::<usize>()?),
+            None => {
+                let n = range.parse::<usize>()?;
+                (n, n)
+            }
+        };
+
+        if start > end || end > lines.len() || (start == 0 && op != "a") {
+            return Err(bad_patch());
+        }
+
+        match op {
+            "d" => {
+                lines.drain(start - 1..end);
+                current = None;
+            }
+            "a" | "c" => {
+                let mut new = vec![];
+
+                loop {
+                    let line = patch.next().ok_or_else(bad_patch)?;
+                    if line == b"." {
+                        break;
+                    }
+                    new.push(line.to_vec());
+                }
+
+                let at = if op == "c" {
+                    lines.drain(start - 1..end);
+                    start - 1
+                } else {
+                    start
+                };
+
+                current = (at + new.len()).checked_sub(1);
+                lines.splice(at..at, new);
+            }
+            _ => return Err(bad_patch()),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn split_lines(data: &[u8]) -> Vec<Vec<u8>> {
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+
+    if data.is_empty() {
+        return vec![];
+    }
+
+    data.split(|x| *x == b'\n').map(|x| x.to_vec()).collect()
+}
+
+pub fn join_lines(lines: &[Vec<u8>]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(lines.iter().map(|x| x.len() + 1).sum());
+
+    for line in lines {
+        res.extend_from_slice(line);
+        res.push(b'\n');
+    }
+
+    res
+}
+
+#[test]
+fn test_apply_ed_patch() {
+    let mut lines = split_lines(b"a\nb\nc\nd\ne\n");
+    let patch = b"5c\nE\n.\n3,4d\n1a\nb1\n..\n.\ns/.//\n0a\nstart\n.\n";
+
+    apply_ed_patch(&mut lines, patch).unwrap();
+
+    assert_eq!(join_lines(&lines), b"start\na\nb1\n.\nb\nE\n");
+}
+
+#[test]
+fn test_pdiff_index() {
+    let index =
+        "SHA256-Current: 2f1a6bdbb49cbee21ab4ab4bc6c4c4ea13a4b8c99ac9b1e0e1eca2e2e7f1e1c2 100
+SHA256-History:
+ 1111111111111111111111111111111111111111111111111111111111111111 90 T-2024-01-01-0000.00
+ 2222222222222222222222222222222222222222222222222222222222222222 95 T-2024-01-02-0000.00
+SHA256-Patches:
+ 3333333333333333333333333333333333333333333333333333333333333333 10 T-2024-01-01-0000.00
+ 4444444444444444444444444444444444444444444444444444444444444444 12 T-2024-01-02-0000.00
+SHA256-Download:
+ 5555555555555555555555555555555555555555555555555555555555555555 8 T-2024-01-01-0000.00.gz
+ 6666666666666666666666666666666666666666666666666666666666666666 9 T-2024-01-02-0000.00.gz
+";
+
+    let index = PDiffIndex::from_str(index).unwrap();
+    assert!(!index.merged);
+
+    let local = Checksum::from_sha256_str(&"1".repeat(64)).unwrap();
+    let patches = index.patches_for(&local).unwrap();
+    assert_eq!(patches.len(), 2);
+    assert_eq!(patches[1].download.name, "T-2024-01-02-0000.00.gz");
+
+    let local = Checksum::from_sha256_str(&"2".repeat(64)).unwrap();
+    assert_eq!(index.patches_for(&local).unwrap().len(), 1);
+
+    let local = Checksum::from_sha256_str(&"7".repeat(64)).unwrap();
+    assert!(index.patches_for(&local).is_none());
+}
@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+use ahash::AHashMap;
+use tokio::fs;
+use tracing::debug;
+
+use crate::db::RefreshError;
+
+/// 记录每个源最后一次接受的 Release 文件 `Date`，用于拒绝元数据回滚
+const RELEASE_DATES_PATH: &str = "var/lib/oma/release-dates";
+
+/// Last accepted `Date` (unix timestamp) of Release files, keyed by dist path
+#[derive(Debug)]
+pub(crate) struct ReleaseDates {
+    path: PathBuf,
+    inner: AHashMap<String, i64>,
+}
+
+impl ReleaseDates {
+    pub async fn load(sysroot: &Path) -> Self {
+        let path = sysroot.join(RELEASE_DATES_PATH);
+
+        let inner = match fs::read_to_string(&path).await {
+            Ok(s) => parse(&s),
+            Err(e) => {
+                debug!("Failed to read {}: {e}", path.display());
+                AHashMap::new()
+            }
+        };
+
+        Self { path, inner }
+    }
+
+    /// Last accepted date of `dist_path`
+    pub fn get(&self, dist_path: &str) -> Option<i64> {
+        self.inner.get(dist_path).copied()
+    }
+
+    pub fn insert(&mut self, dist_path: &str, date: i64) {
+        self.inner.insert(dist_path.to_string(), date);
+    }
+
+    pub async fn save(&self) -> Result<(), RefreshError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await.map_err(|e| {
+                RefreshError::FailedToOperateDirOrFile(parent.display().to_string(), e)
+            })?;
+        }
+
+        let mut entries = self.inner.iter().collect::<Vec<_>>();
+        entries.sort();
+
+        let s = entries
+            .into_iter()
+            .map(|(dist_path, date)| format!("{dist_path}\t{date}\n"))
+            .collect::<String>();
+
+        // 先写入临时文件再重命名，避免写入中断导致记录丢失
+        let tmp = self.path.with_extension("tmp");
+
+        fs::write(&tmp, s)
+            .await
+            .map_err(|e| RefreshError::FailedToOperateDirOrFile(tmp.display().to_string(), e))?;
+
+        fs::rename(&tmp, &self.path).await.map_err(|e| {
+            RefreshError::FailedToOperateDirOrFile(self.path.display().to_string(), e)
+        })?;
+
+        Ok(())
+    }
+}
+
+fn parse(s: &str) -> AHashMap<String, i64> {
+    s.lines()
+        .filter_map(|line| {
+            let (dist_path, date) = line.rsplit_once('\t')?;
+            Some((dist_path.to_string(), date.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_release_dates() {
+    let map = parse(
+        "https://repo.aosc.io/debs/dists/stable\t1700000000\nbroken line\nfile:/srv/repo/dists/sid\t1\n",
+    );
+
+    assert_eq!(map.len(), 2);
+    assert_eq!(map["https://repo.aosc.io/debs/dists/stable"], 1700000000);
+    assert_eq!(map["file:/srv/repo/dists/sid"], 1);
+}
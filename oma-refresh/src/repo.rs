@@ -0,0 +1,567 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use bon::Builder;
+use chrono::Utc;
+use faster_hex::hex_string;
+use flate2::{write::GzEncoder, Compression};
+use oma_repo_verify::{ar, sign_release};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RepoError {
+    #[error("Failed to operate dir or file {0}: {1}")]
+    FailedToOperateDirOrFile(String, io::Error),
+    #[error("Failed to read package {0}: {1}")]
+    BadDebFile(String, io::Error),
+    #[error("Failed to sign Release file: {0}")]
+    SignReleaseFailed(oma_repo_verify::VerifyError),
+}
+
+type Result<T> = std::result::Result<T, RepoError>;
+
+/// Layout of the generated repository
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RepoLayout {
+    /// All indexes are in the root directory, e.g. `deb file:/srv/repo ./`
+    #[default]
+    Flat,
+    /// Indexes are in `dists/<suite>/<component>`, e.g. `deb file:/srv/repo local main`
+    Pool,
+}
+
+/// Generate `Packages`, `Contents-<arch>` and `Release`/`InRelease` from .deb files in `dir`
+#[derive(Builder)]
+pub struct LocalRepo {
+    #[builder(into)]
+    dir: PathBuf,
+    #[builder(default)]
+    layout: RepoLayout,
+    #[builder(into, default = "local".to_string())]
+    suite: String,
+    #[builder(into, default = "main".to_string())]
+    component: String,
+    #[builder(into, default = "local".to_string())]
+    origin: String,
+    /// OpenPGP secret key to sign Release file, the repository is unsigned if not set
+    sign_key: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct RepoBuildResult {
+    /// Number of packages in the repository
+    pub packages: usize,
+    pub archs: Vec<String>,
+    /// Directory of the Release file
+    pub release_dir: PathBuf,
+    pub signed: bool,
+}
+
+/// A .deb file in the repository
+struct DebEntry {
+    name: String,
+    arch: String,
+    section: Option<String>,
+    /// Packages index entry
+    stanza: String,
+    files: Vec<String>,
+}
+
+impl LocalRepo {
+    pub fn build(&self) -> Result<RepoBuildResult> {
+        let debs = self.scan()?;
+
+        // arch => entries
+        let mut groups: BTreeMap<&str, Vec<&DebEntry>> = BTreeMap::new();
+        for deb in &debs {
+            groups.entry(&deb.arch).or_default().push(deb);
+        }
+
+        // 没有软件包时仍然生成空索引，否则 apt 会因找不到索引而报错
+        if groups.is_empty() {
+            groups.insert("all", vec![]);
+        }
+
+        let release_dir = match self.layout {
+            RepoLayout::Flat => self.dir.clone(),
+            RepoLayout::Pool => self.dir.join("dists").join(&self.suite),
+        };
+
+        // Release 中的相对路径
+        let mut indexes = vec![];
+
+        for (arch, entries) in &groups {
+            let (packages, contents) = match self.layout {
+                RepoLayout::Flat => ("Packages".to_string(), format!("Contents-{arch}")),
+                RepoLayout::Pool => (
+                    format!("{}/binary-{arch}/Packages", self.component),
+                    format!("{}/Contents-{arch}", self.component),
+                ),
+            };
+
+            // flat 源只有一个 Packages，包含所有架构的软件包
+            if self.layout == RepoLayout::Pool || !indexes.contains(&packages) {
+                let stanzas = match self.layout {
+                    RepoLayout::Flat => debs.iter().map(|x| x.stanza.as_str()).collect::<Vec<_>>(),
+                    RepoLayout::Pool => entries.iter().map(|x| x.stanza.as_str()).collect(),
+                };
+
+                write_index(&release_dir, &packages, &stanzas.join("\n"))?;
+                indexes.push(packages);
+            }
+
+            write_index(&release_dir, &contents, &contents_index(entries))?;
+            indexes.push(contents);
+        }
+
+        let archs = groups.keys().map(|x| x.to_string()).collect::<Vec<_>>();
+        let release = self.release(&release_dir, &indexes, &archs)?;
+
+        write_file(&release_dir.join("Release"), release.as_bytes())?;
+
+        let signed = if let Some(key) = &self.sign_key {
+            let (inrelease, detached) =
+                sign_release(&release, key).map_err(RepoError::SignReleaseFailed)?;
+            write_file(&release_dir.join("InRelease"), inrelease.as_bytes())?;
+            write_file(&release_dir.join("Release.gpg"), detached.as_bytes())?;
+            true
+        } else {
+            // 避免留下与新 Release 不符的旧签名
+            for name in ["InRelease", "Release.gpg"] {
+                let path = release_dir.join(name);
+                if path.is_file() {
+                    fs::remove_file(&path).map_err(|e| {
+                        RepoError::FailedToOperateDirOrFile(path.display().to_string(), e)
+                    })?;
+                }
+            }
+            false
+        };
+
+        Ok(RepoBuildResult {
+            packages: debs.len(),
+            archs,
+            release_dir,
+            signed,
+        })
+    }
+
+    /// Read all .deb files in the repository directory
+    fn scan(&self) -> Result<Vec<DebEntry>> {
+        let mut paths = vec![];
+        find_debs(&self.dir, &mut paths)?;
+        paths.sort();
+
+        let mut res = vec![];
+
+        for path in paths {
+            debug!("Scanning {}", path.display());
+
+            let filename = path
+                .strip_prefix(&self.dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            let (control, files) = read_deb(&path)
+                .map_err(|e| RepoError::BadDebFile(path.display().to_string(), e))?;
+
+            let field = |name: &str| control_field(&control, name);
+
+            let (Some(name), Some(arch)) = (field("Package"), field("Architecture")) else {
+                return Err(RepoError::BadDebFile(
+                    path.display().to_string(),
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "missing Package or Architecture",
+                    ),
+                ));
+            };
+
+            let (size, sha256) =
+                hash_file(&path).map_err(|e| RepoError::BadDebFile(filename.clone(), e))?;
+
+            let stanza = format!(
+                "{}\nFilename: {filename}\nSize: {size}\nSHA256: {sha256}\n",
+                control.trim_end()
+            );
+
+            res.push(DebEntry {
+                section: field("Section"),
+                name,
+                arch,
+                stanza,
+                files,
+            });
+        }
+
+        res.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(res)
+    }
+
+    fn release(&self, release_dir: &Path, indexes: &[String], archs: &[String]) -> Result<String> {
+        let mut release = format!(
+            "Origin: {}\nLabel: {}\nSuite: {}\nCodename: {}\nDate: {}\nArchitectures: {}\n",
+            self.origin,
+            self.origin,
+            self.suite,
+            self.suite,
+            Utc::now().to_rfc2822(),
+            archs.join(" "),
+        );
+
+        if self.layout == RepoLayout::Pool {
+            release.push_str(&format!("Components: {}\n", self.component));
+        }
+
+        release.push_str("SHA256:\n");
+
+        for index in indexes {
+            for name in [index.to_string(), format!("{index}.gz")] {
+                let path = release_dir.join(&name);
+                let (size, sha256) = hash_file(&path).map_err(|e| {
+                    RepoError::FailedToOperateDirOrFile(path.display().to_string(), e)
+                })?;
+
+                release.push_str(&format!(" {sha256} {size:>16} {name}\n"));
+            }
+        }
+
+        Ok(release)
+    }
+}
+
+/// Read control file and file list of a .deb package
+fn read_deb(path: &Path) -> io::Result<(String, Vec<String>)> {
+    let mut control = None;
+    let mut files = None;
+
+    for m in ar::members(&mut File::open(path)?)? {
+        let name = m.name.as_str();
+
+        if name.starts_with("control.tar") {
+            let mut tar = tar::Archive::new(decompress(name, ar::open_member(path, &m)?)?);
+            for f in tar.entries()? {
+                let mut f = f?;
+                if f.path()?.to_string_lossy().trim_start_matches("./") == "control" {
+                    let mut s = String::new();
+                    f.read_to_string(&mut s)?;
+                    control = Some(s);
+                    break;
+                }
+            }
+        } else if name.starts_with("data.tar") {
+            let mut tar = tar::Archive::new(decompress(name, ar::open_member(path, &m)?)?);
+            let mut list = vec![];
+            for f in tar.entries()? {
+                let f = f?;
+                if f.header().entry_type().is_dir() {
+                    continue;
+                }
+                let path = f.path()?.to_string_lossy().to_string();
+                list.push(path.trim_start_matches("./").to_string());
+            }
+            files = Some(list);
+        }
+    }
+
+    let control = control
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no control file in package"))?;
+
+    Ok((control, files.unwrap_or_default()))
+}
+
+fn decompress<'a>(name: &str, r: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match name.rsplit_once('.').map(|x| x.1) {
+        Some("gz") => Box::new(flate2::read::GzDecoder::new(r)),
+        Some("xz") => Box::new(xz2::read::XzDecoder::new(r)),
+        Some("zst") => Box::new(zstd::Decoder::new(r)?),
+        Some("tar") => Box::new(r),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported member {name}"),
+            ))
+        }
+    })
+}
+
+/// Collect .deb files under `dir` recursively, symlinked directories are not followed
+fn find_debs(dir: &Path, res: &mut Vec<PathBuf>) -> Result<()> {
+    let err = |e| RepoError::FailedToOperateDirOrFile(dir.display().to_string(), e);
+
+    for entry in fs::read_dir(dir).map_err(err)? {
+        let entry = entry.map_err(err)?;
+        let path = entry.path();
+
+        if entry.file_type().map_err(err)?.is_dir() {
+            find_debs(&path, res)?;
+        } else if path.extension().is_some_and(|x| x == "deb") && path.is_file() {
+            res.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Value of a single-line field in control file
+fn control_field(control: &str, name: &str) -> Option<String> {
+    control.lines().find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        (k == name).then(|| v.trim().to_string())
+    })
+}
+
+/// Contents index, each line is `<path> <section>/<package>,...`
+fn contents_index(entries: &[&DebEntry]) -> String {
+    let mut map: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+
+    for entry in entries {
+        let pkg = match &entry.section {
+            Some(section) => format!("{section}/{}", entry.name),
+            None => entry.name.clone(),
+        };
+
+        for file in &entry.files {
+            map.entry(file).or_default().push(pkg.clone());
+        }
+    }
+
+    map.into_iter()
+        .map(|(file, pkgs)| format!("{file}\t{}\n", pkgs.join(",")))
+        .collect()
+}
+
+/// Write `name` and `name.gz` in `dir`
+fn write_index(dir: &Path, name: &str, content: &str) -> Result<()> {
+    let path = dir.join(name);
+    write_file(&path, content.as_bytes())?;
+
+    let gz = dir.join(format!("{name}.gz"));
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder
+        .write_all(content.as_bytes())
+        .and_then(|_| encoder.finish())
+        .and_then(|buf| fs::write(&gz, buf))
+        .map_err(|e| RepoError::FailedToOperateDirOrFile(gz.display().to_string(), e))
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| RepoError::FailedToOperateDirOrFile(parent.display().to_string(), e))?;
+    }
+
+    fs::write(path, content)
+        .map_err(|e| RepoError::FailedToOperateDirOrFile(path.display().to_string(), e))
+}
+
+/// (size, SHA256) of a file
+fn hash_file(path: &Path) -> io::Result<(u64, String)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut sha256 = Sha256::new();
+    let mut size = 0;
+    let mut buf = [0; 8192];
+
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        sha256.update(&buf[..n]);
+        size += n as u64;
+    }
+
+    Ok((size, hex_string(&sha256.finalize())))
+}
+
+#[test]
+fn test_contents_index() {
+    let control = "Package: fish\nVersion: 3.7.1\nArchitecture: amd64\nSection: shells\nDescription: friendly shell\n more";
+    assert_eq!(control_field(control, "Section").as_deref(), Some("shells"));
+    assert_eq!(control_field(control, "Source"), None);
+
+    let fish = DebEntry {
+        name: "fish".to_string(),
+        arch: "amd64".to_string(),
+        section: control_field(control, "Section"),
+        stanza: String::new(),
+        files: vec![
+            "usr/bin/fish".to_string(),
+            "usr/share/man/man1/fish.1.gz".to_string(),
+        ],
+    };
+
+    let fish_common = DebEntry {
+        name: "fish-common".to_string(),
+        arch: "amd64".to_string(),
+        section: None,
+        stanza: String::new(),
+        files: vec!["usr/bin/fish".to_string()],
+    };
+
+    assert_eq!(
+        contents_index(&[&fish, &fish_common]),
+        "usr/bin/fish\tshells/fish,fish-common\nusr/share/man/man1/fish.1.gz\tshells/fish\n"
+    );
+}
+
+#[test]
+fn test_build_repo() {
+    fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *content).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn write_deb(path: &Path, name: &str, arch: &str) {
+        let control = format!(
+            "Package: {name}\nVersion: 1.0\nArchitecture: {arch}\nSection: utils\nDescription: test\n"
+        );
+        let bin = format!("./usr/bin/{name}");
+        let members = [
+            ("debian-binary", b"2.0\n".to_vec()),
+            (
+                "control.tar.gz",
+                tar_gz(&[("./control", control.as_bytes())]),
+            ),
+            ("data.tar.gz", tar_gz(&[(&bin, b"#!/bin/sh\n")])),
+        ];
+
+        let mut f = File::create(path).unwrap();
+        f.write_all(b"!<arch>\n").unwrap();
+        for (name, data) in members {
+            writeln!(
+                f,
+                "{name:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`",
+                0,
+                0,
+                0,
+                100644,
+                data.len()
+            )
+            .unwrap();
+            f.write_all(&data).unwrap();
+            if data.len() % 2 == 1 {
+                f.write_all(b"\n").unwrap();
+            }
+        }
+    }
+
+    /// Check hashes of all indexes listed in Release, returns their names
+    fn check_release(release_dir: &Path) -> Vec<String> {
+        let release = fs::read_to_string(release_dir.join("Release")).unwrap();
+
+        release
+            .lines()
+            .skip_while(|x| *x != "SHA256:")
+            .skip(1)
+            .map(|line| {
+                let entry = line.split_whitespace().collect::<Vec<_>>();
+                let (size, sha256) = hash_file(&release_dir.join(entry[2])).unwrap();
+                assert_eq!(entry[0], sha256);
+                assert_eq!(entry[1], size.to_string());
+                entry[2].to_string()
+            })
+            .collect()
+    }
+
+    /// Check hashes of all packages listed in Packages, returns their names
+    fn check_packages(dir: &Path, packages: &Path) -> Vec<String> {
+        let packages = fs::read_to_string(packages).unwrap();
+
+        packages
+            .split("\n\n")
+            .filter(|x| !x.trim().is_empty())
+            .map(|stanza| {
+                let field = |name| control_field(stanza, name).unwrap();
+                let (size, sha256) = hash_file(&dir.join(field("Filename"))).unwrap();
+                assert_eq!(field("Size"), size.to_string());
+                assert_eq!(field("SHA256"), sha256);
+                field("Package")
+            })
+            .collect()
+    }
+
+    let dir = std::env::temp_dir().join(format!("oma-repo-test-{}", std::process::id()));
+    fs::create_dir_all(dir.join("pool")).unwrap();
+    write_deb(&dir.join("pool/fish_1.0_amd64.deb"), "fish", "amd64");
+    write_deb(&dir.join("hello_1.0_arm64.deb"), "hello", "arm64");
+
+    let res = LocalRepo::builder().dir(&dir).build().build().unwrap();
+    assert_eq!(res.packages, 2);
+    assert_eq!(res.archs, vec!["amd64", "arm64"]);
+    assert_eq!(res.release_dir, dir);
+    assert!(!res.signed);
+    assert_eq!(
+        check_release(&dir),
+        vec![
+            "Packages",
+            "Packages.gz",
+            "Contents-amd64",
+            "Contents-amd64.gz",
+            "Contents-arm64",
+            "Contents-arm64.gz"
+        ]
+    );
+    assert_eq!(
+        check_packages(&dir, &dir.join("Packages")),
+        vec!["fish", "hello"]
+    );
+
+    let res = LocalRepo::builder()
+        .dir(&dir)
+        .layout(RepoLayout::Pool)
+        .build()
+        .build()
+        .unwrap();
+    let release_dir = dir.join("dists/local");
+    assert_eq!(res.release_dir, release_dir);
+    assert_eq!(
+        check_release(&release_dir),
+        vec![
+            "main/binary-amd64/Packages",
+            "main/binary-amd64/Packages.gz",
+            "main/Contents-amd64",
+            "main/Contents-amd64.gz",
+            "main/binary-arm64/Packages",
+            "main/binary-arm64/Packages.gz",
+            "main/Contents-arm64",
+            "main/Contents-arm64.gz"
+        ]
+    );
+    assert_eq!(
+        check_packages(&dir, &release_dir.join("main/binary-amd64/Packages")),
+        vec!["fish"]
+    );
+    assert_eq!(
+        check_packages(&dir, &release_dir.join("main/binary-arm64/Packages")),
+        vec!["hello"]
+    );
+
+    // 没有软件包时仍然生成空的 Packages
+    let empty = dir.join("empty");
+    fs::create_dir_all(&empty).unwrap();
+    let res = LocalRepo::builder().dir(&empty).build().build().unwrap();
+    assert_eq!(res.packages, 0);
+    assert_eq!(res.archs, vec!["all"]);
+    assert_eq!(
+        check_release(&empty),
+        vec!["Packages", "Packages.gz", "Contents-all", "Contents-all.gz"]
+    );
+    assert!(check_packages(&empty, &empty.join("Packages")).is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
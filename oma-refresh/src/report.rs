@@ -0,0 +1,274 @@
+use std::path::{Path, PathBuf};
+
+use ahash::AHashMap;
+use serde::Serialize;
+use tokio::fs;
+use tracing::debug;
+
+/// A package version in Packages index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageVersion {
+    pub name: String,
+    pub arch: String,
+    pub version: String,
+}
+
+/// A new version of a package which already exists before refresh
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NewVersion {
+    pub name: String,
+    pub arch: String,
+    pub version: String,
+    pub old_versions: Vec<String>,
+}
+
+/// What changed in a source after refresh
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SourceReport {
+    /// Dist path of the source, e.g. `https://deb.debian.org/debian/dists/bookworm`
+    pub source: String,
+    pub suite: String,
+    /// Whether this is a security suite, see [`is_security`]
+    pub security: bool,
+    pub added: Vec<PackageVersion>,
+    pub removed: Vec<PackageVersion>,
+    pub new_versions: Vec<NewVersion>,
+}
+
+impl SourceReport {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.new_versions.is_empty()
+    }
+}
+
+/// (name, arch) => versions
+type Index = AHashMap<(String, String), Vec<String>>;
+
+/// A source to report, files of this source start with `prefix`
+pub(crate) struct ReportSource {
+    pub prefix: String,
+    pub dist_path: String,
+    pub suite: String,
+}
+
+/// Packages indexes before refresh
+pub(crate) struct IndexSnapshot {
+    download_dir: PathBuf,
+    sources: Vec<ReportSource>,
+    inner: Vec<Option<Index>>,
+}
+
+impl IndexSnapshot {
+    pub async fn new(download_dir: &Path, sources: Vec<ReportSource>) -> Self {
+        let mut inner = vec![];
+
+        for source in &sources {
+            inner.push(read_index(download_dir, &source.prefix).await);
+        }
+
+        Self {
+            download_dir: download_dir.to_path_buf(),
+            sources,
+            inner,
+        }
+    }
+
+    /// Compare with the Packages indexes now
+    pub async fn report(self) -> Vec<SourceReport> {
+        let mut res = vec![];
+
+        for (source, old) in self.sources.into_iter().zip(self.inner) {
+            // 第一次刷新的源没有可以对比的数据
+            let Some(old) = old else {
+                continue;
+            };
+
+            let new = read_index(&self.download_dir, &source.prefix)
+                .await
+                .unwrap_or_default();
+
+            let mut report = diff(&old, &new);
+
+            if report.is_empty() {
+                continue;
+            }
+
+            report.security = is_security(&self.download_dir, &source).await;
+            report.source = source.dist_path;
+            report.suite = source.suite;
+
+            res.push(report);
+        }
+
+        res
+    }
+}
+
+/// Whether a source carries security updates
+///
+/// Release files have no field for this, so it is a heuristic: the `Label` of the
+/// Release file mentions security (e.g. `Debian-Security`), or the suite ends with
+/// `-security` (e.g. Ubuntu's `noble-security`, labelled just `Ubuntu`).
+async fn is_security(download_dir: &Path, source: &ReportSource) -> bool {
+    let mut label = None;
+
+    for name in ["InRelease", "Release"] {
+        let path = download_dir.join(format!("{}{name}", source.prefix));
+        if let Ok(text) = fs::read_to_string(path).await {
+            label = release_field(&text, "Label").map(|x| x.to_ascii_lowercase());
+            break;
+        }
+    }
+
+    label.is_some_and(|x| x.contains("security")) || source.suite.ends_with("-security")
+}
+
+fn release_field<'a>(text: &'a str, field: &str) -> Option<&'a str> {
+    text.lines()
+        .find_map(|x| x.strip_prefix(field)?.strip_prefix(':'))
+        .map(|x| x.trim())
+}
+
+/// Read all Packages indexes starting with `prefix`, returns `None` if there is no such file
+async fn read_index(download_dir: &Path, prefix: &str) -> Option<Index> {
+    let mut dir = fs::read_dir(download_dir).await.ok()?;
+    let mut res: Option<Index> = None;
+
+    while let Ok(Some(entry)) = dir.next_entry().await {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if !name.starts_with(prefix) || !name.ends_with("_Packages") {
+            continue;
+        }
+
+        let text = match fs::read_to_string(entry.path()).await {
+            Ok(text) => text,
+            Err(e) => {
+                debug!("Failed to read {name}: {e}");
+                continue;
+            }
+        };
+
+        let index = res.get_or_insert_default();
+
+        for (name, arch, version) in parse_packages(&text) {
+            let versions = index.entry((name, arch)).or_default();
+            if !versions.contains(&version) {
+                versions.push(version);
+            }
+        }
+    }
+
+    res
+}
+
+/// (Package, Architecture, Version) of each stanza in Packages index
+fn parse_packages(text: &str) -> Vec<(String, String, String)> {
+    let mut res = vec![];
+
+    for stanza in text.split("\n\n") {
+        let mut name = None;
+        let mut arch = None;
+        let mut version = None;
+
+        for line in stanza.lines() {
+            if let Some(v) = line.strip_prefix("Package: ") {
+                name = Some(v.trim());
+            } else if let Some(v) = line.strip_prefix("Architecture: ") {
+                arch = Some(v.trim());
+            } else if let Some(v) = line.strip_prefix("Version: ") {
+                version = Some(v.trim());
+            }
+        }
+
+        if let (Some(name), Some(arch), Some(version)) = (name, arch, version) {
+            res.push((name.to_string(), arch.to_string(), version.to_string()));
+        }
+    }
+
+    res
+}
+
+fn diff(old: &Index, new: &Index) -> SourceReport {
+    let mut report = SourceReport::default();
+
+    for ((name, arch), versions) in new {
+        match old.get(&(name.to_string(), arch.to_string())) {
+            None => {
+                for version in versions {
+                    report.added.push(PackageVersion {
+                        name: name.to_string(),
+                        arch: arch.to_string(),
+                        version: version.to_string(),
+                    });
+                }
+            }
+            Some(old_versions) => {
+                for version in versions {
+                    if !old_versions.contains(version) {
+                        report.new_versions.push(NewVersion {
+                            name: name.to_string(),
+                            arch: arch.to_string(),
+                            version: version.to_string(),
+                            old_versions: old_versions.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    for ((name, arch), versions) in old {
+        if new.contains_key(&(name.to_string(), arch.to_string())) {
+            continue;
+        }
+
+        for version in versions {
+            report.removed.push(PackageVersion {
+                name: name.to_string(),
+                arch: arch.to_string(),
+                version: version.to_string(),
+            });
+        }
+    }
+
+    report.added.sort_by(|a, b| a.name.cmp(&b.name));
+    report.removed.sort_by(|a, b| a.name.cmp(&b.name));
+    report.new_versions.sort_by(|a, b| a.name.cmp(&b.name));
+
+    report
+}
+
+#[test]
+fn test_diff_packages() {
+    let index = |text: &str| {
+        let mut index = Index::default();
+        for (name, arch, version) in parse_packages(text) {
+            index.entry((name, arch)).or_default().push(version);
+        }
+        index
+    };
+
+    let old = index(
+        "Package: fish\nVersion: 3.7.0\nArchitecture: amd64\n\nPackage: zsh\nVersion: 5.9\nArchitecture: amd64\nDescription: Z shell\n",
+    );
+    let new = index(
+        "Package: fish\nVersion: 3.7.1\nArchitecture: amd64\n\nPackage: bash\nVersion: 5.2\nArchitecture: amd64\n",
+    );
+
+    let report = diff(&old, &new);
+
+    assert_eq!(report.added.len(), 1);
+    assert_eq!(report.added[0].name, "bash");
+    assert_eq!(report.removed.len(), 1);
+    assert_eq!(report.removed[0].name, "zsh");
+    assert_eq!(
+        report.new_versions,
+        vec![NewVersion {
+            name: "fish".to_string(),
+            arch: "amd64".to_string(),
+            version: "3.7.1".to_string(),
+            old_versions: vec!["3.7.0".to_string()],
+        }]
+    );
+}
@@ -0,0 +1,172 @@
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use tokio::fs;
+use tracing::debug;
+
+use crate::db::RefreshError;
+
+/// 记录使用中的快照时间和 URL 模板，之后的 oma refresh 会继续使用同一快照
+const SNAPSHOT_PATH: &str = "var/lib/oma/snapshot";
+
+/// snapshot.debian.org style URL template, `@ARCHIVE@` is the last path segment of source URL
+/// (e.g. `debian`, `debian-security`) and `@SNAPSHOTID@` is the snapshot time (e.g. `20260901T000000Z`)
+pub const DEFAULT_SNAPSHOT_URL: &str =
+    "https://snapshot.debian.org/archive/@ARCHIVE@/@SNAPSHOTID@/";
+
+/// Archives available from [`DEFAULT_SNAPSHOT_URL`], other sources are not rewritten
+pub const DEFAULT_SNAPSHOT_ARCHIVES: &[&str] =
+    &["debian", "debian-security", "debian-ports", "debian-debug"];
+
+/// A point in time of the archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot(DateTime<Utc>);
+
+impl FromStr for Snapshot {
+    type Err = RefreshError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Self(time.to_utc()));
+        }
+
+        for fmt in [
+            "%Y%m%dT%H%M%SZ",
+            "%Y-%m-%dT%H:%MZ",
+            "%Y-%m-%dT%H:%M:%SZ",
+            "%Y-%m-%d %H:%M",
+        ] {
+            if let Ok(time) = NaiveDateTime::parse_from_str(s, fmt) {
+                return Ok(Self(time.and_utc()));
+            }
+        }
+
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .ok()
+            .and_then(|x| x.and_hms_opt(0, 0, 0))
+            .map(|x| Self(x.and_utc()))
+            .ok_or_else(|| RefreshError::InvalidSnapshot(s.to_string()))
+    }
+}
+
+impl Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.format("%Y-%m-%dT%H:%M:%SZ"))
+    }
+}
+
+impl Snapshot {
+    pub fn time(&self) -> DateTime<Utc> {
+        self.0
+    }
+
+    /// Snapshot ID used in snapshot URL, e.g. `20260901T000000Z`
+    pub fn id(&self) -> String {
+        self.0.format("%Y%m%dT%H%M%SZ").to_string()
+    }
+
+    /// URL of `source_url` in this snapshot, `None` if its archive is not one of `archives`
+    pub fn url(&self, template: &str, archives: &[String], source_url: &str) -> Option<String> {
+        let archive = source_url
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default();
+
+        if !archives.iter().any(|x| x == archive) {
+            return None;
+        }
+
+        Some(
+            template
+                .replace("@ARCHIVE@", archive)
+                .replace("@SNAPSHOTID@", &self.id())
+                .trim_end_matches('/')
+                .to_string(),
+        )
+    }
+
+    /// Snapshot and its URL template recorded by the last refresh
+    pub async fn load(sysroot: impl AsRef<Path>) -> Option<(Self, String)> {
+        let path = sysroot.as_ref().join(SNAPSHOT_PATH);
+
+        let s = match fs::read_to_string(&path).await {
+            Ok(s) => s,
+            Err(e) => {
+                debug!("Failed to read {}: {e}", path.display());
+                return None;
+            }
+        };
+
+        let (id, template) = s.trim().split_once('\t')?;
+
+        Some((id.parse().ok()?, template.to_string()))
+    }
+
+    pub async fn save(
+        &self,
+        sysroot: impl AsRef<Path>,
+        template: &str,
+    ) -> Result<(), RefreshError> {
+        let path = sysroot.as_ref().join(SNAPSHOT_PATH);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.map_err(|e| {
+                RefreshError::FailedToOperateDirOrFile(parent.display().to_string(), e)
+            })?;
+        }
+
+        fs::write(&path, format!("{}\t{template}\n", self.id()))
+            .await
+            .map_err(|e| RefreshError::FailedToOperateDirOrFile(path.display().to_string(), e))
+    }
+
+    /// Stop using snapshot in later refreshes
+    pub async fn clear(sysroot: impl AsRef<Path>) -> Result<(), RefreshError> {
+        let path = sysroot.as_ref().join(SNAPSHOT_PATH);
+
+        if !path.exists() {
+            return Ok(());
+        }
+
+        fs::remove_file(&path)
+            .await
+            .map_err(|e| RefreshError::FailedToOperateDirOrFile(path.display().to_string(), e))
+    }
+}
+
+#[test]
+fn test_snapshot() {
+    let snapshot: Snapshot = "2026-09-01T00:00Z".parse().unwrap();
+    assert_eq!(snapshot.id(), "20260901T000000Z");
+    assert_eq!(snapshot, "20260901T000000Z".parse().unwrap());
+    assert_eq!(snapshot, "2026-09-01".parse().unwrap());
+    assert_eq!(snapshot, "2026-09-01T08:00:00+08:00".parse().unwrap());
+    assert!("yesterday".parse::<Snapshot>().is_err());
+
+    let archives = DEFAULT_SNAPSHOT_ARCHIVES
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        snapshot
+            .url(
+                DEFAULT_SNAPSHOT_URL,
+                &archives,
+                "http://security.debian.org/debian-security/"
+            )
+            .as_deref(),
+        Some("https://snapshot.debian.org/archive/debian-security/20260901T000000Z")
+    );
+    assert_eq!(
+        snapshot.url(
+            DEFAULT_SNAPSHOT_URL,
+            &archives,
+            "https://packages.example.com/apt"
+        ),
+        None
+    );
+}
@@ -1,7 +1,8 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use apt_auth_config::AuthConfigEntry;
+use apt_auth_config::{Auth, AuthConfigEntry};
 use oma_apt_sources_lists::{Signature, SourceEntry, SourceLine, SourceListType, SourcesLists};
+use oma_fetch::{mirrorlist::is_mirror_list, DownloadSource, DownloadSourceType, HttpAuth};
 use once_cell::sync::OnceCell;
 use url::Url;
 
@@ -16,6 +17,17 @@ pub struct OmaSourceEntry<'a> {
     dist_path: OnceCell<String>,
     from: OnceCell<OmaSourceEntryFrom>,
     pub auth: Option<AuthConfigEntry>,
+    mirrors: Vec<Mirror>,
+    file: Option<PathBuf>,
+    /// Download from this snapshot url instead
+    snapshot: Option<String>,
+}
+
+/// A mirror from the mirror list of `mirror+` source
+#[derive(Debug, Clone)]
+pub struct Mirror {
+    pub url: String,
+    pub auth: Option<AuthConfigEntry>,
 }
 
 pub fn sources_lists(
@@ -30,13 +42,19 @@ pub fn sources_lists(
             SourceListType::SourceLine(ref lines) => {
                 for i in lines {
                     if let SourceLine::Entry(entry) = i {
-                        res.push(OmaSourceEntry::new(entry.clone(), arch));
+                        if entry.enabled {
+                            let mut ose = OmaSourceEntry::new(entry.clone(), arch);
+                            ose.set_file(&file.path);
+                            res.push(ose);
+                        }
                     }
                 }
             }
             SourceListType::Deb822(ref e) => {
-                for i in &e.entries {
-                    res.push(OmaSourceEntry::new(i.clone(), arch));
+                for i in e.entries.iter().filter(|x| x.enabled) {
+                    let mut ose = OmaSourceEntry::new(i.clone(), arch);
+                    ose.set_file(&file.path);
+                    res.push(ose);
                 }
             }
         }
@@ -49,6 +67,7 @@ pub fn sources_lists(
 pub enum OmaSourceEntryFrom {
     Http,
     Local,
+    MirrorList,
 }
 
 impl<'a> OmaSourceEntry<'a> {
@@ -61,13 +80,27 @@ impl<'a> OmaSourceEntry<'a> {
             dist_path: OnceCell::new(),
             from: OnceCell::new(),
             auth: None,
+            mirrors: vec![],
+            file: None,
+            snapshot: None,
         }
     }
 
+    /// The sources.list file this entry comes from
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn set_file(&mut self, file: &Path) {
+        self.file = Some(file.to_path_buf());
+    }
+
     pub fn from(&self) -> Result<&OmaSourceEntryFrom, RefreshError> {
         self.from.get_or_try_init(|| {
             let url = self.source.url();
-            if url.starts_with("http") {
+            if is_mirror_list(url) {
+                Ok(OmaSourceEntryFrom::MirrorList)
+            } else if url.starts_with("http") {
                 Ok(OmaSourceEntryFrom::Http)
             } else if url.starts_with("file") {
                 Ok(OmaSourceEntryFrom::Local)
@@ -159,6 +192,313 @@ impl<'a> OmaSourceEntry<'a> {
     pub fn set_auth(&mut self, auth: AuthConfigEntry) {
         self.auth = Some(auth);
     }
+
+    /// Set mirrors of `mirror+` source, in priority order
+    pub fn set_mirrors(&mut self, mirrors: Vec<Mirror>) {
+        self.mirrors = mirrors;
+    }
+
+    /// Download from `url` of an archive snapshot instead of the source url
+    pub fn set_snapshot(&mut self, url: String) {
+        self.snapshot = Some(url);
+    }
+
+    /// Downloaded from an archive snapshot
+    pub fn is_snapshot(&self) -> bool {
+        self.snapshot.is_some()
+    }
+
+    /// Auth for `url`, which may be on one of the mirrors
+    pub fn auth_for(&self, url: &str) -> Option<&AuthConfigEntry> {
+        // 不要把源的认证信息发给快照服务器
+        if self.snapshot.is_some() {
+            return None;
+        }
+
+        if self.mirrors.is_empty() {
+            return self.auth.as_ref();
+        }
+
+        self.mirrors
+            .iter()
+            .find(|x| url.starts_with(x.url.trim_end_matches('/')))
+            .and_then(|x| x.auth.as_ref())
+    }
+
+    /// Where to download `url` under this source from, mirror list source yields every mirror
+    pub fn download_sources(&self, url: &str) -> Result<Vec<DownloadSource>, RefreshError> {
+        let http = |url: String, auth: Option<&AuthConfigEntry>| DownloadSource {
+            url,
+            source_type: DownloadSourceType::Http {
+                auth: auth.and_then(http_auth),
+            },
+        };
+
+        let path = || {
+            url.strip_prefix(self.url())
+                .map(|x| x.trim_start_matches('/'))
+                .ok_or_else(|| RefreshError::InvalidUrl(url.to_string()))
+        };
+
+        let res = match self.from()? {
+            OmaSourceEntryFrom::Http => match &self.snapshot {
+                Some(snapshot) => vec![http(format!("{}/{}", snapshot, path()?), None)],
+                None => vec![http(url.to_string(), self.auth.as_ref())],
+            },
+            OmaSourceEntryFrom::Local => vec![DownloadSource {
+                url: url.to_string(),
+                source_type: DownloadSourceType::Local(self.is_flat()),
+            }],
+            OmaSourceEntryFrom::MirrorList => {
+                let path = path()?;
+
+                self.mirrors
+                    .iter()
+                    .map(|m| {
+                        let url = format!("{}/{}", m.url.trim_end_matches('/'), path);
+
+                        if url.starts_with("file:") {
+                            DownloadSource {
+                                url,
+                                source_type: DownloadSourceType::Local(self.is_flat()),
+                            }
+                        } else {
+                            http(url, m.auth.as_ref())
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(res)
+    }
+}
+
+pub(crate) fn http_auth(entry: &AuthConfigEntry) -> Option<HttpAuth> {
+    entry.auth.as_ref().map(|auth| match auth {
+        Auth::Basic { user, password } => HttpAuth::Basic {
+            user: user.clone(),
+            password: password.clone(),
+        },
+        Auth::Bearer(token) => HttpAuth::Bearer(token.clone()),
+    })
+}
+
+/// Render `entry` as a one-line style (`.list`) source
+pub fn one_line_entry(entry: &SourceEntry) -> String {
+    let mut options = vec![];
+
+    if let Some(archs) = &entry.archs {
+        options.push(format!("arch={}", archs.join(",")));
+    }
+
+    // 单行格式无法内嵌公钥
+    if let Some(Signature::KeyPath(paths)) = &entry.signed_by {
+        let paths = paths
+            .iter()
+            .map(|x| x.to_string_lossy())
+            .collect::<Vec<_>>();
+        options.push(format!("signed-by={}", paths.join(",")));
+    }
+
+    if entry.trusted {
+        options.push("trusted=yes".to_string());
+    }
+
+    for (k, v) in &entry.options {
+        let k = if entry.is_deb822 {
+            one_line_option_name(k)
+        } else {
+            k.to_string()
+        };
+        options.push(format!("{k}={}", v.join(",")));
+    }
+
+    let mut res = String::new();
+
+    if !entry.enabled {
+        res.push_str("# ");
+    }
+
+    res.push_str(if entry.source { "deb-src " } else { "deb " });
+
+    if !options.is_empty() {
+        res.push_str(&format!("[{}] ", options.join(" ")));
+    }
+
+    res.push_str(&entry.url);
+    res.push(' ');
+    res.push_str(&entry.suite);
+
+    for c in &entry.components {
+        res.push(' ');
+        res.push_str(c);
+    }
+
+    res
+}
+
+/// Render `entries` as deb822 style (`.sources`) stanzas, entries differing only in suite are merged
+pub fn deb822_entries(entries: &[SourceEntry]) -> String {
+    let mut stanzas: Vec<(&SourceEntry, Vec<&str>)> = vec![];
+
+    for entry in entries {
+        let same = stanzas.iter_mut().find(|(x, _)| {
+            x.enabled == entry.enabled
+                && x.source == entry.source
+                && x.url == entry.url
+                && x.components == entry.components
+                && x.archs == entry.archs
+                && x.signed_by == entry.signed_by
+                && x.trusted == entry.trusted
+                && x.options == entry.options
+        });
+
+        match same {
+            Some((_, suites)) => suites.push(&entry.suite),
+            None => stanzas.push((entry, vec![&entry.suite])),
+        }
+    }
+
+    let mut res = vec![];
+
+    for (entry, suites) in stanzas {
+        let mut stanza = String::new();
+
+        if !entry.enabled {
+            stanza.push_str("Enabled: no\n");
+        }
+
+        stanza.push_str(if entry.source {
+            "Types: deb-src\n"
+        } else {
+            "Types: deb\n"
+        });
+        stanza.push_str(&format!("URIs: {}\n", entry.url));
+        stanza.push_str(&format!("Suites: {}\n", suites.join(" ")));
+
+        if !entry.components.is_empty() {
+            stanza.push_str(&format!("Components: {}\n", entry.components.join(" ")));
+        }
+
+        if let Some(archs) = &entry.archs {
+            stanza.push_str(&format!("Architectures: {}\n", archs.join(" ")));
+        }
+
+        match &entry.signed_by {
+            Some(Signature::KeyPath(paths)) => {
+                let paths = paths
+                    .iter()
+                    .map(|x| x.to_string_lossy())
+                    .collect::<Vec<_>>();
+                stanza.push_str(&format!("Signed-By: {}\n", paths.join(" ")));
+            }
+            Some(Signature::KeyBlock(block)) => {
+                stanza.push_str("Signed-By:\n");
+                // 多行字段中的空行需要写为 " ."
+                for line in block.trim().lines() {
+                    let line = line.trim();
+                    if line.is_empty() || line == "." {
+                        stanza.push_str(" .\n");
+                    } else {
+                        stanza.push_str(&format!(" {line}\n"));
+                    }
+                }
+            }
+            None => {}
+        }
+
+        if entry.trusted {
+            stanza.push_str("Trusted: yes\n");
+        }
+
+        for (k, v) in &entry.options {
+            let k = if entry.is_deb822 {
+                k.to_string()
+            } else {
+                deb822_option_name(k)
+            };
+            stanza.push_str(&format!("{k}: {}\n", v.join(" ")));
+        }
+
+        res.push(stanza);
+    }
+
+    res.join("\n")
+}
+
+/// Option names that can not be converted by capitalizing each word
+const OPTION_NAMES: &[(&str, &str)] = &[
+    ("lang", "Languages"),
+    ("target", "Targets"),
+    ("pdiffs", "PDiffs"),
+];
+
+fn deb822_option_name(name: &str) -> String {
+    if let Some((_, v)) = OPTION_NAMES.iter().find(|(k, _)| *k == name) {
+        return v.to_string();
+    }
+
+    name.split('-')
+        .map(|x| {
+            let mut c = x.chars();
+            match c.next() {
+                Some(first) => first.to_uppercase().chain(c).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn one_line_option_name(name: &str) -> String {
+    if let Some((k, _)) = OPTION_NAMES.iter().find(|(_, v)| *v == name) {
+        return k.to_string();
+    }
+
+    name.to_lowercase()
+}
+
+#[test]
+fn test_render_entries() {
+    let mut entries = "deb [arch=amd64,arm64 signed-by=/usr/share/keyrings/debian.gpg by-hash=yes] https://deb.debian.org/debian bookworm main contrib
+deb [arch=amd64,arm64 signed-by=/usr/share/keyrings/debian.gpg by-hash=yes] https://deb.debian.org/debian bookworm-updates main contrib
+deb-src https://deb.debian.org/debian bookworm main"
+        .lines()
+        .map(|x| match x.parse::<SourceLine>().unwrap() {
+            SourceLine::Entry(e) => e,
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+
+    entries[2].enabled = false;
+
+    assert_eq!(
+        one_line_entry(&entries[0]),
+        "deb [arch=amd64,arm64 signed-by=/usr/share/keyrings/debian.gpg by-hash=yes] https://deb.debian.org/debian bookworm main contrib"
+    );
+    assert_eq!(
+        one_line_entry(&entries[2]),
+        "# deb-src https://deb.debian.org/debian bookworm main"
+    );
+
+    assert_eq!(
+        deb822_entries(&entries),
+        "Types: deb
+URIs: https://deb.debian.org/debian
+Suites: bookworm bookworm-updates
+Components: main contrib
+Architectures: amd64 arm64
+Signed-By: /usr/share/keyrings/debian.gpg
+By-Hash: yes
+
+Enabled: no
+Types: deb-src
+URIs: https://deb.debian.org/debian
+Suites: bookworm
+Components: main
+"
+    );
 }
 
 #[test]
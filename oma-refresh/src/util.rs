@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 
 use aho_corasick::AhoCorasick;
+use oma_fetch::mirrorlist::mirror_list_location;
 use url::Url;
 
 use crate::db::RefreshError;
@@ -20,6 +21,10 @@ impl DatabaseFilenameReplacer {
     }
 
     pub fn replace(&self, url: &str) -> Result<String, RefreshError> {
+        // 与 apt 相同，mirror+ 源的文件名不包含镜像列表的协议
+        let location = mirror_list_location(url);
+        let url = location.as_deref().unwrap_or(url);
+
         let url_parsed = Url::parse(url).map_err(|_| RefreshError::InvalidUrl(url.to_string()))?;
 
         let host = url_parsed.host_str();
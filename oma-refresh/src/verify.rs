@@ -0,0 +1,403 @@
+use std::path::{Path, PathBuf};
+
+use bon::Builder;
+use oma_apt::config::Config;
+use oma_fetch::checksum::Checksum;
+use oma_repo_verify::{SignaturePolicies, SignaturePolicy};
+use oma_utils::dpkg::dpkg_arch;
+use tokio::{fs, task::spawn_blocking};
+use tracing::debug;
+
+use crate::{
+    config::{ChecksumDownloadEntry, IndexTargetConfig},
+    db::{database_file_path, get_apt_update_lock, RefreshError},
+    inrelease::{
+        file_is_compress, split_ext_and_filename, verify_inrelease, InRelease, InReleaseChecksum,
+    },
+    sourceslist::{sources_lists, OmaSourceEntry},
+    util::DatabaseFilenameReplacer,
+};
+
+type Result<T> = std::result::Result<T, RefreshError>;
+
+/// Why a local list file is considered broken
+#[derive(Debug)]
+pub enum ListProblem {
+    /// Release file can not be verified, all indexes of this source are untrusted
+    BadSignature(String),
+    ChecksumMismatch,
+    /// Listed in the Release file but not on disk
+    Missing,
+}
+
+#[derive(Debug)]
+pub struct ListIssue {
+    pub path: PathBuf,
+    pub problem: ListProblem,
+    /// The file has been deleted
+    pub deleted: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyResult {
+    /// Number of files checked
+    pub checked: usize,
+    pub issues: Vec<ListIssue>,
+}
+
+/// Re-verify downloaded lists against their (In)Release files, without network access
+#[derive(Builder)]
+pub struct VerifyLists<'a> {
+    source: PathBuf,
+    download_dir: PathBuf,
+    apt_config: &'a Config,
+    /// Delete files which do not match
+    #[builder(default)]
+    delete: bool,
+    #[builder(default)]
+    signature_policy: SignaturePolicies,
+    /// Expect DEP-11 metadata even if apt is not configured to download it
+    #[builder(default)]
+    appstream: bool,
+}
+
+impl VerifyLists<'_> {
+    pub async fn run(&self) -> Result<VerifyResult> {
+        let download_dir: Box<Path> = Box::from(self.download_dir.as_path());
+
+        // 删除文件时不能有其他进程在刷新
+        spawn_blocking(move || get_apt_update_lock(&download_dir))
+            .await
+            .unwrap()?;
+
+        let arch = dpkg_arch(&self.source)?;
+        let sourcelist = sources_lists(&self.source, &arch)?;
+        let replacer = DatabaseFilenameReplacer::new()?;
+        let index_target_config = IndexTargetConfig::new(self.apt_config, &arch, self.appstream);
+
+        let archs_from_file = fs::read_to_string(self.source.join("var/lib/dpkg/arch"))
+            .await
+            .map(|f| f.lines().map(|x| x.to_string()).collect::<Vec<_>>());
+
+        let mut res = VerifyResult::default();
+        let mut verified = vec![];
+
+        for ose in &sourcelist {
+            let Some((release_path, release)) = self.release_file(ose, &replacer).await? else {
+                debug!("{} has not been refreshed yet", ose.dist_path());
+                continue;
+            };
+
+            // 同一个 Release 文件可能对应多个源条目
+            let first = !verified.contains(&release_path);
+
+            if first {
+                verified.push(release_path.clone());
+                res.checked += 1;
+            }
+
+            // 只检查文件是否被篡改，已下载的列表签名过旧不代表其损坏
+            let policy = SignaturePolicy {
+                max_signature_age: None,
+                ..self.signature_policy.get(ose.url()).clone()
+            };
+
+            let release = match verify_inrelease(
+                &release,
+                ose.signed_by(),
+                &self.source,
+                &release_path,
+                ose.trusted(),
+                &policy,
+            ) {
+                Ok((release, _)) => release,
+                Err(e) => {
+                    if first {
+                        self.untrusted(ose, &release_path, e.to_string(), &replacer, &mut res)
+                            .await?;
+                    }
+                    continue;
+                }
+            };
+
+            let inrelease = InRelease::new(&release).map_err(|e| {
+                RefreshError::InReleaseParseError(release_path.display().to_string(), e)
+            })?;
+
+            let (checksum_type, checksums) = inrelease
+                .get_or_try_init_checksum_type_and_list()
+                .map_err(|e| {
+                    RefreshError::InReleaseParseError(release_path.display().to_string(), e)
+                })?;
+
+            let mut archs = if let Some(archs) = ose.archs() {
+                archs.iter().map(|x| x.as_str()).collect::<Vec<_>>()
+            } else if let Ok(ref f) = archs_from_file {
+                f.iter().map(|x| x.as_str()).collect::<Vec<_>>()
+            } else {
+                vec![arch.as_str()]
+            };
+
+            let download_list = index_target_config.get_download_list(
+                checksums,
+                ose.is_source(),
+                ose.is_flat(),
+                &mut archs,
+                ose.components(),
+            )?;
+
+            for c in download_list {
+                let path = self.download_dir.join(replacer.replace(&database_file_path(
+                    &c,
+                    ose.dist_path(),
+                    &inrelease,
+                ))?);
+
+                if verified.contains(&path) {
+                    continue;
+                }
+
+                verified.push(path.clone());
+
+                let Some(checksum) = expected_checksum(&c, &inrelease, checksum_type)? else {
+                    debug!("No checksum of {} to verify", path.display());
+                    continue;
+                };
+
+                if !path.is_file() {
+                    res.issues.push(ListIssue {
+                        path,
+                        problem: ListProblem::Missing,
+                        deleted: false,
+                    });
+                    continue;
+                }
+
+                res.checked += 1;
+
+                let matches = {
+                    let path = path.clone();
+                    spawn_blocking(move || checksum.cmp_file(&path)).await??
+                };
+
+                if !matches {
+                    let deleted = self.remove(&path).await?;
+                    res.issues.push(ListIssue {
+                        path,
+                        problem: ListProblem::ChecksumMismatch,
+                        deleted,
+                    });
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Local InRelease or Release file of `ose`
+    async fn release_file(
+        &self,
+        ose: &OmaSourceEntry<'_>,
+        replacer: &DatabaseFilenameReplacer,
+    ) -> Result<Option<(PathBuf, String)>> {
+        for name in ["InRelease", "Release"] {
+            let url = format!("{}/{}", ose.dist_path().trim_end_matches('/'), name);
+            let path = self.download_dir.join(replacer.replace(&url)?);
+
+            if !path.is_file() {
+                continue;
+            }
+
+            let s = fs::read_to_string(&path).await.map_err(|e| {
+                RefreshError::FailedToOperateDirOrFile(path.display().to_string(), e)
+            })?;
+
+            return Ok(Some((path, s)));
+        }
+
+        Ok(None)
+    }
+
+    /// 签名无法验证时，这个源的所有文件都不可信
+    async fn untrusted(
+        &self,
+        ose: &OmaSourceEntry<'_>,
+        release_path: &Path,
+        reason: String,
+        replacer: &DatabaseFilenameReplacer,
+        res: &mut VerifyResult,
+    ) -> Result<()> {
+        let deleted = self.remove(release_path).await?;
+
+        res.issues.push(ListIssue {
+            path: release_path.to_path_buf(),
+            problem: ListProblem::BadSignature(reason),
+            deleted,
+        });
+
+        if !self.delete {
+            return Ok(());
+        }
+
+        let prefix = format!(
+            "{}_",
+            replacer.replace(ose.dist_path())?.trim_end_matches('_')
+        );
+
+        let mut dir = fs::read_dir(&self.download_dir).await.map_err(|e| {
+            RefreshError::ReadDownloadDir(self.download_dir.display().to_string(), e)
+        })?;
+
+        while let Ok(Some(entry)) = dir.next_entry().await {
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                self.remove(&entry.path()).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn remove(&self, path: &Path) -> Result<bool> {
+        if !self.delete || !path.is_file() {
+            return Ok(false);
+        }
+
+        debug!("Removing {}", path.display());
+
+        fs::remove_file(path)
+            .await
+            .map_err(|e| RefreshError::FailedToOperateDirOrFile(path.display().to_string(), e))?;
+
+        Ok(true)
+    }
+}
+
+/// Checksum of the file saved on disk, which is decompressed unless `KeepCompressed`
+fn expected_checksum(
+    c: &ChecksumDownloadEntry,
+    inrelease: &InRelease,
+    checksum_type: &InReleaseChecksum,
+) -> Result<Option<Checksum>> {
+    let checksum = if c.keep_compress {
+        Some(&c.item.checksum)
+    } else {
+        let name = if file_is_compress(&c.item.name) {
+            split_ext_and_filename(&c.item.name).1
+        } else {
+            c.item.name.clone()
+        };
+
+        inrelease
+            .checksum_type_and_list()
+            .1
+            .iter()
+            .find(|x| x.name == name)
+            .map(|x| &x.checksum)
+    };
+
+    let Some(checksum) = checksum else {
+        return Ok(None);
+    };
+
+    Ok(Some(match checksum_type {
+        InReleaseChecksum::Sha256 => Checksum::from_sha256_str(checksum)?,
+        InReleaseChecksum::Sha512 => Checksum::from_sha512_str(checksum)?,
+        InReleaseChecksum::Md5 => Checksum::from_md5_str(checksum)?,
+    }))
+}
+
+#[test]
+fn test_verify_lists() {
+    use sequoia_openpgp::{cert::CertBuilder, serialize::Serialize};
+    use sha2::{Digest, Sha256};
+    use std::{fs::File, time::Duration};
+
+    let dir = std::env::temp_dir().join(format!("oma-verify-test-{}", std::process::id()));
+    let lists = dir.join("var/lib/apt/lists");
+    let trusted = dir.join("etc/apt/trusted.gpg.d");
+    std::fs::create_dir_all(&lists).unwrap();
+    std::fs::create_dir_all(&trusted).unwrap();
+    std::fs::create_dir_all(dir.join("etc/apt/sources.list.d")).unwrap();
+    std::fs::write(
+        dir.join("etc/apt/sources.list"),
+        "deb https://repo.example.com/debian stable main\n",
+    )
+    .unwrap();
+
+    let (cert, _) = CertBuilder::general_purpose(None, Some("oma <oma@example.com>"))
+        .generate()
+        .unwrap();
+    let mut f = File::create(trusted.join("oma.gpg")).unwrap();
+    cert.serialize(&mut f).unwrap();
+    let key = dir.join("key.pgp");
+    let mut f = File::create(&key).unwrap();
+    cert.as_tsk().serialize(&mut f).unwrap();
+
+    let arch = dpkg_arch(&dir).unwrap();
+    let packages = "Package: fish\nVersion: 3.7.1\n";
+    let release = format!(
+        "Suite: stable\nSHA256:\n {} {} main/binary-{arch}/Packages\n",
+        faster_hex::hex_string(&Sha256::digest(packages)),
+        packages.len()
+    );
+    let (inrelease, _) = oma_repo_verify::sign_release(&release, &key).unwrap();
+
+    let replacer = DatabaseFilenameReplacer::new().unwrap();
+    let dist = "https://repo.example.com/debian/dists/stable";
+    let inrelease_path = lists.join(replacer.replace(&format!("{dist}/InRelease")).unwrap());
+    let packages_path = lists.join(
+        replacer
+            .replace(&format!("{dist}/main/binary-{arch}/Packages"))
+            .unwrap(),
+    );
+
+    // 签名时间不影响校验
+    let policies = SignaturePolicies::new(SignaturePolicy {
+        max_signature_age: Some(Duration::ZERO),
+        ..Default::default()
+    });
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let config = Config::new();
+    let verify = VerifyLists::builder()
+        .source(dir.clone())
+        .download_dir(lists.clone())
+        .apt_config(&config)
+        .delete(true)
+        .signature_policy(policies)
+        .build();
+
+    // 完好的列表
+    std::fs::write(&inrelease_path, &inrelease).unwrap();
+    std::fs::write(&packages_path, packages).unwrap();
+    let res = rt.block_on(verify.run()).unwrap();
+    assert_eq!(res.checked, 2);
+    assert!(res.issues.is_empty());
+    assert!(inrelease_path.is_file() && packages_path.is_file());
+
+    // 被篡改的列表
+    std::fs::write(&packages_path, "Package: evil\nVersion: 3.7.1\n").unwrap();
+    let res = rt.block_on(verify.run()).unwrap();
+    assert_eq!(res.issues.len(), 1);
+    assert_eq!(res.issues[0].path, packages_path);
+    assert!(matches!(
+        res.issues[0].problem,
+        ListProblem::ChecksumMismatch
+    ));
+    assert!(res.issues[0].deleted);
+    assert!(!packages_path.exists());
+
+    // 没有 InRelease 的源视为尚未刷新
+    std::fs::remove_file(&inrelease_path).unwrap();
+    std::fs::write(&packages_path, packages).unwrap();
+    let res = rt.block_on(verify.run()).unwrap();
+    assert_eq!(res.checked, 0);
+    assert!(res.issues.is_empty());
+    assert!(packages_path.is_file());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
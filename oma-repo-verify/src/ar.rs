@@ -0,0 +1,65 @@
+//! Minimal reader of ar archives (.deb packages)
+
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// A member of the ar archive
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// List members of an ar archive without reading their content
+pub fn members(f: &mut File) -> io::Result<Vec<Member>> {
+    let invalid = |msg: &str| io::Error::new(ErrorKind::InvalidData, msg.to_string());
+
+    let mut magic = [0; 8];
+    f.read_exact(&mut magic)?;
+
+    if &magic != b"!<arch>\n" {
+        return Err(invalid("not an ar archive"));
+    }
+
+    let mut res = vec![];
+    let mut header = [0; 60];
+
+    loop {
+        match f.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+
+        let name = String::from_utf8_lossy(&header[..16])
+            .trim_end()
+            .trim_end_matches('/')
+            .to_string();
+
+        let size = std::str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|x| x.trim().parse::<u64>().ok())
+            .ok_or_else(|| invalid("bad ar member header"))?;
+
+        let offset = f.stream_position()?;
+
+        // 成员按两字节对齐
+        f.seek(SeekFrom::Start(offset + size + size % 2))?;
+
+        res.push(Member { name, offset, size });
+    }
+
+    Ok(res)
+}
+
+/// Read content of member `m` in the archive at `path`
+pub fn open_member(path: &Path, m: &Member) -> io::Result<io::Take<File>> {
+    let mut f = File::open(path)?;
+    f.seek(SeekFrom::Start(m.offset))?;
+
+    Ok(f.take(m.size))
+}
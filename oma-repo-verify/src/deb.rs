@@ -0,0 +1,305 @@
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use sequoia_openpgp::{
+    fmt::hex,
+    parse::{stream::DetachedVerifierBuilder, Parse},
+    types::HashAlgorithm,
+};
+
+use crate::{
+    ar::{members, open_member, Member},
+    find_certs,
+    keyring::CertInfo,
+    verify_clearsigned, verify_error, InReleaseVerifier, SignaturePolicy, VerifyError,
+    VerifyResult,
+};
+
+/// Detached signature made by debsigs
+const ORIGIN: &str = "_gpgorigin";
+/// Clearsigned member checksums made by dpkg-sig
+const BUILDER: &str = "_gpgbuilder";
+
+/// Verify signature members of a .deb package against the system trusted keyrings,
+/// returns the certs which signed it.
+///
+/// Both `_gpgorigin` (debsigs) and `_gpgbuilder` (dpkg-sig) are checked if present.
+pub fn verify_deb(
+    path: impl AsRef<Path>,
+    rootfs: impl AsRef<Path>,
+    policy: &SignaturePolicy,
+) -> VerifyResult<Vec<CertInfo>> {
+    let path = path.as_ref();
+    let name = path.display().to_string();
+    let read_err = |e| VerifyError::FailedToReadDeb(name.clone(), e);
+
+    let members = File::open(path)
+        .and_then(|mut f| members(&mut f))
+        .map_err(read_err)?;
+
+    // 以下划线开头的成员不属于包内容
+    let payload = members
+        .iter()
+        .filter(|x| !x.name.starts_with('_'))
+        .collect::<Vec<_>>();
+
+    let (certs, _) = find_certs(rootfs, &None)?;
+
+    // 包的签名必然早于安装时间，不检查签名时间
+    let policy = SignaturePolicy {
+        max_signature_age: None,
+        ..policy.clone()
+    };
+
+    let mut signers: Vec<CertInfo> = vec![];
+    let mut signed = false;
+
+    for m in &members {
+        let res = match m.name.as_str() {
+            ORIGIN => verify_origin(path, m, &payload, &certs, &policy),
+            BUILDER => verify_builder(path, m, &payload, &certs, &policy),
+            _ => continue,
+        };
+
+        let res = res.map_err(|e| match e {
+            VerifyError::FailedToReadDeb(..) => e,
+            e => VerifyError::BadDebSignature(name.clone(), format!("{}: {e}", m.name)),
+        })?;
+
+        signed = true;
+
+        for info in res {
+            if !signers.iter().any(|x| x.fingerprint == info.fingerprint) {
+                signers.push(info);
+            }
+        }
+    }
+
+    if !signed {
+        return Err(VerifyError::DebNotSigned(name));
+    }
+
+    Ok(signers)
+}
+
+/// `_gpgorigin` signs the concatenation of all package members
+fn verify_origin(
+    path: &Path,
+    sig: &Member,
+    payload: &[&Member],
+    certs: &[PathBuf],
+    policy: &SignaturePolicy,
+) -> VerifyResult<Vec<CertInfo>> {
+    let read_err = |e| VerifyError::FailedToReadDeb(path.display().to_string(), e);
+
+    let mut detached = vec![];
+    open_member(path, sig)
+        .and_then(|mut r| r.read_to_end(&mut detached))
+        .map_err(read_err)?;
+
+    let mut content: Box<dyn Read + Send + Sync> = Box::new(io::empty());
+
+    for m in payload {
+        content = Box::new(content.chain(open_member(path, m).map_err(read_err)?));
+    }
+
+    let p = policy.standard_policy();
+
+    let mut v = DetachedVerifierBuilder::from_bytes(&detached)?
+        .with_policy(&p, None, InReleaseVerifier::from_paths(certs)?)
+        .map_err(verify_error)?;
+
+    v.verify_reader(content).map_err(verify_error)?;
+
+    Ok(v.into_helper().signers)
+}
+
+/// `_gpgbuilder` is a clearsigned list of member checksums:
+///
+/// ```text
+/// Files:
+///     <md5> <sha1> <size> <name>
+/// ```
+fn verify_builder(
+    path: &Path,
+    sig: &Member,
+    payload: &[&Member],
+    certs: &[PathBuf],
+    policy: &SignaturePolicy,
+) -> VerifyResult<Vec<CertInfo>> {
+    let read_err = |e| VerifyError::FailedToReadDeb(path.display().to_string(), e);
+
+    let mut signed = String::new();
+    open_member(path, sig)
+        .and_then(|mut r| r.read_to_string(&mut signed))
+        .map_err(read_err)?;
+
+    let (text, signers) =
+        verify_clearsigned(&signed, policy, InReleaseVerifier::from_paths(certs)?)?;
+
+    let files = text
+        .lines()
+        .skip_while(|x| !x.starts_with("Files:"))
+        .skip(1)
+        .take_while(|x| x.starts_with([' ', '\t']))
+        .map(|x| x.split_whitespace().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    if files.len() != payload.len() {
+        return Err(anyhow::anyhow!("signed file list does not match package members").into());
+    }
+
+    for m in payload {
+        let Some(entry) = files.iter().find(|x| x.len() == 4 && x[3] == m.name) else {
+            return Err(anyhow::anyhow!("{} is not signed", m.name).into());
+        };
+
+        let (md5, sha1) = checksums(path, m).map_err(read_err)?;
+
+        if entry[2] != m.size.to_string()
+            || !entry[0].eq_ignore_ascii_case(&md5)
+            || !entry[1].eq_ignore_ascii_case(&sha1)
+        {
+            return Err(anyhow::anyhow!("checksum mismatch: {}", m.name).into());
+        }
+    }
+
+    Ok(signers)
+}
+
+/// MD5 and SHA-1 of a member
+fn checksums(path: &Path, m: &Member) -> io::Result<(String, String)> {
+    let context = |algo: HashAlgorithm| {
+        algo.context()
+            .map_err(|e| io::Error::new(ErrorKind::Unsupported, e))
+    };
+
+    let mut md5 = context(HashAlgorithm::MD5)?;
+    let mut sha1 = context(HashAlgorithm::SHA1)?;
+
+    let mut r = open_member(path, m)?;
+    let mut buf = vec![0; 65536];
+
+    loop {
+        let len = r.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        md5.update(&buf[..len]);
+        sha1.update(&buf[..len]);
+    }
+
+    let digest = |mut d: Box<dyn sequoia_openpgp::crypto::hash::Digest>| {
+        let mut res = vec![0; d.digest_size()];
+        d.digest(&mut res)
+            .map(|_| hex::encode(res))
+            .map_err(io::Error::other)
+    };
+
+    Ok((digest(md5)?, digest(sha1)?))
+}
+
+#[test]
+fn test_verify_deb() {
+    use sequoia_openpgp::{cert::CertBuilder, serialize::Serialize};
+    use std::{fs, io::Write};
+
+    fn write_ar(path: &Path, members: &[(&str, &[u8])]) {
+        let mut f = File::create(path).unwrap();
+        f.write_all(b"!<arch>\n").unwrap();
+
+        for (name, content) in members {
+            writeln!(
+                f,
+                "{name:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`",
+                0,
+                0,
+                0,
+                100644,
+                content.len()
+            )
+            .unwrap();
+            f.write_all(content).unwrap();
+            if content.len() % 2 == 1 {
+                f.write_all(b"\n").unwrap();
+            }
+        }
+    }
+
+    let (cert, _) = CertBuilder::general_purpose(None, Some("oma <oma@example.com>"))
+        .generate()
+        .unwrap();
+
+    let dir = std::env::temp_dir().join(format!("oma-deb-test-{}", std::process::id()));
+    let trusted = dir.join("etc/apt/trusted.gpg.d");
+    fs::create_dir_all(&trusted).unwrap();
+    let mut f = File::create(trusted.join("oma.gpg")).unwrap();
+    cert.serialize(&mut f).unwrap();
+
+    let key = dir.join("key.pgp");
+    let mut f = File::create(&key).unwrap();
+    cert.as_tsk().serialize(&mut f).unwrap();
+
+    let payload: [(&str, &[u8]); 3] = [
+        ("debian-binary", b"2.0\n"),
+        ("control.tar.xz", b"control"),
+        ("data.tar.zst", b"data!"),
+    ];
+
+    let policy = SignaturePolicy::default();
+    let deb = dir.join("test.deb");
+
+    // 未签名
+    write_ar(&deb, &payload);
+    assert!(matches!(
+        verify_deb(&deb, &dir, &policy),
+        Err(VerifyError::DebNotSigned(_))
+    ));
+
+    // debsigs
+    let (_, origin) = crate::sign_release("2.0\ncontroldata!", &key).unwrap();
+    let mut members = payload.to_vec();
+    members.push((ORIGIN, origin.as_bytes()));
+    write_ar(&deb, &members);
+
+    let signers = verify_deb(&deb, &dir, &policy).unwrap();
+    assert_eq!(signers[0].fingerprint, cert.fingerprint().to_hex());
+
+    members[2].1 = b"evil!";
+    write_ar(&deb, &members);
+    assert!(matches!(
+        verify_deb(&deb, &dir, &policy),
+        Err(VerifyError::BadDebSignature(..))
+    ));
+
+    // dpkg-sig
+    let mut files = String::from("Version: 4\nRole: builder\nFiles: \n");
+    for m in members.iter().take(3) {
+        let member = Member {
+            name: m.0.to_string(),
+            offset: 0,
+            size: m.1.len() as u64,
+        };
+        fs::write(dir.join("member"), m.1).unwrap();
+        let (md5, sha1) = checksums(&dir.join("member"), &member).unwrap();
+        files.push_str(&format!("\t{md5} {sha1} {} {}\n", m.1.len(), m.0));
+    }
+
+    let (builder, _) = crate::sign_release(&files, &key).unwrap();
+    let mut members = members[..3].to_vec();
+    members.push((BUILDER, builder.as_bytes()));
+    write_ar(&deb, &members);
+    assert!(verify_deb(&deb, &dir, &policy).is_ok());
+
+    members[1].1 = b"c0ntrol";
+    write_ar(&deb, &members);
+    assert!(matches!(
+        verify_deb(&deb, &dir, &policy),
+        Err(VerifyError::BadDebSignature(..))
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,342 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use oma_apt_sources_lists::Signature;
+use sequoia_openpgp::{
+    armor,
+    cert::{amalgamation::key::ValidErasedKeyAmalgamation, CertParser},
+    packet::key::PublicParts,
+    parse::{PacketParserBuilder, Parse},
+    serialize::Serialize,
+    types::RevocationStatus,
+    Cert,
+};
+
+use crate::{SignaturePolicy, VerifyError, VerifyResult};
+
+/// Status of a cert under oma's verification policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertStatus {
+    Valid,
+    Expired,
+    Revoked,
+    /// Can not be used to verify anything, e.g. no valid self-signature
+    Invalid(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct CertInfo {
+    pub fingerprint: String,
+    pub user_ids: Vec<String>,
+    pub created: SystemTime,
+    /// `None` means the key never expires
+    pub expires: Option<SystemTime>,
+    pub status: CertStatus,
+}
+
+impl CertInfo {
+    pub(crate) fn new(cert: &Cert) -> Self {
+        let p = SignaturePolicy::default().standard_policy();
+
+        let (expires, status) = match cert.with_policy(&p, None) {
+            Ok(vc) => {
+                let status = if matches!(vc.revocation_status(), RevocationStatus::Revoked(_)) {
+                    CertStatus::Revoked
+                } else if vc.alive().is_err() {
+                    CertStatus::Expired
+                } else {
+                    CertStatus::Valid
+                };
+
+                (vc.primary_key().key_expiration_time(), status)
+            }
+            Err(e) => (None, CertStatus::Invalid(e.to_string())),
+        };
+
+        Self {
+            fingerprint: cert.fingerprint().to_hex(),
+            user_ids: cert
+                .userids()
+                .map(|u| String::from_utf8_lossy(u.userid().value()).to_string())
+                .collect(),
+            created: cert.primary_key().creation_time(),
+            expires,
+            status,
+        }
+    }
+
+    /// Info of the cert which made a signature with key `ka`, which may be a subkey
+    pub(crate) fn signed_by(ka: &ValidErasedKeyAmalgamation<PublicParts>) -> Self {
+        let mut info = Self::new(ka.cert());
+
+        // 签名子密钥可能比主密钥更早过期，以较早者为准
+        if let Some(t) = ka.key_expiration_time() {
+            info.expires = Some(info.expires.map_or(t, |x| x.min(t)));
+        }
+
+        info
+    }
+
+    /// The key is still valid but will expire before `time`
+    pub fn expires_before(&self, time: SystemTime) -> bool {
+        self.status == CertStatus::Valid && self.expires.is_some_and(|x| x <= time)
+    }
+}
+
+/// A keyring file of trusted certs
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    path: PathBuf,
+    certs: Vec<Cert>,
+}
+
+impl Keyring {
+    pub fn load(path: impl AsRef<Path>) -> VerifyResult<Self> {
+        let path = path.as_ref();
+        let mut certs = vec![];
+
+        for cert in CertParser::from_file(path)
+            .map_err(|e| VerifyError::CertParseFileError(path.display().to_string(), e))?
+        {
+            certs.push(cert.map_err(|e| VerifyError::BadCertFile(path.display().to_string(), e))?);
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            certs,
+        })
+    }
+
+    /// Parse armored or binary certs, secret key material is dropped
+    pub fn from_bytes(path: impl AsRef<Path>, bytes: &[u8]) -> VerifyResult<Self> {
+        let path = path.as_ref();
+        let mut certs = vec![];
+
+        for cert in CertParser::from(PacketParserBuilder::from_bytes(bytes)?.build()?) {
+            let cert = cert.map_err(|e| VerifyError::BadCertFile(path.display().to_string(), e))?;
+            certs.push(cert.strip_secret_key_material());
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            certs,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn set_path(&mut self, path: impl AsRef<Path>) {
+        self.path = path.as_ref().to_path_buf();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.certs.is_empty()
+    }
+
+    pub fn infos(&self) -> Vec<CertInfo> {
+        self.certs.iter().map(CertInfo::new).collect()
+    }
+
+    /// Whether the keyring has a cert matches `key` (fingerprint or key ID)
+    pub fn contains(&self, key: &str) -> bool {
+        self.certs.iter().any(|c| cert_matches(c, key))
+    }
+
+    /// Keep only certs matching `key`
+    pub fn select(&self, key: &str) -> Self {
+        Self {
+            path: self.path.clone(),
+            certs: self
+                .certs
+                .iter()
+                .filter(|c| cert_matches(c, key))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Remove certs matching `key`, returns how many certs are removed
+    pub fn remove(&mut self, key: &str) -> usize {
+        let len = self.certs.len();
+        self.certs.retain(|c| !cert_matches(c, key));
+
+        len - self.certs.len()
+    }
+
+    /// Serialize public certs
+    pub fn to_bytes(&self, armored: bool) -> VerifyResult<Vec<u8>> {
+        let mut buf = vec![];
+
+        if armored {
+            let mut w = armor::Writer::new(&mut buf, armor::Kind::PublicKey)
+                .map_err(anyhow::Error::from)?;
+            for cert in &self.certs {
+                cert.serialize(&mut w)?;
+            }
+            w.finalize().map_err(anyhow::Error::from)?;
+        } else {
+            for cert in &self.certs {
+                cert.serialize(&mut buf)?;
+            }
+        }
+
+        Ok(buf)
+    }
+
+    /// Write certs to the keyring file, armored if the file name ends with `.asc`
+    pub fn save(&self) -> VerifyResult<()> {
+        let armored = self.path.extension().is_some_and(|x| x == "asc");
+        let bytes = self.to_bytes(armored)?;
+
+        fs::write(&self.path, bytes).map_err(|e| anyhow::Error::from(e).into())
+    }
+}
+
+/// `key` is the fingerprint or key ID of the primary key or one of subkeys
+fn cert_matches(cert: &Cert, key: &str) -> bool {
+    let key = key
+        .trim_start_matches("0x")
+        .replace(' ', "")
+        .to_ascii_uppercase();
+
+    // 太短的 key ID 容易冲突
+    if key.len() < 8 {
+        return false;
+    }
+
+    cert.keys()
+        .any(|k| k.fingerprint().to_hex().ends_with(&key))
+}
+
+/// All keyring files trusted by sources without `Signed-By`
+pub fn system_keyrings(rootfs: impl AsRef<Path>) -> Vec<PathBuf> {
+    let rootfs = rootfs.as_ref();
+    let mut res = vec![];
+
+    for dir in ["etc/apt/trusted.gpg.d", "etc/apt/keyrings"] {
+        let Ok(dir) = fs::read_dir(rootfs.join(dir)) else {
+            continue;
+        };
+
+        let mut paths = dir
+            .flatten()
+            .map(|x| x.path())
+            .filter(|x| {
+                let ext = x.extension().and_then(|x| x.to_str());
+                ext == Some("gpg") || ext == Some("asc")
+            })
+            .collect::<Vec<_>>();
+
+        paths.sort();
+        res.extend(paths);
+    }
+
+    let trust_main = rootfs.join("etc/apt/trusted.gpg");
+
+    if trust_main.is_file() {
+        res.push(trust_main);
+    }
+
+    res
+}
+
+/// Path of a keyring file in `Signed-By`, relative paths are under `trusted.gpg.d`
+pub(crate) fn signed_by_path(rootfs: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        rootfs.join("etc/apt/trusted.gpg.d").join(path)
+    }
+}
+
+/// Keyrings from `Signed-By`, returns `None` if the source trusts all system keyrings.
+/// Keys embedded in `Signed-By` use `source_file` as their path
+pub fn signed_by_keyrings(
+    rootfs: impl AsRef<Path>,
+    signed_by: &Option<Signature>,
+    source_file: &Path,
+) -> Option<VerifyResult<Vec<Keyring>>> {
+    let rootfs = rootfs.as_ref();
+
+    Some(match signed_by.as_ref()? {
+        Signature::KeyBlock(block) => {
+            // 与 verify_inrelease 相同，这个点存在只是表示换行
+            Keyring::from_bytes(source_file, block.replace('.', "").as_bytes()).map(|x| vec![x])
+        }
+        Signature::KeyPath(paths) => paths
+            .iter()
+            .map(|p| Keyring::load(signed_by_path(rootfs, p)))
+            .collect(),
+    })
+}
+
+#[test]
+fn test_keyring() {
+    use sequoia_openpgp::cert::CertBuilder;
+    use std::time::Duration;
+
+    let (cert, _) = CertBuilder::general_purpose(None, Some("oma <oma@example.com>"))
+        .set_validity_period(Duration::from_secs(10 * 24 * 60 * 60))
+        .generate()
+        .unwrap();
+
+    let fingerprint = cert.fingerprint().to_hex();
+    let mut tsk = vec![];
+    cert.as_tsk().armored().serialize(&mut tsk).unwrap();
+
+    let dir = std::env::temp_dir().join(format!("oma-keyring-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let keyring = Keyring::from_bytes(dir.join("oma.asc"), &tsk).unwrap();
+    keyring.save().unwrap();
+
+    let mut keyring = Keyring::load(dir.join("oma.asc")).unwrap();
+    let info = &keyring.infos()[0];
+    assert_eq!(info.fingerprint, fingerprint);
+    assert_eq!(info.user_ids, vec!["oma <oma@example.com>".to_string()]);
+    assert_eq!(info.status, CertStatus::Valid);
+    assert!(info.expires_before(SystemTime::now() + Duration::from_secs(30 * 24 * 60 * 60)));
+    assert!(!info.expires_before(SystemTime::now()));
+    assert!(!keyring.certs[0].is_tsk());
+
+    assert!(keyring.contains(&format!("0x{}", &fingerprint[24..])));
+    assert!(!keyring.contains(&fingerprint[36..]));
+    assert_eq!(keyring.select("DEADBEEF").certs.len(), 0);
+    assert_eq!(keyring.remove(&fingerprint.to_lowercase()), 1);
+    assert!(keyring.is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_signing_subkey_expiry() {
+    use sequoia_openpgp::{cert::CertBuilder, types::KeyFlags};
+    use std::time::Duration;
+
+    let ten_days = Duration::from_secs(10 * 24 * 60 * 60);
+    let (cert, _) = CertBuilder::new()
+        .add_userid("oma <oma@example.com>")
+        .set_validity_period(None)
+        .add_subkey(KeyFlags::empty().set_signing(), ten_days, None)
+        .generate()
+        .unwrap();
+
+    let p = SignaturePolicy::default().standard_policy();
+    let ka = cert
+        .keys()
+        .subkeys()
+        .with_policy(&p, None)
+        .for_signing()
+        .next()
+        .unwrap();
+
+    assert_eq!(CertInfo::new(&cert).expires, None);
+    let info = CertInfo::signed_by(&ka.into());
+    assert_eq!(info.fingerprint, cert.fingerprint().to_hex());
+    assert!(info.expires_before(SystemTime::now() + 2 * ten_days));
+}
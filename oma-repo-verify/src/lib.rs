@@ -1,12 +1,14 @@
 use std::{
-    io::Read,
+    io::{Read, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    time::{Duration, SystemTime},
 };
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 use oma_apt_sources_lists::Signature;
 use sequoia_openpgp::{
+    armor,
     cert::CertParser,
     parse::{
         stream::{
@@ -15,15 +17,26 @@ use sequoia_openpgp::{
         },
         PacketParserBuilder, Parse,
     },
-    policy::{AsymmetricAlgorithm, StandardPolicy},
-    types::HashAlgorithm,
+    policy::StandardPolicy,
+    serialize::stream::{Armorer, Message, Signer},
     Cert, KeyHandle,
 };
 use tracing::debug;
 
+pub mod ar;
+pub mod deb;
+pub mod keyring;
+pub mod policy;
+
+use keyring::{signed_by_path, system_keyrings, CertInfo};
+pub use policy::{SignaturePolicies, SignaturePolicy};
+
 #[derive(Debug)]
 pub struct InReleaseVerifier {
     certs: Vec<Cert>,
+    max_signature_age: Option<Duration>,
+    /// Certs which made good signatures
+    signers: Vec<CertInfo>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -36,6 +49,20 @@ pub enum VerifyError {
     TrustedDirNotExist,
     #[error("Failed to read decoded InRelease file: {0}")]
     FailedToReadInRelease(std::io::Error),
+    #[error("No usable unencrypted signing key in {0}")]
+    NoSigningKey(String),
+    #[error("Signing key {0} is not accepted: {1}")]
+    BadKey(String, String),
+    #[error("Signature by {0} is not accepted: {1}")]
+    BadSignature(String, String),
+    #[error("Signature by {0} was made {1} days ago, older than the limit of {2} days")]
+    SignatureTooOld(String, u64, u64),
+    #[error("Failed to read package {0}: {1}")]
+    FailedToReadDeb(String, std::io::Error),
+    #[error("Package {0} is not signed")]
+    DebNotSigned(String),
+    #[error("Signature of package {0} is invalid: {1}")]
+    BadDebSignature(String, String),
     #[error(transparent)]
     Anyhow(#[from] anyhow::Error),
 }
@@ -54,7 +81,11 @@ impl FromStr for InReleaseVerifier {
             certs.push(maybe_cert.map_err(|e| VerifyError::BadCertFile(s.to_string(), e))?);
         }
 
-        Ok(InReleaseVerifier { certs })
+        Ok(InReleaseVerifier {
+            certs,
+            max_signature_age: None,
+            signers: vec![],
+        })
     }
 }
 
@@ -73,7 +104,34 @@ impl InReleaseVerifier {
             }
         }
 
-        Ok(InReleaseVerifier { certs })
+        Ok(InReleaseVerifier {
+            certs,
+            max_signature_age: None,
+            signers: vec![],
+        })
+    }
+
+    /// Reject signatures older than `age`
+    pub fn max_signature_age(mut self, age: Option<Duration>) -> Self {
+        self.max_signature_age = age;
+        self
+    }
+
+    fn check_age(&self, fingerprint: String, created: Option<SystemTime>) -> Option<VerifyError> {
+        let max = self.max_signature_age?;
+        let age = SystemTime::now().duration_since(created?).ok()?;
+
+        if age <= max {
+            return None;
+        }
+
+        const DAY: u64 = 24 * 60 * 60;
+
+        Some(VerifyError::SignatureTooOld(
+            fingerprint,
+            age.as_secs() / DAY,
+            max.as_secs() / DAY,
+        ))
     }
 }
 
@@ -90,15 +148,54 @@ impl VerificationHelper for InReleaseVerifier {
             if let MessageLayer::SignatureGroup { results } = layer {
                 for r in results {
                     match r {
-                        Ok(_) => has_success = true,
+                        Ok(good) => {
+                            match self.check_age(
+                                good.ka.fingerprint().to_hex(),
+                                good.sig.signature_creation_time(),
+                            ) {
+                                Some(e) => err = Some(e),
+                                None => {
+                                    has_success = true;
+                                    let info = CertInfo::signed_by(&good.ka);
+                                    if !self
+                                        .signers
+                                        .iter()
+                                        .any(|x| x.fingerprint == info.fingerprint)
+                                    {
+                                        self.signers.push(info);
+                                    }
+                                }
+                            }
+                        }
                         Err(e) => {
                             debug!("{e}");
                             match e {
                                 VerificationError::MissingKey { .. } => {
                                     missing_key_err = Some(e);
                                 }
-                                _ => {
-                                    err = Some(e);
+                                // 被签名策略拒绝时报告具体原因
+                                VerificationError::BadKey { ka, error, .. } => {
+                                    err = Some(VerifyError::BadKey(
+                                        ka.fingerprint().to_hex(),
+                                        format!("{error:#}"),
+                                    ));
+                                }
+                                VerificationError::UnboundKey { cert, error, .. } => {
+                                    err = Some(VerifyError::BadKey(
+                                        cert.fingerprint().to_hex(),
+                                        format!("{error:#}"),
+                                    ));
+                                }
+                                VerificationError::BadSignature { ka, error, .. } => {
+                                    err = Some(VerifyError::BadSignature(
+                                        ka.fingerprint().to_hex(),
+                                        format!("{error:#}"),
+                                    ));
+                                }
+                                VerificationError::MalformedSignature { .. } => {
+                                    err = Some(
+                                        anyhow!("InRelease contains bad signature: {e}.").into(),
+                                    );
                                 }
                             }
                         }
@@ -109,84 +206,90 @@ impl VerificationHelper for InReleaseVerifier {
             }
         }
 
-        if let Some(e) = err {
-            bail!("InRelease contains bad signature: {e}.");
+        // 只要有一个签名验证通过即可，其余签名的问题只在全部失败时报告
+        if has_success {
+            return Ok(());
         }
 
-        if !has_success {
-            bail!(
-                "InRelease contains bad signature: {}.",
-                missing_key_err.unwrap()
-            );
+        if let Some(e) = err {
+            return Err(e.into());
         }
 
-        Ok(())
+        bail!(
+            "InRelease contains bad signature: {}.",
+            missing_key_err.unwrap()
+        );
     }
 }
 
-/// Verify InRelease PGP signature
+/// Verify InRelease PGP signature, returns the signed text and the certs which signed it
 pub fn verify_inrelease(
     inrelease: &str,
     signed_by: &Option<Signature>,
     rootfs: impl AsRef<Path>,
-) -> VerifyResult<String> {
+    policy: &SignaturePolicy,
+) -> VerifyResult<(String, Vec<CertInfo>)> {
     debug!("signed_by: {:?}", signed_by);
 
     let (certs, deb822_inner_signed_by_str) = find_certs(rootfs, signed_by)?;
 
-    let p = policy();
+    let helper = if let Some(deb822_inner_signed_by_str) = deb822_inner_signed_by_str {
+        // 这个点存在只是表示换行，因此把它替换掉
+        let signed_by_str = deb822_inner_signed_by_str.replace('.', "");
+        InReleaseVerifier::from_str(&signed_by_str)?
+    } else {
+        InReleaseVerifier::from_paths(&certs)?
+    };
 
-    let mut v = VerifierBuilder::from_bytes(inrelease.as_bytes())?.with_policy(
-        &p,
-        None,
-        if let Some(deb822_inner_signed_by_str) = deb822_inner_signed_by_str {
-            // 这个点存在只是表示换行，因此把它替换掉
-            let signed_by_str = deb822_inner_signed_by_str.replace('.', "");
-            InReleaseVerifier::from_str(&signed_by_str)?
-        } else {
-            InReleaseVerifier::from_paths(&certs)?
-        },
-    )?;
+    verify_clearsigned(inrelease, policy, helper)
+}
+
+fn verify_clearsigned(
+    inrelease: &str,
+    policy: &SignaturePolicy,
+    helper: InReleaseVerifier,
+) -> VerifyResult<(String, Vec<CertInfo>)> {
+    let p = policy.standard_policy();
+
+    let mut v = VerifierBuilder::from_bytes(inrelease.as_bytes())?
+        .with_policy(&p, None, helper.max_signature_age(policy.max_signature_age))
+        .map_err(verify_error)?;
 
     let mut res = String::new();
     v.read_to_string(&mut res)
         .map_err(VerifyError::FailedToReadInRelease)?;
 
-    Ok(res)
-}
-
-fn policy() -> StandardPolicy<'static> {
-    // Derive p to allow configuring sequoia_openpgp's StandardPolicy.
-    let mut p = StandardPolicy::new();
-    // Allow SHA-1 (considering it safe, whereas sequoia_openpgp's standard
-    // policy forbids it), as many third party APT repositories still uses
-    // SHA-1 to sign their repository metadata (such as InRelease).
-    p.accept_hash(HashAlgorithm::SHA1);
-
-    // Allow RSA-1024
-    p.accept_asymmetric_algo(AsymmetricAlgorithm::RSA1024);
-
-    p
+    Ok((res, v.into_helper().signers))
 }
 
+/// Verify Release with its detached signature, returns the certs which signed it
 pub fn verify_release(
     release: &str,
     detached: &[u8],
     signed_by: &Option<Signature>,
     rootfs: impl AsRef<Path>,
-) -> VerifyResult<()> {
+    policy: &SignaturePolicy,
+) -> VerifyResult<Vec<CertInfo>> {
     let (certs, _) = find_certs(rootfs, signed_by)?;
-    let p = policy();
+    let p = policy.standard_policy();
 
     let mut v = DetachedVerifierBuilder::from_bytes(detached)?.with_policy(
         &p,
         None,
-        InReleaseVerifier::from_paths(&certs)?,
+        InReleaseVerifier::from_paths(&certs)?.max_signature_age(policy.max_signature_age),
     )?;
 
-    v.verify_bytes(release)?;
+    v.verify_bytes(release).map_err(verify_error)?;
 
-    Ok(())
+    Ok(v.into_helper().signers)
+}
+
+/// Keep the precise reason returned by [`InReleaseVerifier::check`]
+fn verify_error(e: anyhow::Error) -> VerifyError {
+    match e.downcast::<VerifyError>() {
+        Ok(e) => e,
+        Err(e) => VerifyError::Anyhow(e),
+    }
 }
 
 fn find_certs(
@@ -195,14 +298,8 @@ fn find_certs(
 ) -> VerifyResult<(Vec<PathBuf>, Option<&str>)> {
     let rootfs = rootfs.as_ref();
 
-    let mut dir = std::fs::read_dir(rootfs.join("etc/apt/trusted.gpg.d"))
-        .map_err(|_| VerifyError::TrustedDirNotExist)?
-        .collect::<Vec<_>>();
-
-    let etc_keyring = std::fs::read_dir(rootfs.join("etc/apt/keyrings"));
-
-    if let Ok(keyring) = etc_keyring {
-        dir.extend(keyring);
+    if !rootfs.join("etc/apt/trusted.gpg.d").is_dir() {
+        return Err(VerifyError::TrustedDirNotExist);
     }
 
     let mut certs = vec![];
@@ -215,30 +312,176 @@ fn find_certs(
                 debug!(deb822_inner_signed_by_str);
             }
             Signature::KeyPath(paths) => {
-                for p in paths {
-                    if p.is_absolute() {
-                        certs.push(p.to_path_buf());
-                    } else {
-                        certs.push(rootfs.join("etc/apt/trusted.gpg.d").join(p))
-                    }
-                }
+                certs.extend(paths.iter().map(|p| signed_by_path(rootfs, p)));
             }
         }
     } else {
-        for i in dir.iter().flatten() {
-            let path = i.path();
-            let ext = path.extension().and_then(|x| x.to_str());
-            if ext == Some("gpg") || ext == Some("asc") {
-                certs.push(i.path().to_path_buf());
-            }
-        }
-
-        let trust_main = rootfs.join("etc/apt/trusted.gpg").to_path_buf();
-
-        if trust_main.is_file() {
-            certs.push(trust_main);
-        }
+        certs = system_keyrings(rootfs);
     }
 
     Ok((certs, deb822_inner_signed_by_str))
 }
+
+/// Sign Release file with the secret key in `key`, returns clearsigned InRelease and
+/// ASCII-armored detached signature (Release.gpg)
+pub fn sign_release(release: &str, key: impl AsRef<Path>) -> VerifyResult<(String, String)> {
+    let key = key.as_ref();
+    let cert = Cert::from_file(key)
+        .map_err(|e| VerifyError::CertParseFileError(key.display().to_string(), e))?;
+
+    let p = StandardPolicy::new();
+
+    let Some(ka) = cert
+        .keys()
+        .unencrypted_secret()
+        .with_policy(&p, None)
+        .supported()
+        .alive()
+        .revoked(false)
+        .for_signing()
+        .next()
+    else {
+        return Err(VerifyError::NoSigningKey(key.display().to_string()));
+    };
+
+    let keypair = ka.key().clone().into_keypair()?;
+
+    let mut inrelease = vec![];
+    let message = Message::new(&mut inrelease);
+    let mut signer = Signer::new(message, keypair.clone()).cleartext().build()?;
+    signer
+        .write_all(release.as_bytes())
+        .map_err(anyhow::Error::from)?;
+    signer.finalize()?;
+
+    let mut detached = vec![];
+    let message = Message::new(&mut detached);
+    let message = Armorer::new(message).kind(armor::Kind::Signature).build()?;
+    let mut signer = Signer::new(message, keypair).detached().build()?;
+    signer
+        .write_all(release.as_bytes())
+        .map_err(anyhow::Error::from)?;
+    signer.finalize()?;
+
+    Ok((
+        String::from_utf8_lossy(&inrelease).to_string(),
+        String::from_utf8_lossy(&detached).to_string(),
+    ))
+}
+
+#[test]
+fn test_sign_release() {
+    use sequoia_openpgp::{
+        cert::{CertBuilder, CipherSuite},
+        serialize::Serialize,
+    };
+
+    let (cert, _) = CertBuilder::general_purpose(None, Some("oma <oma@example.com>"))
+        .set_cipher_suite(CipherSuite::RSA2k)
+        .generate()
+        .unwrap();
+
+    let dir = std::env::temp_dir().join(format!("oma-sign-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let key = dir.join("key.asc");
+    let mut f = std::fs::File::create(&key).unwrap();
+    cert.as_tsk().serialize(&mut f).unwrap();
+
+    let release = "Origin: local\nSHA256:\n e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855 0 Packages\n";
+    let (inrelease, detached) = sign_release(release, &key).unwrap();
+
+    let helper = || InReleaseVerifier {
+        certs: vec![cert.clone()],
+        max_signature_age: None,
+        signers: vec![],
+    };
+
+    let policy = SignaturePolicy::default();
+    let (text, signers) = verify_clearsigned(&inrelease, &policy, helper()).unwrap();
+    assert_eq!(text, release);
+    assert_eq!(signers.len(), 1);
+    assert_eq!(signers[0].fingerprint, cert.fingerprint().to_hex());
+
+    let p = policy.standard_policy();
+    let mut v = DetachedVerifierBuilder::from_bytes(detached.as_bytes())
+        .unwrap()
+        .with_policy(&p, None, helper())
+        .unwrap();
+    v.verify_bytes(release).unwrap();
+
+    let strict = SignaturePolicy {
+        min_rsa_bits: 3072,
+        ..Default::default()
+    };
+
+    let res = verify_clearsigned(&inrelease, &strict, helper());
+    assert!(matches!(res, Err(VerifyError::BadKey(..))));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_any_good_signature() {
+    use sequoia_openpgp::cert::{CertBuilder, CipherSuite};
+
+    let gen = |suite| {
+        CertBuilder::general_purpose(None, Some("oma <oma@example.com>"))
+            .set_cipher_suite(suite)
+            .generate()
+            .unwrap()
+            .0
+    };
+
+    let weak = gen(CipherSuite::RSA2k);
+    let strong = gen(CipherSuite::Cv25519);
+
+    let p = StandardPolicy::new();
+    let keypair = |cert: &Cert| {
+        cert.keys()
+            .unencrypted_secret()
+            .with_policy(&p, None)
+            .for_signing()
+            .next()
+            .unwrap()
+            .key()
+            .clone()
+            .into_keypair()
+            .unwrap()
+    };
+
+    let release = "Origin: local\n";
+    let mut inrelease = vec![];
+    let message = Message::new(&mut inrelease);
+    let mut signer = Signer::new(message, keypair(&weak))
+        .add_signer(keypair(&strong))
+        .cleartext()
+        .build()
+        .unwrap();
+    signer.write_all(release.as_bytes()).unwrap();
+    signer.finalize().unwrap();
+    let inrelease = String::from_utf8(inrelease).unwrap();
+
+    let strict = SignaturePolicy {
+        min_rsa_bits: 3072,
+        ..Default::default()
+    };
+
+    let helper = |certs: Vec<Cert>| InReleaseVerifier {
+        certs,
+        max_signature_age: None,
+        signers: vec![],
+    };
+
+    // RSA 2048 的签名被拒绝，但 Ed25519 的签名有效
+    let (_, signers) = verify_clearsigned(
+        &inrelease,
+        &strict,
+        helper(vec![weak.clone(), strong.clone()]),
+    )
+    .unwrap();
+    assert_eq!(signers.len(), 1);
+    assert_eq!(signers[0].fingerprint, strong.fingerprint().to_hex());
+
+    let res = verify_clearsigned(&inrelease, &strict, helper(vec![weak]));
+    assert!(matches!(res, Err(VerifyError::BadKey(..))));
+}
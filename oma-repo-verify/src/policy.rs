@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use sequoia_openpgp::{
+    policy::{AsymmetricAlgorithm, StandardPolicy},
+    types::HashAlgorithm,
+};
+
+/// Rules to accept repository signatures
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignaturePolicy {
+    /// Accept signatures and keys using SHA-1
+    pub allow_sha1: bool,
+    /// Minimum size of RSA keys in bits, rounded down to 1024, 2048, 3072 or 4096
+    pub min_rsa_bits: u32,
+    /// Reject signatures older than this
+    pub max_signature_age: Option<Duration>,
+}
+
+impl Default for SignaturePolicy {
+    fn default() -> Self {
+        // Allow SHA-1 (considering it safe, whereas sequoia_openpgp's standard
+        // policy forbids it), as many third party APT repositories still uses
+        // SHA-1 to sign their repository metadata (such as InRelease).
+        //
+        // Also allow RSA-1024.
+        Self {
+            allow_sha1: true,
+            min_rsa_bits: 1024,
+            max_signature_age: None,
+        }
+    }
+}
+
+impl SignaturePolicy {
+    /// sequoia policy of these rules
+    pub fn standard_policy(&self) -> StandardPolicy<'static> {
+        let mut p = StandardPolicy::new();
+
+        if self.allow_sha1 {
+            p.accept_hash(HashAlgorithm::SHA1);
+        } else {
+            p.reject_hash(HashAlgorithm::SHA1);
+        }
+
+        for (algo, bits) in [
+            (AsymmetricAlgorithm::RSA1024, 1024),
+            (AsymmetricAlgorithm::RSA2048, 2048),
+            (AsymmetricAlgorithm::RSA3072, 3072),
+            (AsymmetricAlgorithm::RSA4096, 4096),
+        ] {
+            if bits >= self.min_rsa_bits {
+                p.accept_asymmetric_algo(algo);
+            } else {
+                p.reject_asymmetric_algo(algo);
+            }
+        }
+
+        p
+    }
+}
+
+/// Signature policy with per-source overrides
+#[derive(Debug, Clone, Default)]
+pub struct SignaturePolicies {
+    default: SignaturePolicy,
+    /// (URL prefix, policy)
+    overrides: Vec<(String, SignaturePolicy)>,
+}
+
+impl SignaturePolicies {
+    pub fn new(default: SignaturePolicy) -> Self {
+        Self {
+            default,
+            overrides: vec![],
+        }
+    }
+
+    pub fn default_policy(&self) -> &SignaturePolicy {
+        &self.default
+    }
+
+    /// Use `policy` for sources whose URL starts with `url`
+    pub fn add_override(&mut self, url: &str, policy: SignaturePolicy) {
+        self.overrides
+            .push((url.trim_end_matches('/').to_string(), policy));
+    }
+
+    /// Policy of the source at `url`, the longest matching override wins
+    pub fn get(&self, url: &str) -> &SignaturePolicy {
+        let url = url.trim_end_matches('/');
+
+        self.overrides
+            .iter()
+            .filter(|(prefix, _)| {
+                url.strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, p)| p)
+            .unwrap_or(&self.default)
+    }
+}
+
+#[test]
+fn test_policy_overrides() {
+    let strict = SignaturePolicy {
+        allow_sha1: false,
+        min_rsa_bits: 3072,
+        max_signature_age: None,
+    };
+
+    let mut policies = SignaturePolicies::new(strict.clone());
+    policies.add_override(
+        "https://repo.example.com/legacy/",
+        SignaturePolicy::default(),
+    );
+
+    assert_eq!(
+        policies.get("https://repo.example.com/legacy"),
+        &SignaturePolicy::default()
+    );
+    assert_eq!(
+        policies.get("https://repo.example.com/legacy/debian/"),
+        &SignaturePolicy::default()
+    );
+    assert_eq!(
+        policies.get("https://repo.example.com/legacy-new/"),
+        &strict
+    );
+    assert_eq!(policies.get("https://deb.debian.org/debian"), &strict);
+}
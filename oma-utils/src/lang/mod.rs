@@ -0,0 +1,86 @@
+use std::env;
+
+/// Locale of messages, from `LC_ALL`, `LC_MESSAGES` or `LANG`, fallback to `C`
+pub fn env_lang() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|x| env::var(x).ok())
+        .find(|x| !x.is_empty())
+        .unwrap_or_else(|| "C".to_string())
+}
+
+/// Resolve `Acquire::Languages`, `environment` means languages from `env_lang`
+pub fn resolve_languages(configured: &[String], env_lang: &str) -> Vec<String> {
+    if configured.is_empty() {
+        return get_matches_language(env_lang)
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect();
+    }
+
+    if configured.iter().any(|x| x == "none") {
+        return vec![];
+    }
+
+    let mut res: Vec<String> = vec![];
+
+    for lang in configured {
+        let langs = if lang == "environment" {
+            get_matches_language(env_lang)
+        } else {
+            vec![lang.as_str()]
+        };
+
+        for lang in langs {
+            if !res.iter().any(|x| x == lang) {
+                res.push(lang.to_string());
+            }
+        }
+    }
+
+    res
+}
+
+fn get_matches_language(env_lang: &str) -> Vec<&str> {
+    let mut langs = vec![];
+    let env_lang = env_lang.split_once('.').map(|x| x.0).unwrap_or(env_lang);
+
+    let lang = if env_lang == "C" || env_lang == "POSIX" {
+        "en"
+    } else {
+        env_lang
+    };
+
+    langs.push(lang);
+
+    // en_US.UTF-8 => en
+    if let Some((a, _)) = lang.split_once('_') {
+        langs.push(a);
+    }
+
+    langs
+}
+
+#[test]
+fn test_get_matches_language() {
+    assert_eq!(get_matches_language("C"), vec!["en"]);
+    assert_eq!(get_matches_language("zh_CN.UTF-8"), vec!["zh_CN", "zh"]);
+    assert_eq!(get_matches_language("en_US.UTF-8"), vec!["en_US", "en"]);
+}
+
+#[test]
+fn test_resolve_languages() {
+    assert_eq!(resolve_languages(&[], "zh_CN.UTF-8"), vec!["zh_CN", "zh"]);
+    assert!(resolve_languages(&["none".to_string()], "zh_CN.UTF-8").is_empty());
+    assert_eq!(
+        resolve_languages(
+            &[
+                "environment".to_string(),
+                "en".to_string(),
+                "zh".to_string()
+            ],
+            "zh_CN.UTF-8"
+        ),
+        vec!["zh_CN", "zh", "en"]
+    );
+}
@@ -9,6 +9,8 @@ pub use zbus;
 pub mod dpkg;
 #[cfg(feature = "human-bytes")]
 pub mod human_bytes;
+#[cfg(feature = "lang")]
+pub mod lang;
 #[cfg(feature = "oma")]
 pub mod oma;
 #[cfg(feature = "url-no-escape")]
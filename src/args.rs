@@ -15,6 +15,7 @@ use crate::{
     generate::Generate,
     history::{History, Undo},
     install::Install,
+    keys::Keys,
     list::List,
     mark::Mark,
     pick::Pick,
@@ -22,8 +23,10 @@ use crate::{
     rdepends::Rdepends,
     refresh::Refresh,
     remove::{Purge, Remove},
+    repo::Repo,
     search::Search,
     show::Show,
+    sources::Sources,
     tui::Tui,
     upgrade::Upgrade,
     GlobalOptions,
@@ -107,6 +110,13 @@ pub enum SubCmd {
     /// Manage testing topics enrollment
     #[command(visible_alias = "topic")]
     Topics(Topics),
+    /// Manage APT sources (sources.list and deb822 .sources)
+    Sources(Sources),
+    /// Build a local repository from .deb packages
+    Repo(Repo),
+    /// Manage trusted repository signing keys
+    #[command(visible_alias = "key")]
+    Keys(Keys),
     #[cfg(feature = "aosc")]
     /// Manage Mirrors enrollment
     #[command(visible_alias = "mirrors")]
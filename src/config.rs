@@ -1,8 +1,12 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap, time::Duration};
 
 use crate::fl;
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use oma_fetch::{LowSpeedLimit, RetryPolicy, DEFAULT_MAX_PER_HOST};
+use oma_pm::apt::NetworkOptions;
+use oma_refresh::snapshot::{DEFAULT_SNAPSHOT_ARCHIVES, DEFAULT_SNAPSHOT_URL};
+use oma_repo_verify::{SignaturePolicies, SignaturePolicy};
+use serde::{Deserialize, Deserializer, Serialize};
 use tracing::warn;
 
 #[cfg(feature = "aosc")]
@@ -15,6 +19,7 @@ const DEFAULT_CONFIG: &str = include_str!("../data/config/oma-debian.toml");
 pub struct Config {
     pub general: Option<GeneralConfig>,
     pub network: Option<NetworkConfig>,
+    pub verify: Option<VerifyConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -31,18 +36,165 @@ pub struct GeneralConfig {
     pub search_contents_println: bool,
     #[serde(default = "GeneralConfig::default_search_engine")]
     pub search_engine: String,
+    #[serde(default)]
+    pub appstream: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct NetworkConfig {
     #[serde(default = "NetworkConfig::default_network_thread")]
     pub network_threads: usize,
+    #[serde(default = "NetworkConfig::default_download_segments")]
+    pub download_segments: usize,
+    #[serde(default)]
+    pub adaptive_threads: bool,
+    #[serde(default = "NetworkConfig::default_max_network_threads")]
+    pub max_network_threads: usize,
+    #[serde(default = "NetworkConfig::default_max_connections_per_host")]
+    pub max_connections_per_host: usize,
+    #[serde(default, deserialize_with = "deserialize_rate")]
+    pub limit_rate: u64,
+    #[serde(default, deserialize_with = "deserialize_rate")]
+    pub low_speed_limit: u64,
+    #[serde(default = "NetworkConfig::default_low_speed_time")]
+    pub low_speed_time: u64,
+    #[serde(default = "NetworkConfig::default_retry_times")]
+    pub retry_times: usize,
+    /// In seconds
+    #[serde(default = "NetworkConfig::default_retry_delay")]
+    pub retry_delay: u64,
+    /// In seconds
+    #[serde(default = "NetworkConfig::default_retry_max_delay")]
+    pub retry_max_delay: u64,
+    #[serde(default = "NetworkConfig::default_retry_jitter")]
+    pub retry_jitter: bool,
+    #[serde(default = "NetworkConfig::default_snapshot_url")]
+    pub snapshot_url: String,
+    #[serde(default = "NetworkConfig::default_snapshot_archives")]
+    pub snapshot_archives: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VerifyConfig {
+    #[serde(default = "VerifyConfig::default_allow_sha1")]
+    pub allow_sha1: bool,
+    #[serde(default = "VerifyConfig::default_min_rsa_bits")]
+    pub min_rsa_bits: u32,
+    /// In days, 0 means no limit
+    #[serde(default)]
+    pub max_signature_age: u64,
+    /// In days, 0 means never warn
+    #[serde(default = "VerifyConfig::default_key_expiry_warning")]
+    pub key_expiry_warning: u64,
+    #[serde(default)]
+    pub deb_signature: DebSignaturePolicy,
+    /// Source URL prefix => override
+    #[serde(default)]
+    pub sources: HashMap<String, VerifyOverride>,
+}
+
+/// How to handle signatures of local .deb packages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebSignaturePolicy {
+    #[default]
+    Off,
+    /// Warn about unsigned or badly signed packages
+    Warn,
+    /// Refuse unsigned or badly signed packages
+    Require,
+}
+
+/// Per-source verify config, unset fields fall back to `[verify]`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VerifyOverride {
+    pub allow_sha1: Option<bool>,
+    pub min_rsa_bits: Option<u32>,
+    pub max_signature_age: Option<u64>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            network_threads: Self::default_network_thread(),
+            download_segments: Self::default_download_segments(),
+            adaptive_threads: false,
+            max_network_threads: Self::default_max_network_threads(),
+            max_connections_per_host: Self::default_max_connections_per_host(),
+            limit_rate: 0,
+            low_speed_limit: 0,
+            low_speed_time: Self::default_low_speed_time(),
+            retry_times: Self::default_retry_times(),
+            retry_delay: Self::default_retry_delay(),
+            retry_max_delay: Self::default_retry_max_delay(),
+            retry_jitter: Self::default_retry_jitter(),
+            snapshot_url: Self::default_snapshot_url(),
+            snapshot_archives: Self::default_snapshot_archives(),
+        }
+    }
 }
 
 impl NetworkConfig {
     pub const fn default_network_thread() -> usize {
         4
     }
+
+    pub const fn default_download_segments() -> usize {
+        1
+    }
+
+    pub const fn default_max_network_threads() -> usize {
+        16
+    }
+
+    pub const fn default_max_connections_per_host() -> usize {
+        DEFAULT_MAX_PER_HOST
+    }
+
+    pub const fn default_low_speed_time() -> u64 {
+        30
+    }
+
+    pub const fn default_retry_times() -> usize {
+        3
+    }
+
+    pub const fn default_retry_delay() -> u64 {
+        1
+    }
+
+    pub const fn default_retry_max_delay() -> u64 {
+        60
+    }
+
+    pub const fn default_retry_jitter() -> bool {
+        true
+    }
+
+    pub fn default_snapshot_url() -> String {
+        DEFAULT_SNAPSHOT_URL.to_string()
+    }
+
+    pub fn default_snapshot_archives() -> Vec<String> {
+        DEFAULT_SNAPSHOT_ARCHIVES
+            .iter()
+            .map(|x| x.to_string())
+            .collect()
+    }
+}
+
+impl VerifyConfig {
+    pub const fn default_allow_sha1() -> bool {
+        true
+    }
+
+    pub const fn default_min_rsa_bits() -> u32 {
+        1024
+    }
+
+    pub const fn default_key_expiry_warning() -> u64 {
+        30
+    }
 }
 
 impl GeneralConfig {
@@ -102,6 +254,145 @@ impl Config {
             .unwrap_or_else(NetworkConfig::default_network_thread)
     }
 
+    pub fn download_segments(&self) -> usize {
+        self.network
+            .as_ref()
+            .map(|x| x.download_segments)
+            .unwrap_or_else(NetworkConfig::default_download_segments)
+    }
+
+    /// Upper bound of network threads if they are adjusted by throughput, `None` means fixed
+    pub fn adaptive_threads(&self) -> Option<usize> {
+        self.network
+            .as_ref()
+            .filter(|x| x.adaptive_threads)
+            .map(|x| x.max_network_threads.max(x.network_threads))
+    }
+
+    pub fn max_connections_per_host(&self) -> usize {
+        self.network
+            .as_ref()
+            .map(|x| x.max_connections_per_host)
+            .unwrap_or_else(NetworkConfig::default_max_connections_per_host)
+            .max(1)
+    }
+
+    /// Bandwidth limit in bytes per second, `None` means unlimited
+    pub fn limit_rate(&self) -> Option<u64> {
+        self.network
+            .as_ref()
+            .map(|x| x.limit_rate)
+            .filter(|x| *x != 0)
+    }
+
+    /// Override bandwidth limit from command line
+    pub fn set_limit_rate(&mut self, rate: u64) {
+        self.network
+            .get_or_insert_with(NetworkConfig::default)
+            .limit_rate = rate;
+    }
+
+    /// Abort a mirror if its speed stays below the limit, `None` means never
+    pub fn low_speed_limit(&self) -> Option<LowSpeedLimit> {
+        self.network
+            .as_ref()
+            .filter(|x| x.low_speed_limit != 0 && x.low_speed_time != 0)
+            .map(|x| LowSpeedLimit {
+                bytes_per_sec: x.low_speed_limit,
+                time: Duration::from_secs(x.low_speed_time),
+            })
+    }
+
+    /// How to retry a failed download on the same mirror
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let default = NetworkConfig::default();
+        let network = self.network.as_ref().unwrap_or(&default);
+
+        RetryPolicy::builder()
+            .retry_times(network.retry_times)
+            .base_delay(Duration::from_secs(network.retry_delay))
+            .max_delay(Duration::from_secs(network.retry_max_delay))
+            .jitter(network.retry_jitter)
+            .build()
+    }
+
+    /// All download tuning above, for passing through to downloads at once
+    pub fn network_options(&self) -> NetworkOptions {
+        NetworkOptions {
+            network_thread: Some(self.network_thread()),
+            adaptive_threads: self.adaptive_threads(),
+            max_per_host: Some(self.max_connections_per_host()),
+            retry_policy: Some(self.retry_policy()),
+            segments: Some(self.download_segments()),
+            limit_rate: self.limit_rate(),
+            low_speed_limit: self.low_speed_limit(),
+        }
+    }
+
+    /// URL template of archive snapshots used by `oma refresh --snapshot`
+    pub fn snapshot_url(&self) -> String {
+        self.network
+            .as_ref()
+            .map(|x| x.snapshot_url.clone())
+            .unwrap_or_else(NetworkConfig::default_snapshot_url)
+    }
+
+    /// Archives (last path component of source URLs) which have snapshots at `snapshot_url`
+    pub fn snapshot_archives(&self) -> Vec<String> {
+        self.network
+            .as_ref()
+            .map(|x| x.snapshot_archives.clone())
+            .unwrap_or_else(NetworkConfig::default_snapshot_archives)
+    }
+
+    /// Repository signature policy from `[verify]`, with per-source overrides
+    pub fn signature_policy(&self) -> SignaturePolicies {
+        let Some(verify) = &self.verify else {
+            return SignaturePolicies::default();
+        };
+
+        let default = SignaturePolicy {
+            allow_sha1: verify.allow_sha1,
+            min_rsa_bits: verify.min_rsa_bits,
+            max_signature_age: from_days(verify.max_signature_age),
+        };
+
+        let mut policies = SignaturePolicies::new(default.clone());
+
+        for (url, o) in &verify.sources {
+            policies.add_override(
+                url,
+                SignaturePolicy {
+                    allow_sha1: o.allow_sha1.unwrap_or(default.allow_sha1),
+                    min_rsa_bits: o.min_rsa_bits.unwrap_or(default.min_rsa_bits),
+                    max_signature_age: o
+                        .max_signature_age
+                        .map(from_days)
+                        .unwrap_or(default.max_signature_age),
+                },
+            );
+        }
+
+        policies
+    }
+
+    /// Warn about signing keys expiring within this duration, `None` means never
+    pub fn key_expiry_warning(&self) -> Option<Duration> {
+        from_days(
+            self.verify
+                .as_ref()
+                .map(|x| x.key_expiry_warning)
+                .unwrap_or_else(VerifyConfig::default_key_expiry_warning),
+        )
+    }
+
+    pub fn deb_signature(&self) -> DebSignaturePolicy {
+        self.verify
+            .as_ref()
+            .map(|x| x.deb_signature)
+            .unwrap_or_default()
+    }
+
     pub fn no_check_dbus(&self) -> bool {
         self.general
             .as_ref()
@@ -117,6 +408,11 @@ impl Config {
             .unwrap_or_else(GeneralConfig::default_no_refresh_topics)
     }
 
+    /// Download DEP-11 (AppStream) metadata even if apt is not configured to
+    pub fn appstream(&self) -> bool {
+        self.general.as_ref().is_some_and(|x| x.appstream)
+    }
+
     pub fn follow_terminal_color(&self) -> bool {
         self.general
             .as_ref()
@@ -138,3 +434,119 @@ impl Config {
             .unwrap_or_else(|| Cow::Owned(GeneralConfig::default_search_engine()))
     }
 }
+
+fn from_days(days: u64) -> Option<Duration> {
+    (days != 0).then(|| Duration::from_secs(days * 24 * 60 * 60))
+}
+
+/// Parse bandwidth like `500K`, `2M` or `1048576` into bytes per second
+pub fn parse_rate(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+
+    let num = num
+        .parse::<u64>()
+        .map_err(|_| anyhow::anyhow!("Invalid rate: {s}"))?;
+
+    let unit: u64 = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1024,
+        "M" | "MB" | "MIB" => 1024 * 1024,
+        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+        _ => return Err(anyhow::anyhow!("Invalid rate unit: {s}")),
+    };
+
+    num.checked_mul(unit)
+        .ok_or_else(|| anyhow::anyhow!("Rate is too large: {s}"))
+}
+
+fn deserialize_rate<'de, D>(deserializer: D) -> std::result::Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Rate {
+        Bytes(u64),
+        Human(String),
+    }
+
+    match Rate::deserialize(deserializer)? {
+        Rate::Bytes(b) => Ok(b),
+        Rate::Human(s) => parse_rate(&s).map_err(serde::de::Error::custom),
+    }
+}
+
+#[test]
+fn test_parse_rate() {
+    assert_eq!(parse_rate("0").unwrap(), 0);
+    assert_eq!(parse_rate("1024").unwrap(), 1024);
+    assert_eq!(parse_rate("500K").unwrap(), 500 * 1024);
+    assert_eq!(parse_rate("2m").unwrap(), 2 * 1024 * 1024);
+    assert_eq!(parse_rate("1GiB").unwrap(), 1024 * 1024 * 1024);
+    assert!(parse_rate("fast").is_err());
+    assert!(parse_rate("10X").is_err());
+    assert!(parse_rate("18446744073709551615G").is_err());
+    assert_eq!(
+        parse_rate("16777215G").unwrap(),
+        16777215 * 1024 * 1024 * 1024
+    );
+}
+
+#[test]
+fn test_retry_policy() {
+    let config: Config = toml::from_str("[network]\nretry_times = 5\nretry_delay = 2\n").unwrap();
+    let policy = config.retry_policy();
+    assert_eq!(policy.retry_times, 5);
+    assert_eq!(policy.base_delay, Duration::from_secs(2));
+    assert_eq!(policy.max_delay, Duration::from_secs(60));
+    assert!(policy.jitter);
+
+    let config: Config = toml::from_str(DEFAULT_CONFIG).unwrap();
+    let policy = config.retry_policy();
+    assert_eq!(policy.retry_times, RetryPolicy::default().retry_times);
+    assert_eq!(policy.base_delay, RetryPolicy::default().base_delay);
+}
+
+#[test]
+fn test_low_speed_limit() {
+    let config: Config = toml::from_str(DEFAULT_CONFIG).unwrap();
+    assert!(config.low_speed_limit().is_none());
+
+    let config: Config = toml::from_str("[network]\nlow_speed_limit = \"1K\"\n").unwrap();
+    let limit = config.low_speed_limit().unwrap();
+    assert_eq!(limit.bytes_per_sec, 1024);
+    assert_eq!(limit.time, Duration::from_secs(30));
+}
+
+#[test]
+fn test_signature_policy() {
+    let config: Config = toml::from_str(
+        r#"
+[verify]
+allow_sha1 = false
+min_rsa_bits = 3072
+deb_signature = "require"
+
+[verify.sources."https://repo.example.com/legacy"]
+allow_sha1 = true
+max_signature_age = 30
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.deb_signature(), DebSignaturePolicy::Require);
+
+    let policies = config.signature_policy();
+    let default = policies.get("https://deb.debian.org/debian");
+    assert!(!default.allow_sha1);
+    assert_eq!(default.max_signature_age, None);
+
+    let legacy = policies.get("https://repo.example.com/legacy/");
+    assert!(legacy.allow_sha1);
+    assert_eq!(legacy.min_rsa_bits, 3072);
+    assert_eq!(
+        legacy.max_signature_age,
+        Some(Duration::from_secs(30 * 24 * 60 * 60))
+    );
+}
@@ -17,9 +17,11 @@ use oma_pm::AptErrors;
 use oma_pm::{apt::OmaAptError, matches::MatcherError};
 use oma_refresh::db::RefreshError;
 use oma_refresh::inrelease::InReleaseError;
+use oma_refresh::repo::RepoError;
 use oma_repo_verify::VerifyError;
 use oma_utils::dbus::OmaDbusError;
 use oma_utils::dpkg::DpkgError;
+use oma_utils::human_bytes::HumanBytes;
 
 #[cfg(feature = "aosc")]
 use oma_topics::OmaTopicsError;
@@ -325,28 +327,7 @@ impl From<RefreshError> for OutputError {
                 source: None,
             },
             RefreshError::InReleaseParseError(path, e) => match e {
-                InReleaseError::VerifyError(e) => match e {
-                    VerifyError::CertParseFileError(p, e) => Self {
-                        description: fl!("fail-load-certs-from-file", path = p),
-                        source: Some(Box::new(io::Error::new(ErrorKind::Other, e))),
-                    },
-                    VerifyError::BadCertFile(p, e) => Self {
-                        description: fl!("cert-file-is-bad", path = p),
-                        source: Some(Box::new(io::Error::new(ErrorKind::Other, e))),
-                    },
-                    VerifyError::TrustedDirNotExist => Self {
-                        description: e.to_string(),
-                        source: None,
-                    },
-                    VerifyError::Anyhow(e) => Self {
-                        description: e.to_string(),
-                        source: None,
-                    },
-                    VerifyError::FailedToReadInRelease(e) => Self {
-                        description: fl!("failed-to-read-decode-inrelease"),
-                        source: Some(Box::new(e)),
-                    },
-                },
+                InReleaseError::VerifyError(e) => OutputError::from(e),
                 InReleaseError::BadInReleaseData => Self {
                     description: fl!("can-not-parse-date"),
                     source: None,
@@ -429,6 +410,27 @@ impl From<RefreshError> for OutputError {
                 description: value.to_string(),
                 source: None,
             },
+            RefreshError::PDiffError(e) => Self {
+                description: e.to_string(),
+                source: None,
+            },
+            RefreshError::ReleaseRollback(path, date, last) => Self {
+                description: fl!(
+                    "release-rollback",
+                    filename = path,
+                    date = date,
+                    last = last
+                ),
+                source: None,
+            },
+            RefreshError::NoMatchingSource(s) => Self {
+                description: fl!("refresh-no-matching-source", source = s),
+                source: None,
+            },
+            RefreshError::InvalidSnapshot(s) => Self {
+                description: fl!("refresh-invalid-snapshot", time = s),
+                source: None,
+            },
         }
         #[cfg(not(feature = "aosc"))]
         match value {
@@ -451,28 +453,7 @@ impl From<RefreshError> for OutputError {
                 source: None,
             },
             RefreshError::InReleaseParseError(p, e) => match e {
-                InReleaseError::VerifyError(e) => match e {
-                    VerifyError::CertParseFileError(p, e) => Self {
-                        description: fl!("fail-load-certs-from-file", path = p),
-                        source: Some(Box::new(io::Error::new(ErrorKind::Other, e))),
-                    },
-                    VerifyError::BadCertFile(p, e) => Self {
-                        description: fl!("cert-file-is-bad", path = p),
-                        source: Some(Box::new(io::Error::new(ErrorKind::Other, e))),
-                    },
-                    VerifyError::TrustedDirNotExist => Self {
-                        description: e.to_string(),
-                        source: None,
-                    },
-                    VerifyError::Anyhow(e) => Self {
-                        description: e.to_string(),
-                        source: None,
-                    },
-                    VerifyError::FailedToReadInRelease(e) => Self {
-                        description: fl!("failed-to-read-decode-inrelease"),
-                        source: Some(Box::new(e)),
-                    },
-                },
+                InReleaseError::VerifyError(e) => OutputError::from(e),
                 InReleaseError::BadInReleaseData => Self {
                     description: fl!("can-not-parse-date"),
                     source: None,
@@ -555,6 +536,110 @@ impl From<RefreshError> for OutputError {
                 description: value.to_string(),
                 source: None,
             },
+            RefreshError::PDiffError(e) => Self {
+                description: e.to_string(),
+                source: None,
+            },
+            RefreshError::ReleaseRollback(path, date, last) => Self {
+                description: fl!(
+                    "release-rollback",
+                    filename = path,
+                    date = date,
+                    last = last
+                ),
+                source: None,
+            },
+            RefreshError::NoMatchingSource(s) => Self {
+                description: fl!("refresh-no-matching-source", source = s),
+                source: None,
+            },
+            RefreshError::InvalidSnapshot(s) => Self {
+                description: fl!("refresh-invalid-snapshot", time = s),
+                source: None,
+            },
+        }
+    }
+}
+
+impl From<RepoError> for OutputError {
+    fn from(value: RepoError) -> Self {
+        match value {
+            RepoError::FailedToOperateDirOrFile(path, e) => Self {
+                description: fl!("failed-to-operate-path", p = path),
+                source: Some(Box::new(e)),
+            },
+            RepoError::BadDebFile(path, e) => Self {
+                description: fl!("repo-bad-deb", path = path),
+                source: Some(Box::new(e)),
+            },
+            RepoError::SignReleaseFailed(e) => Self {
+                description: fl!("repo-sign-failed"),
+                source: Some(Box::new(e)),
+            },
+        }
+    }
+}
+
+impl From<VerifyError> for OutputError {
+    fn from(e: VerifyError) -> Self {
+        match e {
+            VerifyError::CertParseFileError(p, e) => Self {
+                description: fl!("fail-load-certs-from-file", path = p),
+                source: Some(Box::new(io::Error::new(ErrorKind::Other, e))),
+            },
+            VerifyError::BadCertFile(p, e) => Self {
+                description: fl!("cert-file-is-bad", path = p),
+                source: Some(Box::new(io::Error::new(ErrorKind::Other, e))),
+            },
+            VerifyError::TrustedDirNotExist => Self {
+                description: e.to_string(),
+                source: None,
+            },
+            VerifyError::Anyhow(e) => Self {
+                description: e.to_string(),
+                source: None,
+            },
+            VerifyError::FailedToReadInRelease(e) => Self {
+                description: fl!("failed-to-read-decode-inrelease"),
+                source: Some(Box::new(e)),
+            },
+            VerifyError::NoSigningKey(_) => Self {
+                description: e.to_string(),
+                source: None,
+            },
+            VerifyError::BadKey(fingerprint, reason) => Self {
+                description: fl!("verify-bad-key", fingerprint = fingerprint, reason = reason),
+                source: None,
+            },
+            VerifyError::BadSignature(fingerprint, reason) => Self {
+                description: fl!(
+                    "verify-bad-signature",
+                    fingerprint = fingerprint,
+                    reason = reason
+                ),
+                source: None,
+            },
+            VerifyError::SignatureTooOld(fingerprint, age, max) => Self {
+                description: fl!(
+                    "verify-signature-too-old",
+                    fingerprint = fingerprint,
+                    age = age,
+                    max = max
+                ),
+                source: None,
+            },
+            VerifyError::FailedToReadDeb(p, e) => Self {
+                description: fl!("failed-to-operate-path", p = p),
+                source: Some(Box::new(e)),
+            },
+            VerifyError::DebNotSigned(p) => Self {
+                description: fl!("deb-not-signed", path = p),
+                source: None,
+            },
+            VerifyError::BadDebSignature(p, reason) => Self {
+                description: fl!("deb-bad-signature", path = p, reason = reason),
+                source: None,
+            },
         }
     }
 }
@@ -939,6 +1024,7 @@ fn oma_download_error(e: DownloadError) -> OutputError {
             source: Some(Box::new(e)),
         },
         DownloadError::ReqwestError(e) => OutputError::from(e),
+        DownloadError::RateLimited { source, .. } => OutputError::from(source),
         DownloadError::ChecksumError(e) => oma_checksum_error(e),
         DownloadError::FailedOpenLocalSourceFile(path, e) => OutputError {
             description: fl!("can-not-parse-sources-list", path = path.to_string()),
@@ -952,6 +1038,15 @@ fn oma_download_error(e: DownloadError) -> OutputError {
             description: e.to_string(),
             source: None,
         },
+        DownloadError::LowSpeed(filename, limit, time) => OutputError {
+            description: fl!(
+                "download-too-slow",
+                filename = filename,
+                limit = HumanBytes(limit).to_string(),
+                secs = time
+            ),
+            source: None,
+        },
     }
 }
 
@@ -5,7 +5,9 @@ use oma_pm::{
     progress::{get_apt_progress_string, terminal_height, terminal_width, InstallProgressManager},
 };
 
-use crate::subcommand::utils::is_terminal;
+use serde_json::json;
+
+use crate::{pb::write_json_event, subcommand::utils::is_terminal};
 
 pub struct OmaInstallProgressManager {
     yes: bool,
@@ -113,3 +115,25 @@ impl InstallProgressManager for NoInstallProgressManager {
         false
     }
 }
+
+pub struct JsonInstallProgressManager;
+
+impl InstallProgressManager for JsonInstallProgressManager {
+    fn status_change(&self, pkgname: &str, steps_done: u64, total_steps: u64, _config: &AptConfig) {
+        write_json_event(json!({
+            "type": "install",
+            "event": "status_change",
+            "package": pkgname,
+            "steps_done": steps_done,
+            "total_steps": total_steps,
+        }));
+    }
+
+    fn no_interactive(&self) -> bool {
+        true
+    }
+
+    fn use_pty(&self) -> bool {
+        false
+    }
+}
@@ -13,6 +13,7 @@ mod error;
 mod install_progress;
 mod lang;
 mod pb;
+mod proxy;
 mod subcommand;
 mod table;
 mod tui;
@@ -22,17 +23,19 @@ mod utils;
 mod egg;
 
 use args::{print_version, CliExecuter, OhManagerAilurus};
-use clap::{ArgAction, Args, ColorChoice, Parser};
+use clap::{ArgAction, Args, ColorChoice, Parser, ValueEnum};
 use error::OutputError;
 use i18n_embed::{DesktopLanguageRequester, Localizer};
 use lang::LANGUAGE_LOADER;
 use oma_console::print::{termbg, OmaColorFormat};
 use oma_console::writer::{writeln_inner, MessageType, Writer};
 use oma_console::OmaLayer;
+use oma_pm::apt::AptConfig;
 use oma_utils::dbus::{create_dbus_connection, get_another_oma_status, OmaDbusError};
 use oma_utils::oma::{terminal_ring, unlock_oma};
 use oma_utils::OsRelease;
-use reqwest::Client;
+use proxy::AptProxy;
+use reqwest::{Client, ClientBuilder};
 use rustix::stdio::stdout;
 use subcommand::utils::{is_terminal, LockError};
 use tokio::runtime::Runtime;
@@ -65,14 +68,19 @@ static RT: LazyLock<Runtime> = LazyLock::new(|| {
         .build()
         .expect("Failed to init async runtime")
 });
-static HTTP_CLIENT: LazyLock<Client> = LazyLock::new(|| {
-    Client::builder()
-        .user_agent(APP_USER_AGENT)
-        .build()
-        .unwrap()
-});
+static HTTP_CLIENT: LazyLock<Client> =
+    LazyLock::new(|| http_client_builder(&AptConfig::new()).build().unwrap());
 static WRITER: LazyLock<Writer> = LazyLock::new(Writer::default);
 
+fn http_client_builder(apt_config: &AptConfig) -> ClientBuilder {
+    let builder = Client::builder().user_agent(APP_USER_AGENT);
+
+    match AptProxy::new(apt_config).reqwest_proxy() {
+        Some(proxy) => builder.proxy(proxy),
+        None => builder,
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct GlobalOptions {
     /// Run oma in “dry-run” mode
@@ -111,6 +119,38 @@ pub struct GlobalOptions {
     /// Set apt options
     #[arg(long, global = true, action = ArgAction::Append)]
     apt_options: Vec<String>,
+    /// Limit download bandwidth (e.g. 500K, 2M)
+    #[arg(
+        long,
+        global = true,
+        value_parser = config::parse_rate,
+        long_help = "Limit total download bandwidth shared by all download tasks, in bytes per second. Accepts K, M and G suffixes (e.g. 500K, 2M). Overrides `limit_rate' in /etc/oma.toml"
+    )]
+    limit_rate: Option<u64>,
+    /// Progress output format
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = ProgressFormat::Text,
+        long_help = "Progress output format. `json' writes one JSON object per download, refresh and dpkg progress event, for frontends wrapping oma"
+    )]
+    progress_format: ProgressFormat,
+    /// Write JSON progress events to this file descriptor (implies --progress-format=json)
+    #[arg(
+        long,
+        global = true,
+        value_name = "FD",
+        value_parser = clap::value_parser!(i32).range(0..),
+        long_help = "Write JSON progress events to this already open file descriptor (implies --progress-format=json). Without it, events are written to stderr, mixed with warnings of oma and dpkg, so frontends should pass a dedicated file descriptor such as a pipe"
+    )]
+    progress_fd: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressFormat {
+    Text,
+    Json,
 }
 
 fn main() {
@@ -251,12 +291,26 @@ fn try_main(oma: OhManagerAilurus) -> Result<i32, OutputError> {
     }
 
     // Init config file
-    let config = Config::read()?;
+    let mut config = Config::read()?;
+
+    if let Some(rate) = oma.global.limit_rate {
+        config.set_limit_rate(rate);
+    }
 
     init_color_formatter(&oma, &config);
 
-    let no_progress =
-        oma.global.no_progress || !is_terminal() || oma.global.debug || oma.global.dry_run;
+    if oma.global.progress_format == ProgressFormat::Json || oma.global.progress_fd.is_some() {
+        pb::init_json_progress(oma.global.progress_fd).map_err(|e| OutputError {
+            description: fl!("failed-to-open-progress-fd"),
+            source: Some(Box::new(e)),
+        })?;
+    }
+
+    let no_progress = oma.global.no_progress
+        || !is_terminal()
+        || oma.global.debug
+        || oma.global.dry_run
+        || pb::json_progress();
 
     match oma.subcmd {
         Some(subcmd) => subcmd.execute(&config, no_progress),
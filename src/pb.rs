@@ -1,10 +1,15 @@
 use std::{
     borrow::Cow,
     cell::OnceCell,
-    time::{Duration, Instant},
+    fs::File,
+    io::{self, Write},
+    os::fd::{BorrowedFd, RawFd},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime},
 };
 
 use ahash::{HashMap, RandomState};
+use chrono::{DateTime, Local, Utc};
 use oma_console::{
     console::style,
     indicatif::{MultiProgress, ProgressBar},
@@ -16,8 +21,11 @@ use oma_fetch::Event;
 use crate::{fl, msg, WRITER};
 use oma_refresh::db::Event as RefreshEvent;
 use oma_utils::human_bytes::HumanBytes;
+use serde_json::{json, Value};
 use tracing::{error, info, warn};
 
+static JSON_PROGRESS: OnceLock<Mutex<File>> = OnceLock::new();
+
 pub trait RenderDownloadProgress {
     fn render_progress(&mut self, rx: &flume::Receiver<Event>);
 }
@@ -149,6 +157,17 @@ impl RenderRefreshProgress for OmaMultiProgressBar {
                     )
                     .ok();
                 }
+                RefreshEvent::KeyExpiring {
+                    source,
+                    fingerprint,
+                    expires,
+                } => {
+                    self.writeln(
+                        &style("WARNING").yellow().bold().to_string(),
+                        &key_expiring_msg(source, fingerprint, expires),
+                    )
+                    .ok();
+                }
                 RefreshEvent::RunInvokeScript => {
                     let (sty, inv) = spinner_style();
                     let pb = self
@@ -227,6 +246,23 @@ impl OmaMultiProgressBar {
                 )
                 .ok();
             }
+            Event::RetryWait {
+                index: _,
+                err,
+                times,
+                wait,
+            } => {
+                self.writeln(
+                    &style("WARNING").yellow().bold().to_string(),
+                    &fl!(
+                        "download-retry-wait",
+                        e = err,
+                        secs = format!("{:.1}", wait.as_secs_f64()),
+                        retry = times
+                    ),
+                )
+                .ok();
+            }
             Event::DownloadDone { index: _, msg } => {
                 tracing::debug!("Downloaded {msg}");
             }
@@ -292,6 +328,13 @@ impl RenderRefreshProgress for NoProgressBar {
                     );
                     warn!("{}", fl!("skip-write-mirror"));
                 }
+                RefreshEvent::KeyExpiring {
+                    source,
+                    fingerprint,
+                    expires,
+                } => {
+                    warn!("{}", key_expiring_msg(source, fingerprint, expires));
+                }
                 RefreshEvent::RunInvokeScript => {
                     info!("{}", fl!("oma-refresh-success-invoke"));
                 }
@@ -302,6 +345,17 @@ impl RenderRefreshProgress for NoProgressBar {
     }
 }
 
+fn key_expiring_msg(source: String, fingerprint: String, expires: SystemTime) -> String {
+    fl!(
+        "refresh-key-expiring",
+        source = source,
+        fingerprint = fingerprint,
+        date = DateTime::<Local>::from(expires)
+            .format("%Y-%m-%d")
+            .to_string()
+    )
+}
+
 impl NoProgressBar {
     fn download_event(&mut self, event: Event) -> bool {
         match event {
@@ -338,6 +392,22 @@ impl NoProgressBar {
                     fl!("can-not-get-source-next-url", e = err.to_string())
                 );
             }
+            Event::RetryWait {
+                index: _,
+                err,
+                times,
+                wait,
+            } => {
+                warn!(
+                    "{}",
+                    fl!(
+                        "download-retry-wait",
+                        e = err,
+                        secs = format!("{:.1}", wait.as_secs_f64()),
+                        retry = times
+                    )
+                );
+            }
             Event::DownloadDone { index: _, msg } => {
                 WRITER.writeln("DONE", &msg).ok();
             }
@@ -351,3 +421,219 @@ impl NoProgressBar {
         false
     }
 }
+
+/// Write progress events as JSON lines to `fd`
+///
+/// The default is stderr, so that events never mix with the normal output on stdout,
+/// frontends should use a dedicated fd instead.
+pub fn init_json_progress(fd: Option<RawFd>) -> io::Result<()> {
+    let fd = fd.unwrap_or(2);
+
+    // 借用前确认 fd 已打开，否则 borrow_raw 是未定义行为
+    if fd < 0 || unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(io::Error::from_raw_os_error(libc::EBADF));
+    }
+
+    // 复制一份，避免与 stdout 等共用同一个 fd 时提前关闭
+    let file = File::from(rustix::io::dup(unsafe { BorrowedFd::borrow_raw(fd) })?);
+    JSON_PROGRESS.get_or_init(|| Mutex::new(file));
+
+    Ok(())
+}
+
+pub fn json_progress() -> bool {
+    JSON_PROGRESS.get().is_some()
+}
+
+pub fn write_json_event(value: Value) {
+    let Some(out) = JSON_PROGRESS.get() else {
+        return;
+    };
+
+    let mut out = out.lock().unwrap();
+    let mut line = value.to_string();
+    line.push('\n');
+    out.write_all(line.as_bytes()).ok();
+    out.flush().ok();
+}
+
+/// Minimum interval between two byte progress events of JSON progress
+const JSON_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Print one JSON object per event, for frontends wrapping oma
+///
+/// Byte progress (`progress_inc` and `global_progress`) is merged and written at most
+/// once per [`JSON_PROGRESS_INTERVAL`].
+#[derive(Default)]
+pub struct JsonProgress {
+    /// index => bytes not yet reported
+    pending_inc: HashMap<usize, u64>,
+    pending_global: Option<u64>,
+    last_flush: Option<Instant>,
+}
+
+impl RenderDownloadProgress for JsonProgress {
+    fn render_progress(&mut self, rx: &flume::Receiver<Event>) {
+        while let Ok(event) = rx.recv() {
+            if self.download_event("download", event) {
+                break;
+            }
+        }
+    }
+}
+
+impl RenderRefreshProgress for JsonProgress {
+    fn render_refresh_progress(&mut self, rx: &flume::Receiver<RefreshEvent>) {
+        while let Ok(event) = rx.recv() {
+            let value = match event {
+                RefreshEvent::DownloadEvent(event) => {
+                    self.download_event("refresh", event);
+                    continue;
+                }
+                RefreshEvent::ScanningTopic => {
+                    json!({ "type": "refresh", "event": "scanning_topic" })
+                }
+                RefreshEvent::ClosingTopic(topic) => {
+                    json!({ "type": "refresh", "event": "closing_topic", "topic": topic })
+                }
+                RefreshEvent::TopicNotInMirror { topic, mirror } => json!({
+                    "type": "refresh",
+                    "event": "topic_not_in_mirror",
+                    "topic": topic,
+                    "mirror": mirror,
+                }),
+                RefreshEvent::KeyExpiring {
+                    source,
+                    fingerprint,
+                    expires,
+                } => json!({
+                    "type": "refresh",
+                    "event": "key_expiring",
+                    "source": source,
+                    "fingerprint": fingerprint,
+                    "expires": DateTime::<Utc>::from(expires).to_rfc3339(),
+                }),
+                RefreshEvent::RunInvokeScript => {
+                    json!({ "type": "refresh", "event": "run_invoke_script" })
+                }
+                RefreshEvent::Done => {
+                    write_json_event(json!({ "type": "refresh", "event": "done" }));
+                    break;
+                }
+            };
+
+            write_json_event(value);
+        }
+    }
+}
+
+impl JsonProgress {
+    fn download_event(&mut self, ty: &str, event: Event) -> bool {
+        match event {
+            Event::ProgressInc { index, size } => {
+                *self.pending_inc.entry(index).or_default() += size;
+            }
+            Event::GlobalProgressSet(downloaded) => {
+                self.pending_global = Some(downloaded);
+            }
+            event => {
+                // 其他事件前先输出积累的进度，保证事件顺序
+                self.flush(ty);
+                return self.write_download_event(ty, event);
+            }
+        }
+
+        if self
+            .last_flush
+            .is_none_or(|x| x.elapsed() >= JSON_PROGRESS_INTERVAL)
+        {
+            self.flush(ty);
+        }
+
+        false
+    }
+
+    fn flush(&mut self, ty: &str) {
+        self.last_flush = Some(Instant::now());
+
+        for (index, size) in self.pending_inc.drain() {
+            write_json_event(json!({
+                "type": ty,
+                "event": "progress_inc",
+                "index": index,
+                "size": size,
+            }));
+        }
+
+        if let Some(downloaded) = self.pending_global.take() {
+            write_json_event(json!({
+                "type": ty,
+                "event": "global_progress",
+                "downloaded": downloaded,
+            }));
+        }
+    }
+
+    fn write_download_event(&mut self, ty: &str, event: Event) -> bool {
+        let mut all_done = false;
+
+        let mut value = match event {
+            Event::ChecksumMismatch {
+                index,
+                filename,
+                times,
+            } => json!({
+                "event": "checksum_mismatch",
+                "index": index,
+                "filename": filename,
+                "times": times,
+            }),
+            Event::GlobalProgressSet(downloaded) => {
+                json!({ "event": "global_progress", "downloaded": downloaded })
+            }
+            Event::ProgressDone(index) => json!({ "event": "progress_done", "index": index }),
+            Event::NewProgressSpinner { index, msg } => {
+                json!({ "event": "new_spinner", "index": index, "msg": msg })
+            }
+            Event::NewProgressBar { index, msg, size } => json!({
+                "event": "new_progress",
+                "index": index,
+                "msg": msg,
+                "size": size,
+            }),
+            Event::ProgressInc { index, size } => {
+                json!({ "event": "progress_inc", "index": index, "size": size })
+            }
+            Event::NextUrl { index, err } => {
+                json!({ "event": "next_url", "index": index, "error": err })
+            }
+            Event::RetryWait {
+                index,
+                err,
+                times,
+                wait,
+            } => json!({
+                "event": "retry_wait",
+                "index": index,
+                "error": err,
+                "times": times,
+                "wait": wait.as_secs_f64(),
+            }),
+            Event::DownloadDone { index, msg } => {
+                json!({ "event": "download_done", "index": index, "msg": msg })
+            }
+            Event::AllDone => {
+                all_done = true;
+                json!({ "event": "all_done" })
+            }
+            Event::NewGlobalProgressBar(total_size) => {
+                json!({ "event": "new_global_progress", "total_size": total_size })
+            }
+        };
+
+        value["type"] = ty.into();
+        write_json_event(value);
+
+        all_done
+    }
+}
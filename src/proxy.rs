@@ -0,0 +1,263 @@
+use std::{collections::HashMap, env};
+
+use oma_pm::apt::AptConfig;
+use reqwest::{Proxy, Url};
+use tracing::{debug, warn};
+
+const DIRECT: &str = "DIRECT";
+
+/// Proxy settings read from APT `Acquire::http::Proxy` and `Acquire::https::Proxy`
+#[derive(Debug, Default, Clone)]
+pub struct AptProxy {
+    http: SchemeProxy,
+    https: SchemeProxy,
+    env_http: Option<Url>,
+    env_https: Option<Url>,
+    /// Hosts from `no_proxy`, only used with proxies from the environment
+    env_no_proxy: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct SchemeProxy {
+    default: Option<ProxyTarget>,
+    hosts: HashMap<String, ProxyTarget>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ProxyTarget {
+    Direct,
+    Url(Url),
+}
+
+impl AptProxy {
+    pub fn new(config: &AptConfig) -> Self {
+        let mut proxy = Self {
+            http: SchemeProxy::new(config, "http"),
+            https: SchemeProxy::new(config, "https"),
+            ..Default::default()
+        };
+
+        // 一旦设置了自定义代理，reqwest 便不再读取环境变量，因此需要自行回退
+        if !proxy.is_empty() {
+            proxy.env_http = env_proxy("http_proxy");
+            proxy.env_https = env_proxy("https_proxy");
+            proxy.env_no_proxy = env::var("no_proxy")
+                .or_else(|_| env::var("NO_PROXY"))
+                .map(|x| parse_no_proxy(&x))
+                .unwrap_or_default();
+        }
+
+        debug!("APT proxy config: {proxy:?}");
+
+        proxy
+    }
+
+    fn is_empty(&self) -> bool {
+        self.http.is_empty() && self.https.is_empty()
+    }
+
+    /// Resolve proxy for url, `None` means connect directly
+    pub fn proxy_for(&self, url: &Url) -> Option<Url> {
+        let host = url.host_str()?;
+
+        // https 未设置时沿用 http 的配置，与 apt 的行为一致
+        let chain = match url.scheme() {
+            "http" => vec![&self.http],
+            "https" => vec![&self.https, &self.http],
+            _ => return None,
+        };
+
+        let target = chain
+            .iter()
+            .find_map(|x| x.hosts.get(host))
+            .or_else(|| chain.iter().find_map(|x| x.default.as_ref()));
+
+        match target {
+            Some(ProxyTarget::Direct) => None,
+            Some(ProxyTarget::Url(proxy)) => Some(proxy.clone()),
+            None if self.no_proxy(host) => None,
+            None if url.scheme() == "https" => self.env_https.clone().or(self.env_http.clone()),
+            None => self.env_http.clone(),
+        }
+    }
+
+    /// Whether `host` is excluded by `no_proxy`, subdomains included
+    fn no_proxy(&self, host: &str) -> bool {
+        // url 已将域名转为小写
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+
+        self.env_no_proxy
+            .iter()
+            .any(|x| x == "*" || host == x || host.ends_with(&format!(".{x}")))
+    }
+
+    /// Build reqwest proxy, `None` if APT has no proxy configured (use system proxy)
+    pub fn reqwest_proxy(self) -> Option<Proxy> {
+        if self.is_empty() {
+            return None;
+        }
+
+        Some(Proxy::custom(move |url| self.proxy_for(url)))
+    }
+}
+
+impl SchemeProxy {
+    fn new(config: &AptConfig, scheme: &str) -> Self {
+        let key = format!("Acquire::{scheme}::Proxy");
+        let mut res = Self::default();
+
+        let Some(tree) = config.tree(&key) else {
+            return res;
+        };
+
+        res.default = tree.value().and_then(|x| parse_target(&key, &x));
+
+        let Some(child) = tree.child() else {
+            return res;
+        };
+
+        for node in child.iter() {
+            let Some((host, value)) = node.tag().zip(node.value()) else {
+                continue;
+            };
+
+            if let Some(target) = parse_target(&format!("{key}::{host}"), &value) {
+                res.hosts.insert(host, target);
+            }
+        }
+
+        res
+    }
+
+    fn is_empty(&self) -> bool {
+        self.default.is_none() && self.hosts.is_empty()
+    }
+}
+
+fn parse_target(key: &str, value: &str) -> Option<ProxyTarget> {
+    if value.eq_ignore_ascii_case(DIRECT) {
+        return Some(ProxyTarget::Direct);
+    }
+
+    match Url::parse(value) {
+        Ok(url) => Some(ProxyTarget::Url(url)),
+        Err(e) => {
+            warn!("Invalid proxy {value} in {key}: {e}");
+            None
+        }
+    }
+}
+
+fn env_proxy(name: &str) -> Option<Url> {
+    env::var(name)
+        .or_else(|_| env::var(name.to_ascii_uppercase()))
+        .ok()
+        .and_then(|x| Url::parse(&x).ok())
+}
+
+/// Split `no_proxy` into lowercase host names, leading dots and ports are dropped
+fn parse_no_proxy(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|x| x.trim().trim_start_matches('.'))
+        .map(|x| match x.rsplit_once(':') {
+            // 不处理 IPv6 地址中的冒号
+            Some((host, port)) if !host.contains(':') && port.parse::<u16>().is_ok() => host,
+            _ => x,
+        })
+        .map(|x| x.trim_start_matches('[').trim_end_matches(']'))
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_ascii_lowercase())
+        .collect()
+}
+
+#[test]
+fn test_proxy_for() {
+    let proxy = Url::parse("http://proxy.example.com:3128").unwrap();
+    let https_proxy = Url::parse("http://secure-proxy.example.com:3128").unwrap();
+
+    let ap = AptProxy {
+        http: SchemeProxy {
+            default: Some(ProxyTarget::Url(proxy.clone())),
+            hosts: HashMap::from([("repo.internal".to_string(), ProxyTarget::Direct)]),
+        },
+        https: SchemeProxy {
+            default: None,
+            hosts: HashMap::from([(
+                "vendor.example.com".to_string(),
+                ProxyTarget::Url(https_proxy.clone()),
+            )]),
+        },
+        ..Default::default()
+    };
+
+    let url = |s| Url::parse(s).unwrap();
+
+    assert_eq!(
+        ap.proxy_for(&url("http://deb.debian.org/debian")),
+        Some(proxy.clone())
+    );
+    assert_eq!(ap.proxy_for(&url("http://repo.internal/debs")), None);
+    // https 回退到 http 的配置
+    assert_eq!(ap.proxy_for(&url("https://repo.internal/debs")), None);
+    assert_eq!(
+        ap.proxy_for(&url("https://deb.debian.org/debian")),
+        Some(proxy)
+    );
+    assert_eq!(
+        ap.proxy_for(&url("https://vendor.example.com/apt")),
+        Some(https_proxy)
+    );
+}
+
+#[test]
+fn test_env_proxy_fallback() {
+    let proxy = Url::parse("http://proxy.example.com:3128").unwrap();
+    let https_proxy = Url::parse("http://secure-proxy.example.com:3128").unwrap();
+
+    let ap = AptProxy {
+        http: SchemeProxy {
+            default: None,
+            hosts: HashMap::from([(
+                "vendor.example.com".to_string(),
+                ProxyTarget::Url(https_proxy.clone()),
+            )]),
+        },
+        env_http: Some(proxy.clone()),
+        env_no_proxy: parse_no_proxy(" localhost, .Internal ,mirror.example.org:8080,[::1],"),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        ap.env_no_proxy,
+        vec!["localhost", "internal", "mirror.example.org", "::1"]
+    );
+
+    let url = |s| Url::parse(s).unwrap();
+
+    assert_eq!(
+        ap.proxy_for(&url("https://deb.debian.org/debian")),
+        Some(proxy.clone())
+    );
+    assert_eq!(ap.proxy_for(&url("http://localhost:8000/debs")), None);
+    assert_eq!(ap.proxy_for(&url("http://repo.internal/debs")), None);
+    assert_eq!(ap.proxy_for(&url("http://INTERNAL/debs")), None);
+    assert_eq!(
+        ap.proxy_for(&url("http://notinternal/debs")),
+        Some(proxy.clone())
+    );
+    assert_eq!(ap.proxy_for(&url("http://mirror.example.org/debs")), None);
+    assert_eq!(ap.proxy_for(&url("http://[::1]/debs")), None);
+    // APT 的配置优先于 no_proxy
+    assert_eq!(
+        ap.proxy_for(&url("https://vendor.example.com/apt")),
+        Some(https_proxy)
+    );
+
+    let all = AptProxy {
+        env_http: Some(proxy),
+        env_no_proxy: parse_no_proxy("*"),
+        ..Default::default()
+    };
+    assert_eq!(all.proxy_for(&url("http://deb.debian.org/debian")), None);
+}
@@ -10,14 +10,18 @@ use reqwest::StatusCode;
 use tracing::{error, info};
 
 use crate::config::Config;
-use crate::pb::{NoProgressBar, OmaMultiProgressBar, RenderDownloadProgress};
+use crate::pb::{
+    json_progress, JsonProgress, NoProgressBar, OmaMultiProgressBar, RenderDownloadProgress,
+};
 use crate::utils::is_root;
-use crate::{due_to, fl, success, HTTP_CLIENT};
+use crate::{due_to, fl, http_client_builder, success};
 use crate::{error::OutputError, subcommand::utils::handle_no_result};
 
 use crate::args::CliExecuter;
 
 use super::utils::is_terminal;
+use super::utils::mirror_lists;
+use super::utils::tls_clients;
 
 #[derive(Debug, Args)]
 pub struct Download {
@@ -59,10 +63,17 @@ impl CliExecuter for Download {
             matcher.match_pkgs_and_versions(packages.iter().map(|x| x.as_str()))?;
         handle_no_result("/", no_result, no_progress)?;
 
+        let auth_config = AuthConfig::system("/")?;
+        let client = http_client_builder(&apt.config).build()?;
+        let tls_clients = tls_clients(&apt.config, &auth_config)?;
+        let mirror_lists = mirror_lists("/");
+
         let (tx, rx) = unbounded();
 
         thread::spawn(move || {
-            let mut pb: Box<dyn RenderDownloadProgress> = if no_progress || !is_terminal() {
+            let mut pb: Box<dyn RenderDownloadProgress> = if json_progress() {
+                Box::new(JsonProgress::default())
+            } else if no_progress || !is_terminal() {
                 Box::new(NoProgressBar::default())
             } else {
                 Box::new(OmaMultiProgressBar::default())
@@ -71,12 +82,14 @@ impl CliExecuter for Download {
         });
 
         let (success, failed) = apt.download(
-            &HTTP_CLIENT,
+            &client,
             pkgs,
             DownloadConfig {
-                network_thread: Some(config.network_thread()),
+                network: config.network_options(),
+                tls_clients: Some(&tls_clients),
+                mirror_lists: Some(&mirror_lists),
                 download_dir: Some(&path),
-                auth: &AuthConfig::system("/")?,
+                auth: &auth_config,
             },
             dry_run,
             |event| async {
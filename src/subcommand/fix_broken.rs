@@ -98,7 +98,7 @@ impl CliExecuter for FixBroken {
             .autoremove(autoremove)
             .remove_config(remove_config)
             .auth_config(&auth_config)
-            .network_thread(config.network_thread())
+            .network(config.network_options())
             .build()
             .run()
     }
@@ -242,7 +242,7 @@ impl CliExecuter for Undo {
             .yes(false)
             .remove_config(remove_config)
             .autoremove(autoremove)
-            .network_thread(config.network_thread())
+            .network(config.network_options())
             .auth_config(&auth_config)
             .build()
             .run()
@@ -18,6 +18,7 @@ use crate::utils::dbus_check;
 use crate::utils::root;
 use crate::HTTP_CLIENT;
 
+use super::utils::check_local_debs;
 use super::utils::handle_no_result;
 use super::utils::lock_oma;
 use super::utils::no_check_dbus_warn;
@@ -141,9 +142,9 @@ impl CliExecuter for Install {
                 .client(&HTTP_CLIENT)
                 .dry_run(dry_run)
                 .no_progress(no_progress)
-                .network_thread(config.network_thread())
                 .sysroot(&sysroot)
-                .config(&apt_config)
+                .config(config)
+                .apt_config(&apt_config)
                 .auth_config(&auth_config);
 
             #[cfg(feature = "aosc")]
@@ -167,6 +168,8 @@ impl CliExecuter for Install {
             .map(|x| x.to_owned())
             .collect::<Vec<_>>();
 
+        check_local_debs(config, &sysroot, &local_debs)?;
+
         let pkgs_unparse = packages.iter().map(|x| x.as_str()).collect::<Vec<_>>();
 
         let oma_apt_args = OmaAptArgs::builder()
@@ -221,7 +224,7 @@ impl CliExecuter for Install {
             .yes(yes)
             .remove_config(remove_config)
             .autoremove(autoremove)
-            .network_thread(config.network_thread())
+            .network(config.network_options())
             .auth_config(&auth_config)
             .fix_dpkg_status(!no_fix_dpkg_status)
             .build()
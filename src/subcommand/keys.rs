@@ -0,0 +1,520 @@
+use std::fs;
+use std::io::{stdin, stdout, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local};
+use clap::{Args, Subcommand};
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use oma_refresh::sourceslist::sources_lists;
+use oma_repo_verify::keyring::{
+    signed_by_keyrings, system_keyrings, CertInfo, CertStatus, Keyring,
+};
+use oma_utils::dpkg::dpkg_arch;
+use tabled::Tabled;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::error::OutputError;
+use crate::table::PagerPrinter;
+use crate::utils::root;
+use crate::{fl, success};
+
+use crate::args::CliExecuter;
+
+#[derive(Debug, Args)]
+pub struct Keys {
+    #[command(subcommand)]
+    subcmd: KeysSubCmd,
+    /// Set sysroot target directory
+    #[arg(from_global)]
+    sysroot: PathBuf,
+}
+
+#[derive(Debug, Subcommand)]
+enum KeysSubCmd {
+    /// List trusted repository signing keys
+    List,
+    /// Show details of a key
+    Show {
+        /// Fingerprint or key ID
+        key: String,
+    },
+    /// Import key(s) to /etc/apt/keyrings
+    Add {
+        /// Armored or binary key file, read from stdin if not set or `-`
+        file: Option<PathBuf>,
+        /// File name (without extension) in /etc/apt/keyrings
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Remove a key from system keyrings
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Fingerprint or key ID
+        key: String,
+        /// Remove the key without confirmation even if sources still use it
+        #[arg(long)]
+        force: bool,
+    },
+    /// Export a key to stdout
+    Export {
+        /// Fingerprint or key ID
+        key: String,
+        /// Export in binary format instead of ASCII-armored
+        #[arg(long)]
+        binary: bool,
+    },
+}
+
+/// A trusted key and the sources using it
+struct KeyEntry {
+    keyring: Keyring,
+    info: CertInfo,
+    /// In a keyring trusted by sources without Signed-By
+    system: bool,
+    used_by: Vec<String>,
+}
+
+#[derive(Debug, Tabled)]
+struct KeyDisplay {
+    fingerprint: String,
+    user_id: String,
+    expires: String,
+    status: String,
+    file: String,
+    used_by: usize,
+}
+
+impl CliExecuter for Keys {
+    fn execute(self, config: &Config, _no_progress: bool) -> Result<i32, OutputError> {
+        let Keys { subcmd, sysroot } = self;
+        let window = config.key_expiry_warning();
+
+        match subcmd {
+            KeysSubCmd::List => {
+                let keys = collect_keys(&sysroot)?;
+                list(&keys);
+                warn_expiry(keys.iter().map(|x| &x.info), window);
+            }
+            KeysSubCmd::Show { key } => {
+                let keys = find_keys(&sysroot, &key)?;
+                for k in &keys {
+                    show(k);
+                }
+                warn_expiry(keys.iter().map(|x| &x.info), window);
+            }
+            KeysSubCmd::Add { file, name } => {
+                root()?;
+                add(&sysroot, file, name, window)?;
+            }
+            KeysSubCmd::Remove { key, force } => {
+                root()?;
+                remove(&sysroot, &key, force)?;
+            }
+            KeysSubCmd::Export { key, binary } => {
+                let keys = find_keys(&sysroot, &key)?;
+
+                // 同一个密钥可能存在于多个文件中，只导出一次
+                let bytes = keys[0].keyring.select(&key).to_bytes(!binary)?;
+
+                stdout().write_all(&bytes).map_err(|e| OutputError {
+                    description: e.to_string(),
+                    source: None,
+                })?;
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+/// All trusted keys, including keyrings only used by Signed-By
+fn collect_keys(sysroot: &Path) -> Result<Vec<KeyEntry>, OutputError> {
+    let arch = dpkg_arch(sysroot)?;
+    let sources = sources_lists(sysroot, &arch)?;
+
+    let mut keyrings = vec![];
+
+    for path in system_keyrings(sysroot) {
+        match Keyring::load(&path) {
+            Ok(k) => keyrings.push((k, true)),
+            Err(e) => warn!("{e}"),
+        }
+    }
+
+    // source => keyrings, None 表示信任所有系统密钥
+    let mut source_keyrings = vec![];
+
+    for ose in &sources {
+        let name = format!("{} {}", ose.url(), ose.suite());
+        let file = ose.file().unwrap_or(Path::new(""));
+
+        match signed_by_keyrings(sysroot, ose.signed_by(), file) {
+            None => source_keyrings.push((name, None)),
+            Some(Ok(k)) => {
+                for keyring in &k {
+                    if !keyrings.iter().any(|x| x.0.path() == keyring.path()) {
+                        keyrings.push((keyring.clone(), false));
+                    }
+                }
+                source_keyrings.push((name, Some(k)));
+            }
+            Some(Err(e)) => warn!("{name}: {e}"),
+        }
+    }
+
+    let mut res = vec![];
+
+    for (keyring, system) in keyrings {
+        for info in keyring.infos() {
+            let used_by = source_keyrings
+                .iter()
+                .filter(|(_, k)| match k {
+                    None => system,
+                    Some(k) => k.iter().any(|x| x.contains(&info.fingerprint)),
+                })
+                .map(|x| x.0.clone())
+                .collect();
+
+            res.push(KeyEntry {
+                keyring: keyring.clone(),
+                info,
+                system,
+                used_by,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+fn find_keys(sysroot: &Path, key: &str) -> Result<Vec<KeyEntry>, OutputError> {
+    let keys = collect_keys(sysroot)?
+        .into_iter()
+        .filter(|x| x.keyring.select(key).contains(&x.info.fingerprint))
+        .collect::<Vec<_>>();
+
+    if keys.is_empty() {
+        return Err(OutputError {
+            description: fl!("keys-not-found", key = key),
+            source: None,
+        });
+    }
+
+    Ok(keys)
+}
+
+fn list(keys: &[KeyEntry]) {
+    let res = keys
+        .iter()
+        .map(|k| KeyDisplay {
+            fingerprint: k.info.fingerprint.clone(),
+            user_id: k.info.user_ids.first().cloned().unwrap_or_default(),
+            expires: format_expires(&k.info),
+            status: status(&k.info.status),
+            file: k.keyring.path().display().to_string(),
+            used_by: k.used_by.len(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut printer = PagerPrinter::new(stdout());
+    printer
+        .print_table(
+            res,
+            vec![
+                &fl!("keys-fingerprint"),
+                &fl!("keys-user-id"),
+                &fl!("keys-expires"),
+                &fl!("keys-status"),
+                &fl!("keys-file"),
+                &fl!("keys-used-by"),
+            ],
+        )
+        .ok();
+}
+
+fn show(k: &KeyEntry) {
+    println!("{}: {}", fl!("keys-fingerprint"), k.info.fingerprint);
+    for uid in &k.info.user_ids {
+        println!("{}: {uid}", fl!("keys-user-id"));
+    }
+    println!("{}: {}", fl!("keys-created"), format_time(k.info.created));
+    println!("{}: {}", fl!("keys-expires"), format_expires(&k.info));
+    match &k.info.status {
+        CertStatus::Invalid(reason) => {
+            println!(
+                "{}: {} ({reason})",
+                fl!("keys-status"),
+                status(&k.info.status)
+            )
+        }
+        s => println!("{}: {}", fl!("keys-status"), status(s)),
+    }
+    println!("{}: {}", fl!("keys-file"), k.keyring.path().display());
+    println!(
+        "{}: {}",
+        fl!("keys-trusted-by-default"),
+        if k.system {
+            fl!("keys-yes")
+        } else {
+            fl!("keys-no")
+        }
+    );
+    println!("{}:", fl!("keys-used-by"));
+    for source in &k.used_by {
+        println!("  {source}");
+    }
+    println!();
+}
+
+fn add(
+    sysroot: &Path,
+    file: Option<PathBuf>,
+    name: Option<String>,
+    window: Option<Duration>,
+) -> Result<(), OutputError> {
+    let mut bytes = vec![];
+
+    let res = match &file {
+        Some(path) if path != Path::new("-") => {
+            fs::File::open(path).and_then(|mut f| f.read_to_end(&mut bytes))
+        }
+        _ => stdin().read_to_end(&mut bytes),
+    };
+
+    let input = file
+        .as_ref()
+        .map(|x| x.display().to_string())
+        .unwrap_or_else(|| "-".to_string());
+
+    res.map_err(|e| OutputError {
+        description: fl!("failed-to-operate-path", p = input.clone()),
+        source: Some(Box::new(e)),
+    })?;
+
+    let dir = sysroot.join("etc/apt/keyrings");
+    let mut keyring = Keyring::from_bytes(&input, &bytes)?;
+
+    let infos = keyring.infos();
+
+    let Some(first) = infos.first() else {
+        return Err(OutputError {
+            description: fl!("keys-no-key-in-file", path = input),
+            source: None,
+        });
+    };
+
+    if let Some(name) = &name {
+        check_name(name)?;
+    }
+
+    let name = name
+        .or_else(|| {
+            file.as_ref()
+                .filter(|x| *x != Path::new("-"))
+                .and_then(|x| x.file_stem())
+                .map(|x| x.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| first.fingerprint.clone());
+
+    let path = dir.join(format!("{name}.asc"));
+
+    if path.exists() {
+        return Err(OutputError {
+            description: fl!("keys-file-exists", path = path.display().to_string()),
+            source: None,
+        });
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| OutputError {
+        description: fl!("failed-to-operate-path", p = dir.display().to_string()),
+        source: Some(Box::new(e)),
+    })?;
+
+    keyring.set_path(&path);
+    keyring.save()?;
+
+    for info in &infos {
+        success!(
+            "{}",
+            fl!(
+                "keys-added",
+                fingerprint = info.fingerprint.clone(),
+                path = path.display().to_string()
+            )
+        );
+    }
+
+    warn_expiry(infos.iter(), window);
+
+    // 提示用户使用系统中的路径
+    let signed_by = Path::new("/").join(path.strip_prefix(sysroot).unwrap_or(&path));
+
+    info!(
+        "{}",
+        fl!("keys-added-tips", path = signed_by.display().to_string())
+    );
+
+    Ok(())
+}
+
+/// File name given by `--name` must stay in /etc/apt/keyrings
+fn check_name(name: &str) -> Result<(), OutputError> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\0']) {
+        return Err(OutputError {
+            description: fl!("keys-invalid-name", name = name),
+            source: None,
+        });
+    }
+
+    Ok(())
+}
+
+fn remove(sysroot: &Path, key: &str, force: bool) -> Result<(), OutputError> {
+    let keys = find_keys(sysroot, key)?;
+    let mut removed = false;
+    let mut skipped = false;
+
+    for k in keys {
+        if !k.system {
+            warn!(
+                "{}",
+                fl!(
+                    "keys-not-system",
+                    path = k.keyring.path().display().to_string()
+                )
+            );
+            continue;
+        }
+
+        if !k.used_by.is_empty() {
+            warn!(
+                "{}",
+                fl!(
+                    "keys-still-used",
+                    fingerprint = k.info.fingerprint.clone(),
+                    sources = k.used_by.join(", ")
+                )
+            );
+
+            if !force && !confirm_remove() {
+                info!(
+                    "{}",
+                    fl!(
+                        "keys-still-used-skipped",
+                        fingerprint = k.info.fingerprint.clone()
+                    )
+                );
+                skipped = true;
+                continue;
+            }
+        }
+
+        let mut keyring = Keyring::load(k.keyring.path())?;
+        keyring.remove(&k.info.fingerprint);
+
+        let path = k.keyring.path();
+
+        if keyring.is_empty() {
+            fs::remove_file(path).map_err(|e| OutputError {
+                description: fl!("failed-to-operate-path", p = path.display().to_string()),
+                source: Some(Box::new(e)),
+            })?;
+        } else {
+            keyring.save()?;
+        }
+
+        success!(
+            "{}",
+            fl!(
+                "keys-removed",
+                fingerprint = k.info.fingerprint.clone(),
+                path = path.display().to_string()
+            )
+        );
+
+        removed = true;
+    }
+
+    if !removed && !skipped {
+        return Err(OutputError {
+            description: fl!("keys-not-found", key = key),
+            source: None,
+        });
+    }
+
+    Ok(())
+}
+
+/// Ask before removing a key still in use, never remove it without a terminal
+fn confirm_remove() -> bool {
+    stdin().is_terminal()
+        && Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(fl!("continue"))
+            .default(false)
+            .interact()
+            .unwrap_or(false)
+}
+
+/// `window`: also warn about keys expiring within it
+fn warn_expiry<'a>(infos: impl Iterator<Item = &'a CertInfo>, window: Option<Duration>) {
+    let soon = window.map(|x| SystemTime::now() + x);
+    let mut warned = vec![];
+
+    for info in infos {
+        if warned.contains(&info.fingerprint) {
+            continue;
+        }
+
+        if info.status == CertStatus::Expired {
+            warn!(
+                "{}",
+                fl!("keys-expired", fingerprint = info.fingerprint.clone())
+            );
+        } else if soon.is_some_and(|x| info.expires_before(x)) {
+            warn!(
+                "{}",
+                fl!(
+                    "keys-expiring",
+                    fingerprint = info.fingerprint.clone(),
+                    date = format_expires(info)
+                )
+            );
+        } else {
+            continue;
+        }
+
+        warned.push(info.fingerprint.clone());
+    }
+}
+
+fn status(status: &CertStatus) -> String {
+    match status {
+        CertStatus::Valid => fl!("keys-status-valid"),
+        CertStatus::Expired => fl!("keys-status-expired"),
+        CertStatus::Revoked => fl!("keys-status-revoked"),
+        CertStatus::Invalid(_) => fl!("keys-status-invalid"),
+    }
+}
+
+fn format_expires(info: &CertInfo) -> String {
+    info.expires
+        .map(format_time)
+        .unwrap_or_else(|| fl!("keys-never"))
+}
+
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).format("%Y-%m-%d").to_string()
+}
+
+#[test]
+fn test_check_name() {
+    assert!(check_name("vendor").is_ok());
+    assert!(check_name("vendor-archive-keyring.v2").is_ok());
+    assert!(check_name("").is_err());
+    assert!(check_name("..").is_err());
+    assert!(check_name(".hidden").is_err());
+    assert!(check_name("../../etc/x").is_err());
+    assert!(check_name("sub/dir").is_err());
+}
@@ -36,6 +36,7 @@ use crate::config::Config;
 use crate::error::OutputError;
 use crate::fl;
 use crate::pb::OmaProgressBar;
+use crate::proxy::AptProxy;
 use crate::success;
 use crate::table::PagerPrinter;
 use crate::utils::root;
@@ -168,7 +169,7 @@ impl CliExecuter for CliMirror {
                 } => operate(
                     no_progress,
                     !no_refresh_topics && !config.no_refresh_topics(),
-                    config.network_thread(),
+                    config,
                     no_refresh,
                     names.iter().map(|x| x.as_str()).collect::<Vec<_>>(),
                     sysroot,
@@ -183,7 +184,7 @@ impl CliExecuter for CliMirror {
                     no_progress,
                     set_fastest,
                     !no_refresh_topics && !config.no_refresh_topics(),
-                    config.network_thread(),
+                    config,
                     no_refresh,
                 ),
                 MirrorSubCmd::Add {
@@ -194,7 +195,7 @@ impl CliExecuter for CliMirror {
                 } => operate(
                     no_progress,
                     !no_refresh_topics && !config.no_refresh_topics(),
-                    config.network_thread(),
+                    config,
                     no_refresh,
                     names.iter().map(|x| x.as_str()).collect::<Vec<_>>(),
                     sysroot,
@@ -208,7 +209,7 @@ impl CliExecuter for CliMirror {
                 } => operate(
                     no_progress,
                     !no_refresh_topics && !config.no_refresh_topics(),
-                    config.network_thread(),
+                    config,
                     no_refresh,
                     names.iter().map(|x| x.as_str()).collect::<Vec<_>>(),
                     sysroot,
@@ -220,7 +221,7 @@ impl CliExecuter for CliMirror {
                 } => set_order(
                     no_progress,
                     !no_refresh_topics && !config.no_refresh_topics(),
-                    config.network_thread(),
+                    config,
                     no_refresh,
                 ),
             }
@@ -228,7 +229,7 @@ impl CliExecuter for CliMirror {
             tui(
                 no_progress,
                 !no_refresh_topics && !config.no_refresh_topics(),
-                config.network_thread(),
+                config,
                 no_refresh,
             )
         }
@@ -238,7 +239,7 @@ impl CliExecuter for CliMirror {
 pub fn tui(
     no_progress: bool,
     refresh_topic: bool,
-    network_threads: usize,
+    config: &Config,
     no_refresh: bool,
 ) -> Result<i32, OutputError> {
     root()?;
@@ -287,7 +288,7 @@ pub fn tui(
     mm.write_status(Some(&fl!("do-not-edit-topic-sources-list")))?;
 
     if !no_refresh {
-        refresh(no_progress, network_threads, refresh_topic)?;
+        refresh(no_progress, config, refresh_topic)?;
     }
 
     Ok(0)
@@ -302,7 +303,7 @@ pub enum Operate {
 pub fn operate(
     no_progress: bool,
     refresh_topic: bool,
-    network_threads: usize,
+    config: &Config,
     no_refresh: bool,
     args: Vec<&str>,
     sysroot: PathBuf,
@@ -331,7 +332,7 @@ pub fn operate(
     mm.write_status(Some(&fl!("do-not-edit-topic-sources-list")))?;
 
     if !no_refresh {
-        refresh(no_progress, network_threads, refresh_topic)?;
+        refresh(no_progress, config, refresh_topic)?;
     }
 
     Ok(0)
@@ -340,7 +341,7 @@ pub fn operate(
 pub fn set_order(
     no_progress: bool,
     refresh_topic: bool,
-    network_threads: usize,
+    config: &Config,
     no_refresh: bool,
 ) -> Result<i32, OutputError> {
     root()?;
@@ -366,7 +367,7 @@ pub fn set_order(
     mm.write_status(Some(&fl!("do-not-edit-topic-sources-list")))?;
 
     if !no_refresh {
-        refresh(no_progress, network_threads, refresh_topic)?;
+        refresh(no_progress, config, refresh_topic)?;
     }
 
     Ok(0)
@@ -382,7 +383,7 @@ pub fn speedtest(
     no_progress: bool,
     set_fastest: bool,
     refresh_topic: bool,
-    network_threads: usize,
+    config: &Config,
     no_refresh: bool,
 ) -> Result<i32, OutputError> {
     if set_fastest {
@@ -407,10 +408,15 @@ pub fn speedtest(
         None
     };
 
-    let client = blocking::ClientBuilder::new()
+    let builder = blocking::ClientBuilder::new()
         .user_agent(APP_USER_AGENT)
-        .timeout(Duration::from_secs(120))
-        .build()?;
+        .timeout(Duration::from_secs(120));
+
+    let client = match AptProxy::new(&AptConfig::new()).reqwest_proxy() {
+        Some(proxy) => builder.proxy(proxy),
+        None => builder,
+    }
+    .build()?;
 
     let mut score_map = HashMap::new();
 
@@ -503,27 +509,23 @@ pub fn speedtest(
         mm.write_status(Some(&fl!("do-not-edit-topic-sources-list")))?;
 
         if !no_refresh {
-            refresh(no_progress, network_threads, refresh_topic)?;
+            refresh(no_progress, config, refresh_topic)?;
         }
     }
 
     Ok(0)
 }
 
-fn refresh(
-    no_progress: bool,
-    network_threads: usize,
-    refresh_topic: bool,
-) -> Result<(), OutputError> {
+fn refresh(no_progress: bool, config: &Config, refresh_topic: bool) -> Result<(), OutputError> {
     let auth_config = AuthConfig::system("/")?;
 
     Refresh::builder()
         .client(&HTTP_CLIENT)
         .dry_run(false)
         .no_progress(no_progress)
-        .network_thread(network_threads)
         .refresh_topics(refresh_topic)
-        .config(&AptConfig::new())
+        .config(config)
+        .apt_config(&AptConfig::new())
         .auth_config(&auth_config)
         .build()
         .run()?;
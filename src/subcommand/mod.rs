@@ -7,6 +7,7 @@ pub mod fix_broken;
 pub mod generate;
 pub mod history;
 pub mod install;
+pub mod keys;
 pub mod list;
 pub mod mark;
 #[cfg(feature = "aosc")]
@@ -16,8 +17,10 @@ pub mod pkgnames;
 pub mod rdepends;
 pub mod refresh;
 pub mod remove;
+pub mod repo;
 pub mod search;
 pub mod show;
+pub mod sources;
 #[cfg(feature = "aosc")]
 pub mod topics;
 pub mod upgrade;
@@ -110,9 +110,9 @@ impl CliExecuter for Pick {
                 .client(&HTTP_CLIENT)
                 .dry_run(dry_run)
                 .no_progress(no_progress)
-                .network_thread(config.network_thread())
                 .sysroot(&sysroot)
-                .config(&apt_config)
+                .config(config)
+                .apt_config(&apt_config)
                 .auth_config(&auth_config);
 
             #[cfg(feature = "aosc")]
@@ -215,7 +215,7 @@ impl CliExecuter for Pick {
             .yes(false)
             .remove_config(remove_config)
             .autoremove(autoremove)
-            .network_thread(config.network_thread())
+            .network(config.network_options())
             .auth_config(&auth_config)
             .build()
             .run()
@@ -1,15 +1,21 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use apt_auth_config::AuthConfig;
-use clap::Args;
+use clap::{Args, ValueEnum};
+use oma_console::console::style;
 use oma_console::indicatif::ProgressBar;
 use oma_console::pb::spinner_style;
+use oma_console::writer::Writeln;
 use oma_pm::apt::{AptConfig, OmaApt, OmaAptArgs};
-use tracing::info;
+use oma_refresh::report::SourceReport;
+use oma_refresh::snapshot::Snapshot;
+use oma_refresh::verify::{ListProblem, VerifyLists, VerifyResult};
+use serde_json::json;
+use tracing::{info, warn};
 
 use crate::config::Config;
 use crate::{error::OutputError, utils::root};
-use crate::{fl, success, HTTP_CLIENT};
+use crate::{fl, msg, success, HTTP_CLIENT, RT, WRITER};
 
 use super::utils::Refresh as RefreshInner;
 use crate::args::CliExecuter;
@@ -20,6 +26,27 @@ pub struct Refresh {
     /// Do not refresh topics manifest.json file
     #[arg(long)]
     no_refresh_topics: bool,
+    /// Accept repository metadata older than the last accepted one (intentional downgrade)
+    #[arg(long)]
+    allow_release_rollback: bool,
+    /// Only refresh source(s) matching the URI, sources.list file or suite
+    #[arg(long = "source")]
+    sources: Vec<String>,
+    /// Show what changed in the repositories, in this format
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "text")]
+    report: Option<ReportFormat>,
+    /// Resolve against the archive as it was at this time (e.g. 2026-09-01T00:00Z), later `oma refresh` keeps using it
+    #[arg(long, value_name = "TIME")]
+    snapshot: Option<String>,
+    /// Stop using the recorded archive snapshot
+    #[arg(long, conflicts_with = "snapshot")]
+    no_snapshot: bool,
+    /// Re-verify local repository metadata against their Release files without network access
+    #[arg(long)]
+    verify_only: bool,
+    /// Remove metadata files which fail verification
+    #[arg(long, requires = "verify_only")]
+    remove_broken: bool,
     /// Set sysroot target directory
     #[arg(from_global)]
     sysroot: PathBuf,
@@ -33,6 +60,13 @@ impl CliExecuter for Refresh {
         let Refresh {
             #[cfg(feature = "aosc")]
             no_refresh_topics,
+            allow_release_rollback,
+            sources,
+            report,
+            snapshot,
+            no_snapshot,
+            verify_only,
+            remove_broken,
             sysroot,
             dry_run,
         } = self;
@@ -44,6 +78,22 @@ impl CliExecuter for Refresh {
 
         root()?;
 
+        if verify_only {
+            return verify_lists(config, &sysroot, remove_broken);
+        }
+
+        let snapshot = snapshot.map(|x| x.parse::<Snapshot>()).transpose()?;
+
+        // 之前使用过快照时继续使用同一快照
+        let (snapshot, snapshot_url) = match snapshot {
+            Some(snapshot) => (Some(snapshot), config.snapshot_url()),
+            None if no_snapshot => (None, config.snapshot_url()),
+            None => match RT.block_on(Snapshot::load(&sysroot)) {
+                Some((snapshot, url)) => (Some(snapshot), url),
+                None => (None, config.snapshot_url()),
+            },
+        };
+
         let apt_config = AptConfig::new();
         let auth_config = AuthConfig::system(&sysroot)?;
 
@@ -52,10 +102,15 @@ impl CliExecuter for Refresh {
             .client(&HTTP_CLIENT)
             .dry_run(false)
             .no_progress(no_progress)
-            .network_thread(config.network_thread())
             .sysroot(&sysroot_str)
-            .config(&apt_config)
-            .auth_config(&auth_config);
+            .config(config)
+            .apt_config(&apt_config)
+            .auth_config(&auth_config)
+            .allow_release_rollback(allow_release_rollback)
+            .sources(sources)
+            .report(report.is_some())
+            .maybe_snapshot(snapshot)
+            .snapshot_url(snapshot_url);
 
         #[cfg(feature = "aosc")]
         let refresh = builder
@@ -65,7 +120,12 @@ impl CliExecuter for Refresh {
         #[cfg(not(feature = "aosc"))]
         let refresh = builder.build();
 
-        refresh.run()?;
+        let changes = refresh.run()?.unwrap_or_default();
+
+        // 回到实时仓库后才清除记录的快照
+        if no_snapshot {
+            RT.block_on(Snapshot::clear(&sysroot))?;
+        }
 
         let oma_apt_args = OmaAptArgs::builder()
             .sysroot(sysroot.to_string_lossy().to_string())
@@ -107,6 +167,101 @@ impl CliExecuter for Refresh {
             success!("{}", fl!("successfully-refresh-with-tips", s = s));
         }
 
+        match report {
+            Some(ReportFormat::Text) => print_report(&changes),
+            Some(ReportFormat::Json) => println!("{}", json!({ "sources": changes })),
+            None => {}
+        }
+
         Ok(0)
     }
 }
+
+fn verify_lists(config: &Config, sysroot: &Path, remove_broken: bool) -> Result<i32, OutputError> {
+    info!("{}", fl!("refresh-verifying"));
+
+    let apt_config = AptConfig::new();
+
+    let verify = VerifyLists::builder()
+        .source(sysroot.to_path_buf())
+        .download_dir(sysroot.join("var/lib/apt/lists"))
+        .apt_config(&apt_config)
+        .delete(remove_broken)
+        .signature_policy(config.signature_policy())
+        .appstream(config.appstream())
+        .build();
+
+    let VerifyResult { checked, issues } = RT.block_on(verify.run())?;
+
+    if issues.is_empty() {
+        success!("{}", fl!("refresh-verify-ok", count = checked));
+        return Ok(0);
+    }
+
+    for i in &issues {
+        let path = i.path.display().to_string();
+
+        match &i.problem {
+            ListProblem::BadSignature(reason) => warn!(
+                "{}",
+                fl!(
+                    "refresh-verify-bad-signature",
+                    path = path,
+                    reason = reason.as_str()
+                )
+            ),
+            ListProblem::ChecksumMismatch => {
+                warn!("{}", fl!("refresh-verify-mismatch", path = path))
+            }
+            ListProblem::Missing => warn!("{}", fl!("refresh-verify-missing", path = path)),
+        }
+    }
+
+    let removed = issues.iter().filter(|x| x.deleted).count();
+
+    if remove_broken {
+        info!("{}", fl!("refresh-verify-removed", count = removed));
+        Ok(0)
+    } else {
+        info!("{}", fl!("refresh-verify-failed", count = issues.len()));
+        Ok(1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+fn print_report(changes: &[SourceReport]) {
+    if changes.is_empty() {
+        return;
+    }
+
+    info!("{}", fl!("refresh-report-title"));
+
+    for source in changes {
+        let s = fl!(
+            "refresh-report-source",
+            source = source.source.as_str(),
+            added = source.added.len(),
+            removed = source.removed.len(),
+            updated = source.new_versions.len()
+        );
+
+        if !source.security {
+            msg!("{s}");
+            continue;
+        }
+
+        // 安全更新源的新版本逐个列出
+        WRITER
+            .writeln(&style("SECURITY").red().bold().to_string(), &s)
+            .ok();
+
+        for i in &source.new_versions {
+            msg!("  {} {} ({})", style(&i.name).bold(), i.version, i.arch);
+        }
+    }
+}
@@ -256,7 +256,7 @@ impl CliExecuter for Remove {
             .yes(yes)
             .remove_config(remove_config)
             .autoremove(!no_autoremove)
-            .network_thread(config.network_thread())
+            .network(config.network_options())
             .auth_config(&auth)
             .fix_dpkg_status(!no_fix_dpkg_status)
             .build()
@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand, ValueEnum};
+use oma_refresh::repo::{LocalRepo, RepoLayout};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::error::OutputError;
+use crate::{fl, success};
+
+use crate::args::CliExecuter;
+
+#[derive(Debug, Args)]
+pub struct Repo {
+    #[command(subcommand)]
+    subcmd: RepoSubCmd,
+}
+
+#[derive(Debug, Subcommand)]
+enum RepoSubCmd {
+    /// Generate repository metadata from a directory of .deb packages
+    Build {
+        /// Directory of .deb packages, metadata will be written to it
+        dir: PathBuf,
+        /// Repository layout
+        #[arg(long, value_enum, default_value_t = Layout::Flat)]
+        layout: Layout,
+        /// Suite name of the repository
+        #[arg(long, default_value = "local")]
+        suite: String,
+        /// Component name of pool layout repository
+        #[arg(long, default_value = "main")]
+        component: String,
+        /// Origin and Label of the repository
+        #[arg(long, default_value = "local")]
+        origin: String,
+        /// OpenPGP secret key file to sign the Release file with
+        #[arg(long)]
+        sign_key: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Layout {
+    /// All metadata in the root directory (deb file:/path ./)
+    Flat,
+    /// Metadata in dists/<suite>/<component> (deb file:/path <suite> <component>)
+    Pool,
+}
+
+impl CliExecuter for Repo {
+    fn execute(self, _config: &Config, _no_progress: bool) -> Result<i32, OutputError> {
+        let RepoSubCmd::Build {
+            dir,
+            layout,
+            suite,
+            component,
+            origin,
+            sign_key,
+        } = self.subcmd;
+
+        let dir = fs::canonicalize(&dir).map_err(|e| OutputError {
+            description: fl!("failed-to-operate-path", p = dir.display().to_string()),
+            source: Some(Box::new(e)),
+        })?;
+
+        let res = LocalRepo::builder()
+            .dir(&dir)
+            .layout(match layout {
+                Layout::Flat => RepoLayout::Flat,
+                Layout::Pool => RepoLayout::Pool,
+            })
+            .suite(&suite)
+            .component(&component)
+            .origin(origin)
+            .maybe_sign_key(sign_key)
+            .build()
+            .build()?;
+
+        if res.packages == 0 {
+            warn!(
+                "{}",
+                fl!("repo-no-package", path = dir.display().to_string())
+            );
+        }
+
+        success!(
+            "{}",
+            fl!(
+                "repo-build-done",
+                count = res.packages,
+                archs = res.archs.join(", "),
+                path = res.release_dir.display().to_string()
+            )
+        );
+
+        let dist = match layout {
+            Layout::Flat => "./".to_string(),
+            Layout::Pool => format!("{suite} {component}"),
+        };
+
+        if res.signed {
+            info!(
+                "{}",
+                fl!(
+                    "repo-build-signed",
+                    line = format!("deb file:{} {dist}", dir.display())
+                )
+            );
+        } else {
+            info!(
+                "{}",
+                fl!(
+                    "repo-build-unsigned",
+                    line = format!("deb [trusted=yes] file:{} {dist}", dir.display())
+                )
+            );
+        }
+
+        Ok(0)
+    }
+}
@@ -3,9 +3,10 @@ use std::path::PathBuf;
 use clap::Args;
 use oma_console::{indicatif::ProgressBar, pager::Pager, pb::spinner_style};
 use oma_pm::{
+    appstream::AppStreamIndex,
     apt::{AptConfig, OmaApt, OmaAptArgs},
     matches::SearchEngine,
-    search::{IndiciumSearch, OmaSearch, SearchResult, StrSimSearch, TextSearch},
+    search::{AppStreamSearch, IndiciumSearch, OmaSearch, SearchResult, StrSimSearch, TextSearch},
 };
 use tracing::warn;
 
@@ -25,6 +26,9 @@ pub struct Search {
     /// Set output format as JSON
     #[arg(long)]
     json: bool,
+    /// Search applications in AppStream metadata (e.g. "image editor")
+    #[arg(long)]
+    app: bool,
     /// Set sysroot target directory
     #[arg(from_global)]
     sysroot: PathBuf,
@@ -39,6 +43,7 @@ impl CliExecuter for Search {
             pattern,
             no_pager,
             json,
+            app,
             sysroot,
             apt_options,
         } = self;
@@ -64,19 +69,29 @@ impl CliExecuter for Search {
             None
         };
 
-        let res = search(
-            &apt,
-            &pattern,
-            match config.search_engine().as_str() {
-                "indicium" => SearchEngine::Indicium(Box::new(|_| {})),
-                "strsim" => SearchEngine::Strsim,
-                "text" => SearchEngine::Text,
-                x => {
-                    warn!("Unsupported mode: {x}, fallback to indicium ...");
-                    SearchEngine::Indicium(Box::new(|_| {}))
-                }
-            },
-        )?;
+        let res = if app {
+            let index = AppStreamIndex::load(sysroot.join("var/lib/apt/lists"));
+
+            if index.is_empty() {
+                warn!("{}", fl!("no-appstream-metadata"));
+            }
+
+            AppStreamSearch::new(&apt.cache, &index).search(&pattern)?
+        } else {
+            search(
+                &apt,
+                &pattern,
+                match config.search_engine().as_str() {
+                    "indicium" => SearchEngine::Indicium(Box::new(|_| {})),
+                    "strsim" => SearchEngine::Strsim,
+                    "text" => SearchEngine::Text,
+                    x => {
+                        warn!("Unsupported mode: {x}, fallback to indicium ...");
+                        SearchEngine::Indicium(Box::new(|_| {}))
+                    }
+                },
+            )?
+        };
 
         if let Some(pb) = pb {
             pb.finish_and_clear();
@@ -2,6 +2,7 @@ use std::{io::stdout, path::PathBuf};
 
 use clap::Args;
 use oma_pm::{
+    appstream::AppStreamIndex,
     apt::{AptConfig, OmaApt, OmaAptArgs},
     matches::{GetArchMethod, PackagesMatcher},
     pkginfo::OmaPackage,
@@ -64,6 +65,36 @@ impl CliExecuter for Show {
 
         let mut stdout = stdout();
 
+        let names = pkgs
+            .iter()
+            .map(|x| x.raw_pkg.name().to_string())
+            .collect::<Vec<_>>();
+        let index_files = pkgs
+            .iter()
+            .flat_map(|x| {
+                x.version(&apt.cache)
+                    .package_files()
+                    .filter_map(|x| x.filename().map(|x| x.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let apps = AppStreamIndex::load_packages(
+            sysroot.join("var/lib/apt/lists"),
+            &names.iter().map(|x| x.as_str()).collect::<Vec<_>>(),
+            &index_files.iter().map(|x| x.as_str()).collect::<Vec<_>>(),
+        );
+
+        let pkg_info = |pkg: &OmaPackage| -> Result<_, OutputError> {
+            let mut info = pkg.pkg_info(&apt.cache)?;
+            info.apps = apps
+                .apps_by_package(pkg.raw_pkg.name())
+                .into_iter()
+                .cloned()
+                .collect();
+
+            Ok(info)
+        };
+
         if !all {
             let mut filter_pkgs: Vec<OmaPackage> = vec![];
             let pkgs_len = pkgs.len();
@@ -85,7 +116,7 @@ impl CliExecuter for Show {
                     writeln!(
                         stdout,
                         "{}",
-                        serde_json::to_string(&pkg.pkg_info(&apt.cache)?).map_err(|e| {
+                        serde_json::to_string(&pkg_info(pkg)?).map_err(|e| {
                             OutputError {
                                 description: e.to_string(),
                                 source: None,
@@ -94,7 +125,7 @@ impl CliExecuter for Show {
                     )
                     .ok();
                 } else {
-                    writeln!(stdout, "{}", pkg.pkg_info(&apt.cache)?).ok();
+                    writeln!(stdout, "{}", pkg_info(pkg)?).ok();
                     if i != filter_pkgs.len() - 1 {
                         writeln!(stdout).ok();
                     }
@@ -114,7 +145,7 @@ impl CliExecuter for Show {
                     writeln!(
                         stdout,
                         "{}",
-                        serde_json::to_string(&pkg.pkg_info(&apt.cache)?).map_err(|e| {
+                        serde_json::to_string(&pkg_info(pkg)?).map_err(|e| {
                             OutputError {
                                 description: e.to_string(),
                                 source: None,
@@ -123,10 +154,10 @@ impl CliExecuter for Show {
                     )
                     .ok();
                 } else if i != pkgs.len() - 1 {
-                    writeln!(stdout, "{}", pkg.pkg_info(&apt.cache)?).ok();
+                    writeln!(stdout, "{}", pkg_info(pkg)?).ok();
                     writeln!(stdout).ok();
                 } else {
-                    writeln!(stdout, "{}", pkg.pkg_info(&apt.cache)?).ok();
+                    writeln!(stdout, "{}", pkg_info(pkg)?).ok();
                 }
             }
         }
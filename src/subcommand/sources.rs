@@ -0,0 +1,889 @@
+use std::fs;
+use std::io::stdout;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use apt_auth_config::AuthConfig;
+use clap::{Args, Subcommand};
+use oma_apt_sources_lists::{
+    source_deb822::SourceListDeb822, Signature, SourceEntry, SourcesLists,
+};
+use oma_pm::apt::AptConfig;
+use oma_refresh::{
+    db::{detect_duplicate_repositories, RefreshError},
+    sourceslist::{deb822_entries, one_line_entry, OmaSourceEntry},
+};
+use tabled::Tabled;
+use tracing::info;
+
+use crate::config::Config;
+use crate::error::OutputError;
+use crate::table::PagerPrinter;
+use crate::utils::root;
+use crate::{fl, success, HTTP_CLIENT};
+
+use super::utils::Refresh;
+use crate::args::CliExecuter;
+
+/// apt 转换 sources.list 时使用的文件名
+const MOVED_FROM_MAIN: &str = "etc/apt/sources.list.d/moved-from-main.sources";
+
+#[derive(Debug, Args)]
+pub struct Sources {
+    #[command(subcommand)]
+    subcmd: SourcesSubCmd,
+    /// Do not refresh repository metadata after changing sources
+    #[arg(long, global = true)]
+    no_refresh: bool,
+    /// Set sysroot target directory
+    #[arg(from_global)]
+    sysroot: PathBuf,
+    /// Run oma in “dry-run” mode. Useful for testing changes and operations without making changes to the system
+    #[arg(from_global)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum SourcesSubCmd {
+    /// List configured sources
+    List,
+    /// Add a source
+    Add {
+        /// URI of the repository
+        uri: String,
+        /// Suite of the repository (e.g. `stable`, or `./` for flat repository)
+        suite: String,
+        /// Component(s) to enable
+        components: Vec<String>,
+        /// Add a source package (`deb-src`) repository
+        #[arg(long)]
+        source: bool,
+        /// Keyring(s) used to verify the repository (Signed-By)
+        #[arg(long)]
+        signed_by: Vec<PathBuf>,
+        /// Only use this repository for the architecture(s)
+        #[arg(long = "arch", value_delimiter = ',')]
+        archs: Vec<String>,
+        /// File name (without extension) in sources.list.d
+        #[arg(long)]
+        name: Option<String>,
+        /// Write one-line style (.list) source instead of deb822 style (.sources)
+        #[arg(long)]
+        one_line: bool,
+    },
+    /// Remove source(s)
+    #[command(visible_alias = "rm")]
+    Remove(Selector),
+    /// Enable source(s)
+    Enable(Selector),
+    /// Disable source(s)
+    Disable(Selector),
+    /// Set Signed-By or architectures of source(s)
+    Set {
+        #[command(flatten)]
+        selector: Selector,
+        /// Keyring(s) used to verify the repository (Signed-By)
+        #[arg(long)]
+        signed_by: Vec<PathBuf>,
+        /// Only use this repository for the architecture(s)
+        #[arg(long = "arch", value_delimiter = ',')]
+        archs: Vec<String>,
+    },
+    /// Convert one-line style sources to deb822 style
+    Modernize,
+}
+
+#[derive(Debug, Args)]
+struct Selector {
+    /// URI of the source(s)
+    uri: String,
+    /// Only match source(s) with this suite
+    #[arg(long)]
+    suite: Option<String>,
+}
+
+impl Selector {
+    fn matches(&self, entry: &SourceEntry) -> bool {
+        entry.url.trim_end_matches('/') == self.uri.trim_end_matches('/')
+            && self.suite.as_ref().is_none_or(|x| *x == entry.suite)
+    }
+}
+
+#[derive(Debug, Tabled)]
+struct SourceDisplay {
+    file: String,
+    enabled: &'static str,
+    types: &'static str,
+    uri: String,
+    suite: String,
+    components: String,
+    archs: String,
+    signed_by: String,
+}
+
+/// A sources.list file
+struct SourcesFile {
+    path: PathBuf,
+    text: String,
+}
+
+enum Change {
+    Write(PathBuf, String),
+    Delete(PathBuf),
+    /// 转换为 deb822 格式后备份原文件
+    Backup(PathBuf),
+}
+
+impl CliExecuter for Sources {
+    fn execute(self, config: &Config, no_progress: bool) -> Result<i32, OutputError> {
+        let Sources {
+            subcmd,
+            no_refresh,
+            sysroot,
+            dry_run,
+        } = self;
+
+        let files = read_sources(&sysroot)?;
+
+        // 新增源时只需刷新这个源
+        let mut refresh_only = vec![];
+
+        let changes = match subcmd {
+            SourcesSubCmd::List => {
+                list(&files)?;
+                return Ok(0);
+            }
+            SourcesSubCmd::Add {
+                uri,
+                suite,
+                components,
+                source,
+                signed_by,
+                archs,
+                name,
+                one_line,
+            } => {
+                // 非平铺源必须指定 component
+                if !suite.ends_with('/') && components.is_empty() {
+                    return Err(OutputError {
+                        description: fl!("sources-missing-components", uri = uri),
+                        source: None,
+                    });
+                }
+
+                refresh_only.push(uri.clone());
+
+                let entry = SourceEntry {
+                    enabled: true,
+                    source,
+                    options: vec![],
+                    url: uri,
+                    suite,
+                    components,
+                    archs: (!archs.is_empty()).then_some(archs),
+                    signed_by: (!signed_by.is_empty()).then_some(Signature::KeyPath(signed_by)),
+                    trusted: false,
+                    is_deb822: !one_line,
+                };
+
+                vec![add(&files, &sysroot, entry, name, one_line)?]
+            }
+            SourcesSubCmd::Remove(selector) => edit(&files, &selector, |_| false)?,
+            SourcesSubCmd::Enable(selector) => edit(&files, &selector, |e| {
+                e.enabled = true;
+                true
+            })?,
+            SourcesSubCmd::Disable(selector) => edit(&files, &selector, |e| {
+                e.enabled = false;
+                true
+            })?,
+            SourcesSubCmd::Set {
+                selector,
+                signed_by,
+                archs,
+            } => edit(&files, &selector, |e| {
+                if !signed_by.is_empty() {
+                    e.signed_by = Some(Signature::KeyPath(signed_by.clone()));
+                }
+                if !archs.is_empty() {
+                    e.archs = Some(archs.clone());
+                }
+                true
+            })?,
+            SourcesSubCmd::Modernize => modernize(&files, &sysroot)?,
+        };
+
+        check_duplicate(&files, &changes)?;
+
+        if dry_run {
+            for change in &changes {
+                match change {
+                    Change::Write(path, text) => println!("{}:\n{text}", path.display()),
+                    Change::Delete(path) => println!("rm {}", path.display()),
+                    Change::Backup(path) => {
+                        println!("mv {} {}.bak", path.display(), path.display())
+                    }
+                }
+            }
+            info!("Running in dry-run mode, Exit.");
+            return Ok(0);
+        }
+
+        root()?;
+
+        apply(&changes)?;
+
+        success!("{}", fl!("sources-updated"));
+
+        if !no_refresh {
+            let apt_config = AptConfig::new();
+            let auth_config = AuthConfig::system(&sysroot)?;
+            let sysroot = sysroot.to_string_lossy();
+
+            let refresh = Refresh::builder()
+                .client(&HTTP_CLIENT)
+                .dry_run(false)
+                .no_progress(no_progress)
+                .sysroot(&sysroot)
+                .config(config)
+                .apt_config(&apt_config)
+                .auth_config(&auth_config)
+                .sources(refresh_only);
+
+            #[cfg(feature = "aosc")]
+            let refresh = refresh.refresh_topics(!config.no_refresh_topics()).build();
+
+            #[cfg(not(feature = "aosc"))]
+            let refresh = refresh.build();
+
+            refresh.run()?;
+        }
+
+        Ok(0)
+    }
+}
+
+fn read_sources(sysroot: &Path) -> Result<Vec<SourcesFile>, OutputError> {
+    let lists = SourcesLists::scan_from_root(sysroot).map_err(RefreshError::ScanSourceError)?;
+
+    lists
+        .iter()
+        .map(|list| {
+            let text = fs::read_to_string(&list.path).map_err(|e| OutputError {
+                description: fl!("failed-to-read", p = list.path.display().to_string()),
+                source: Some(Box::new(e)),
+            })?;
+
+            Ok(SourcesFile {
+                path: list.path.clone(),
+                text,
+            })
+        })
+        .collect()
+}
+
+fn is_deb822(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x == "sources")
+}
+
+/// Parse a line of one-line style sources.list, commented out entries are treated as disabled
+fn parse_line(line: &str) -> Option<SourceEntry> {
+    let line = line.trim();
+
+    match line.strip_prefix('#') {
+        Some(line) => {
+            let mut entry = SourceEntry::from_str(line.trim_start_matches('#').trim()).ok()?;
+            entry.enabled = false;
+            Some(entry)
+        }
+        None => SourceEntry::from_str(line).ok(),
+    }
+}
+
+/// All entries (disabled included) of a sources.list file
+fn parse_entries(path: &Path, text: &str) -> Result<Vec<SourceEntry>, OutputError> {
+    if !is_deb822(path) {
+        return Ok(text.lines().filter_map(parse_line).collect());
+    }
+
+    // 空文件不是合法的 deb822 格式
+    if text.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let list = SourceListDeb822::from_str(text).map_err(RefreshError::ScanSourceError)?;
+
+    Ok(list.entries)
+}
+
+fn list(files: &[SourcesFile]) -> Result<(), OutputError> {
+    let mut res = vec![];
+
+    for file in files {
+        let name = file
+            .path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        for entry in parse_entries(&file.path, &file.text)? {
+            res.push(SourceDisplay {
+                file: name.clone(),
+                enabled: if entry.enabled { "yes" } else { "no" },
+                types: if entry.source { "deb-src" } else { "deb" },
+                uri: entry.url,
+                suite: entry.suite,
+                components: entry.components.join(" "),
+                archs: entry.archs.map(|x| x.join(" ")).unwrap_or_default(),
+                signed_by: match entry.signed_by {
+                    Some(Signature::KeyPath(paths)) => paths
+                        .iter()
+                        .map(|x| x.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    Some(Signature::KeyBlock(_)) => fl!("sources-embedded-key"),
+                    None => String::new(),
+                },
+            });
+        }
+    }
+
+    let mut printer = PagerPrinter::new(stdout());
+    printer
+        .print_table(
+            res,
+            vec![
+                "File",
+                "Enabled",
+                "Type",
+                "URI",
+                "Suite",
+                "Components",
+                "Architectures",
+                "Signed-By",
+            ],
+        )
+        .ok();
+
+    Ok(())
+}
+
+fn add(
+    files: &[SourcesFile],
+    sysroot: &Path,
+    entry: SourceEntry,
+    name: Option<String>,
+    one_line: bool,
+) -> Result<Change, OutputError> {
+    let name = name.unwrap_or_else(|| default_name(&entry.url));
+
+    if name.is_empty() || name.contains('/') {
+        return Err(OutputError {
+            description: fl!("sources-invalid-name", name = name),
+            source: None,
+        });
+    }
+
+    let path = sysroot.join("etc/apt/sources.list.d").join(format!(
+        "{name}.{}",
+        if one_line { "list" } else { "sources" }
+    ));
+
+    let line = if one_line {
+        one_line_entry(&entry)
+    } else {
+        deb822_entries(&[entry])
+    };
+
+    let text = match files.iter().find(|x| x.path == path) {
+        Some(file) if one_line => format!("{}\n{line}\n", file.text.trim_end()),
+        Some(file) => format!("{}\n\n{line}", file.text.trim_end()),
+        None if one_line => format!("{line}\n"),
+        None => line,
+    };
+
+    Ok(Change::Write(path, text))
+}
+
+/// File name of new source, e.g. `https://repo.example.com/debian` => `repo.example.com_debian`
+fn default_name(uri: &str) -> String {
+    uri.split_once("://")
+        .map(|x| x.1)
+        .unwrap_or(uri)
+        .trim_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Modify entries matching `selector` with `f`, entry is removed if `f` returns false
+fn edit<F>(files: &[SourcesFile], selector: &Selector, f: F) -> Result<Vec<Change>, OutputError>
+where
+    F: Fn(&mut SourceEntry) -> bool,
+{
+    let mut changes = vec![];
+
+    for file in files {
+        let mut matched = false;
+
+        let text = if is_deb822(&file.path) {
+            // 逐段修改，保留未修改的段落、注释和 oma 不认识的字段
+            let mut lines: Vec<String> = vec![];
+            let mut skip_blank = false;
+
+            for block in blocks(&file.text) {
+                let Block::Stanza(stanza) = block else {
+                    if !skip_blank {
+                        lines.push(String::new());
+                    }
+                    skip_blank = false;
+                    continue;
+                };
+
+                let edited = edit_stanza(&stanza, selector, &f);
+
+                match edited {
+                    None => lines.extend(stanza.iter().map(|x| x.to_string())),
+                    Some(stanzas) => {
+                        matched = true;
+                        skip_blank = stanzas.is_empty();
+                        lines.extend(stanzas.join(&[String::new()][..]));
+                    }
+                }
+            }
+
+            let mut text = lines.join("\n").trim_end().to_string();
+            text.push('\n');
+            text
+        } else {
+            // 单行格式逐行修改，保留未修改的行和注释
+            let mut lines = vec![];
+
+            for line in file.text.lines() {
+                match parse_line(line) {
+                    Some(mut entry) if selector.matches(&entry) => {
+                        matched = true;
+                        if f(&mut entry) {
+                            lines.push(one_line_entry(&entry));
+                        }
+                    }
+                    _ => lines.push(line.to_string()),
+                }
+            }
+
+            let mut text = lines.join("\n");
+            text.push('\n');
+            text
+        };
+
+        if !matched {
+            continue;
+        }
+
+        let is_main = file.path.ends_with("etc/apt/sources.list");
+
+        if parse_entries(&file.path, &text)?.is_empty() && !is_main {
+            changes.push(Change::Delete(file.path.clone()));
+        } else {
+            changes.push(Change::Write(file.path.clone(), text));
+        }
+    }
+
+    if changes.is_empty() {
+        return Err(OutputError {
+            description: fl!("sources-no-match", uri = selector.uri.as_str()),
+            source: None,
+        });
+    }
+
+    Ok(changes)
+}
+
+enum Block<'a> {
+    Stanza(Vec<&'a str>),
+    Blank,
+}
+
+/// Split deb822 text into stanzas (comments included) and the blank lines between them
+fn blocks(text: &str) -> Vec<Block<'_>> {
+    let mut res = vec![];
+    let mut stanza = vec![];
+
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !stanza.is_empty() {
+                res.push(Block::Stanza(std::mem::take(&mut stanza)));
+            }
+            res.push(Block::Blank);
+        } else {
+            stanza.push(line);
+        }
+    }
+
+    if !stanza.is_empty() {
+        res.push(Block::Stanza(stanza));
+    }
+
+    res
+}
+
+/// Apply `f` to entries of a deb822 stanza matching `selector`
+///
+/// Returns `None` if nothing matches, otherwise the stanzas to write in place of it. Only
+/// the fields that `f` changed are rewritten. If just some of the entries in the stanza
+/// match, the stanza is split into one stanza per entry first.
+fn edit_stanza<F>(stanza: &[&str], selector: &Selector, f: &F) -> Option<Vec<Vec<String>>>
+where
+    F: Fn(&mut SourceEntry) -> bool,
+{
+    let entries = SourceListDeb822::from_str(&stanza.join("\n")).ok()?.entries;
+
+    if !entries.iter().any(|x| selector.matches(x)) {
+        return None;
+    }
+
+    let lines = stanza.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+    if entries.iter().all(|x| selector.matches(x)) {
+        // 同一段落的条目除了类型、URI 和套件外都相同，修改结果也相同
+        let mut new = entries[0].clone();
+        if !f(&mut new) {
+            return Some(vec![]);
+        }
+
+        return Some(vec![edit_fields(lines, &entries[0], &new)]);
+    }
+
+    let mut res = vec![];
+
+    for entry in entries {
+        let mut lines = lines.clone();
+        set_field(
+            &mut lines,
+            "Types",
+            Some(if entry.source { "deb-src" } else { "deb" }),
+        );
+        set_field(&mut lines, "URIs", Some(&entry.url));
+        set_field(&mut lines, "Suites", Some(&entry.suite));
+
+        if !selector.matches(&entry) {
+            res.push(lines);
+            continue;
+        }
+
+        let mut new = entry.clone();
+        if f(&mut new) {
+            res.push(edit_fields(lines, &entry, &new));
+        }
+    }
+
+    Some(res)
+}
+
+/// Rewrite fields of a stanza which differ between `old` and `new`
+fn edit_fields(mut lines: Vec<String>, old: &SourceEntry, new: &SourceEntry) -> Vec<String> {
+    if old.enabled != new.enabled {
+        set_field(
+            &mut lines,
+            "Enabled",
+            Some(if new.enabled { "yes" } else { "no" }),
+        );
+    }
+
+    if old.archs != new.archs {
+        let archs = new.archs.as_ref().map(|x| x.join(" "));
+        set_field(&mut lines, "Architectures", archs.as_deref());
+    }
+
+    if old.signed_by != new.signed_by {
+        let signed_by = match &new.signed_by {
+            Some(Signature::KeyPath(paths)) => Some(
+                paths
+                    .iter()
+                    .map(|x| x.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            // 内嵌密钥不会被修改
+            Some(Signature::KeyBlock(_)) | None => None,
+        };
+        set_field(&mut lines, "Signed-By", signed_by.as_deref());
+    }
+
+    lines
+}
+
+/// Replace (or remove if `value` is `None`) a field of a stanza, append it if not exist
+fn set_field(lines: &mut Vec<String>, key: &str, value: Option<&str>) {
+    let is_key = |line: &str| {
+        line.split_once(':')
+            .is_some_and(|(k, _)| k.trim().eq_ignore_ascii_case(key))
+    };
+
+    let pos = lines
+        .iter()
+        .position(|x| !x.starts_with([' ', '\t', '#']) && is_key(x));
+
+    let pos = match pos {
+        Some(pos) => {
+            // 连同多行字段的后续行一起删除
+            let end = lines[pos + 1..]
+                .iter()
+                .position(|x| !x.starts_with([' ', '\t']))
+                .map(|x| pos + 1 + x)
+                .unwrap_or(lines.len());
+            lines.drain(pos..end);
+            pos
+        }
+        None => lines.len(),
+    };
+
+    if let Some(value) = value {
+        lines.insert(pos, format!("{key}: {value}"));
+    }
+}
+
+fn modernize(files: &[SourcesFile], sysroot: &Path) -> Result<Vec<Change>, OutputError> {
+    let mut changes = vec![];
+
+    for file in files {
+        if is_deb822(&file.path) {
+            continue;
+        }
+
+        let entries = parse_entries(&file.path, &file.text)?;
+
+        if entries.is_empty() {
+            continue;
+        }
+
+        let new = if file.path.ends_with("etc/apt/sources.list") {
+            sysroot.join(MOVED_FROM_MAIN)
+        } else {
+            file.path.with_extension("sources")
+        };
+
+        if new.exists() {
+            return Err(OutputError {
+                description: fl!("sources-file-exists", path = new.display().to_string()),
+                source: None,
+            });
+        }
+
+        changes.push(Change::Write(new, deb822_entries(&entries)));
+        changes.push(Change::Backup(file.path.clone()));
+    }
+
+    Ok(changes)
+}
+
+/// Check duplicate repositories in sources after `changes` are applied
+fn check_duplicate(files: &[SourcesFile], changes: &[Change]) -> Result<(), OutputError> {
+    let mut entries = vec![];
+
+    for file in files {
+        let changed = changes.iter().any(|x| match x {
+            Change::Write(path, _) | Change::Delete(path) | Change::Backup(path) => {
+                *path == file.path
+            }
+        });
+
+        if !changed {
+            entries.extend(parse_entries(&file.path, &file.text)?);
+        }
+    }
+
+    for change in changes {
+        if let Change::Write(path, text) = change {
+            entries.extend(parse_entries(path, text)?);
+        }
+    }
+
+    let entries = entries
+        .into_iter()
+        .filter(|x| x.enabled)
+        .map(|x| OmaSourceEntry::new(x, ""))
+        .collect::<Vec<_>>();
+
+    detect_duplicate_repositories(&entries)?;
+
+    Ok(())
+}
+
+fn apply(changes: &[Change]) -> Result<(), OutputError> {
+    let err = |path: &Path| {
+        let p = path.display().to_string();
+        move |e| OutputError {
+            description: fl!("failed-to-operate-path", p = p),
+            source: Some(Box::new(e)),
+        }
+    };
+
+    for change in changes {
+        match change {
+            Change::Write(path, text) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(err(parent))?;
+                }
+                fs::write(path, text).map_err(err(path))?;
+            }
+            Change::Delete(path) => fs::remove_file(path).map_err(err(path))?,
+            Change::Backup(path) => {
+                let mut bak = path.clone().into_os_string();
+                bak.push(".bak");
+                fs::rename(path, bak).map_err(err(path))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_default_name() {
+    assert_eq!(
+        default_name("https://repo.example.com/debian/"),
+        "repo.example.com_debian"
+    );
+    assert_eq!(default_name("file:/srv/repo"), "file__srv_repo");
+}
+
+#[cfg(test)]
+fn edit_file(
+    path: &str,
+    text: &str,
+    uri: &str,
+    suite: Option<&str>,
+    f: impl Fn(&mut SourceEntry) -> bool,
+) -> Option<String> {
+    let files = [SourcesFile {
+        path: PathBuf::from(path),
+        text: text.to_string(),
+    }];
+
+    let selector = Selector {
+        uri: uri.to_string(),
+        suite: suite.map(|x| x.to_string()),
+    };
+
+    match edit(&files, &selector, f).unwrap().remove(0) {
+        Change::Write(_, text) => Some(text),
+        Change::Delete(_) => None,
+        Change::Backup(_) => unreachable!(),
+    }
+}
+
+#[test]
+fn test_edit_one_line() {
+    let path = "/etc/apt/sources.list.d/test.list";
+    let text = "# main repo\ndeb https://a.example.com/debian stable main\n# deb https://b.example.com/debian stable main\n";
+
+    let disabled = edit_file(path, text, "https://a.example.com/debian", None, |e| {
+        e.enabled = false;
+        true
+    })
+    .unwrap();
+    assert_eq!(
+        disabled,
+        "# main repo\n# deb https://a.example.com/debian stable main\n# deb https://b.example.com/debian stable main\n"
+    );
+
+    let enabled = edit_file(path, text, "https://b.example.com/debian/", None, |e| {
+        e.enabled = true;
+        true
+    })
+    .unwrap();
+    assert!(enabled.contains("\ndeb https://b.example.com/debian stable main\n"));
+
+    let set = edit_file(path, text, "https://a.example.com/debian", None, |e| {
+        e.archs = Some(vec!["amd64".to_string()]);
+        true
+    })
+    .unwrap();
+    assert!(
+        set.starts_with("# main repo\ndeb [arch=amd64] https://a.example.com/debian stable main\n")
+    );
+
+    let removed = edit_file(path, text, "https://a.example.com/debian", None, |_| false).unwrap();
+    assert_eq!(
+        removed,
+        "# main repo\n# deb https://b.example.com/debian stable main\n"
+    );
+
+    // 只剩注释的文件会被删除
+    let text = "deb https://a.example.com/debian stable main\n";
+    assert!(edit_file(path, text, "https://a.example.com/debian", None, |_| false).is_none());
+}
+
+#[test]
+fn test_edit_deb822() {
+    let path = "/etc/apt/sources.list.d/test.sources";
+    let text = "# Debian
+Types: deb
+URIs: https://a.example.com/debian
+Suites: stable stable-updates
+Components: main
+X-Repolib-Name: Debian
+
+Types: deb
+URIs: https://b.example.com/debian
+Suites: stable
+Components: main
+Enabled: no
+";
+
+    let disabled = edit_file(path, text, "https://a.example.com/debian", None, |e| {
+        e.enabled = false;
+        true
+    })
+    .unwrap();
+    assert!(disabled.starts_with(
+        "# Debian\nTypes: deb\nURIs: https://a.example.com/debian\nSuites: stable stable-updates\nComponents: main\nX-Repolib-Name: Debian\nEnabled: no\n\n"
+    ));
+
+    let enabled = edit_file(path, text, "https://b.example.com/debian", None, |e| {
+        e.enabled = true;
+        true
+    })
+    .unwrap();
+    assert!(enabled.ends_with("Components: main\nEnabled: yes\n"));
+    assert!(enabled.starts_with("# Debian\nTypes: deb\n"));
+
+    let set = edit_file(path, text, "https://a.example.com/debian", None, |e| {
+        e.signed_by = Some(Signature::KeyPath(vec!["/usr/share/keyrings/a.gpg".into()]));
+        true
+    })
+    .unwrap();
+    assert!(set.contains("X-Repolib-Name: Debian\nSigned-By: /usr/share/keyrings/a.gpg\n\n"));
+
+    // 只修改段落中的一个套件时拆分段落
+    let set = edit_file(
+        path,
+        text,
+        "https://a.example.com/debian",
+        Some("stable-updates"),
+        |e| {
+            e.archs = Some(vec!["amd64".to_string(), "i386".to_string()]);
+            true
+        },
+    )
+    .unwrap();
+    assert!(set.starts_with(
+        "# Debian\nTypes: deb\nURIs: https://a.example.com/debian\nSuites: stable\nComponents: main\nX-Repolib-Name: Debian\n\n# Debian\nTypes: deb\nURIs: https://a.example.com/debian\nSuites: stable-updates\nComponents: main\nX-Repolib-Name: Debian\nArchitectures: amd64 i386\n\n"
+    ));
+
+    let removed = edit_file(path, text, "https://a.example.com/debian", None, |_| false).unwrap();
+    assert_eq!(
+        removed,
+        "Types: deb\nURIs: https://b.example.com/debian\nSuites: stable\nComponents: main\nEnabled: no\n"
+    );
+
+    let text = "Types: deb\nURIs: https://a.example.com/debian\nSuites: stable\nComponents: main\n";
+    assert!(edit_file(path, text, "https://a.example.com/debian", None, |_| false).is_none());
+}
@@ -182,10 +182,10 @@ impl CliExecuter for Topics {
                 .client(&HTTP_CLIENT)
                 .dry_run(dry_run)
                 .no_progress(no_progress)
-                .network_thread(config.network_thread())
                 .sysroot(&sysroot.to_string_lossy())
                 .refresh_topics(true)
-                .config(&apt_config)
+                .config(config)
+                .apt_config(&apt_config)
                 .auth_config(&auth_config)
                 .build()
                 .run()?;
@@ -243,7 +243,7 @@ impl CliExecuter for Topics {
                 .yes(false)
                 .remove_config(remove_config)
                 .autoremove(autoremove)
-                .network_thread(config.network_thread())
+                .network(config.network_options())
                 .auth_config(&auth_config)
                 .check_update(true)
                 .build()
@@ -44,8 +44,12 @@ use crate::color_formatter;
 use crate::config::Config;
 use crate::error::OutputError;
 use crate::fl;
+use crate::http_client_builder;
+use crate::install_progress::JsonInstallProgressManager;
 use crate::install_progress::NoInstallProgressManager;
 use crate::install_progress::OmaInstallProgressManager;
+use crate::pb::json_progress;
+use crate::pb::JsonProgress;
 use crate::pb::NoProgressBar;
 use crate::pb::OmaMultiProgressBar;
 use crate::pb::OmaProgressBar;
@@ -57,11 +61,14 @@ use crate::utils::root;
 use crate::HTTP_CLIENT;
 
 use super::remove::ask_user_do_as_i_say;
+use super::utils::check_local_debs;
 use super::utils::handle_features;
 use super::utils::handle_no_result;
 use super::utils::is_nothing_to_do;
 use super::utils::lock_oma;
+use super::utils::mirror_lists;
 use super::utils::no_check_dbus_warn;
+use super::utils::tls_clients;
 use super::utils::Refresh;
 use crate::args::CliExecuter;
 
@@ -156,6 +163,8 @@ impl CliExecuter for Upgrade {
         let apt_config = AptConfig::new();
 
         let auth_config = AuthConfig::system(&sysroot)?;
+        let tls_clients = tls_clients(&apt_config, &auth_config)?;
+        let mut mirrors = None;
 
         if !no_refresh {
             let sysroot = sysroot.to_string_lossy();
@@ -163,9 +172,9 @@ impl CliExecuter for Upgrade {
                 .client(&HTTP_CLIENT)
                 .dry_run(dry_run)
                 .no_progress(no_progress)
-                .network_thread(config.network_thread())
                 .sysroot(&sysroot)
-                .config(&apt_config)
+                .config(config)
+                .apt_config(&apt_config)
                 .auth_config(&auth_config);
 
             #[cfg(feature = "aosc")]
@@ -189,10 +198,14 @@ impl CliExecuter for Upgrade {
             .map(|x| x.to_owned())
             .collect::<Vec<_>>();
 
+        check_local_debs(config, &sysroot, &local_debs)?;
+
         let (tx, rx) = unbounded();
 
         thread::spawn(move || {
-            let mut pb: Box<dyn RenderDownloadProgress> = if no_progress || !is_terminal() {
+            let mut pb: Box<dyn RenderDownloadProgress> = if json_progress() {
+                Box::new(JsonProgress::default())
+            } else if no_progress || !is_terminal() {
                 Box::new(NoProgressBar::default())
             } else {
                 Box::new(OmaMultiProgressBar::default())
@@ -338,17 +351,23 @@ impl CliExecuter for Upgrade {
             );
 
             let start_time = Local::now().timestamp();
+            let mirror_lists = mirrors.get_or_insert_with(|| mirror_lists(&sysroot));
+            let client = http_client_builder(&apt.config).build()?;
 
             match apt.commit(
-                if no_progress || !is_terminal() {
+                if json_progress() {
+                    Box::new(JsonInstallProgressManager)
+                } else if no_progress || !is_terminal() {
                     Box::new(NoInstallProgressManager)
                 } else {
                     Box::new(OmaInstallProgressManager::new(yes))
                 },
                 &op,
-                &HTTP_CLIENT,
+                &client,
                 CommitNetworkConfig {
-                    network_thread: Some(config.network_thread()),
+                    network: config.network_options(),
+                    tls_clients: Some(&tls_clients),
+                    mirror_lists: Some(mirror_lists),
                     auth_config: &auth_config,
                 },
                 |event| async {
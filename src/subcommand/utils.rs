@@ -15,11 +15,17 @@ use std::sync::atomic::Ordering;
 use std::thread;
 
 use crate::color_formatter;
+use crate::config::Config;
+use crate::config::DebSignaturePolicy;
 use crate::error::OutputError;
 use crate::fl;
+use crate::http_client_builder;
+use crate::install_progress::JsonInstallProgressManager;
 use crate::install_progress::NoInstallProgressManager;
 use crate::install_progress::OmaInstallProgressManager;
 use crate::msg;
+use crate::pb::json_progress;
+use crate::pb::JsonProgress;
 use crate::pb::NoProgressBar;
 use crate::pb::OmaMultiProgressBar;
 use crate::pb::OmaProgressBar;
@@ -35,6 +41,7 @@ use crate::RT;
 use crate::WRITER;
 use ahash::HashSet;
 use apt_auth_config::AuthConfig;
+use apt_auth_config::ClientCert;
 use bon::builder;
 use bon::Builder;
 use chrono::Local;
@@ -49,23 +56,31 @@ use oma_console::print::Action;
 use oma_console::writer::Writeln;
 use oma_contents::searcher::search;
 use oma_contents::searcher::Mode;
+use oma_fetch::MirrorLists;
+use oma_fetch::TlsClients;
 use oma_history::connect_db;
 use oma_history::create_db_file;
 use oma_history::write_history_entry;
 use oma_history::SummaryType;
 use oma_pm::apt::AptConfig;
 use oma_pm::apt::FilterMode;
+use oma_pm::apt::NetworkOptions;
 use oma_pm::apt::OmaApt;
 use oma_pm::apt::OmaAptArgs;
 use oma_pm::apt::OmaAptError;
 use oma_pm::apt::SummarySort;
 use oma_pm::apt::{InstallEntry, RemoveEntry};
 use oma_pm::CommitNetworkConfig;
+use oma_refresh::db::mirror_lists as refresh_mirror_lists;
 use oma_refresh::db::OmaRefresh;
+use oma_refresh::report::SourceReport;
+use oma_refresh::snapshot::Snapshot;
+use oma_repo_verify::deb::verify_deb;
 use oma_utils::dpkg::dpkg_arch;
 use oma_utils::oma::lock_oma_inner;
 use oma_utils::oma::unlock_oma;
 use reqwest::Client;
+use reqwest::Identity;
 use std::fmt::Display;
 use tracing::debug;
 use tracing::error;
@@ -155,6 +170,42 @@ pub(crate) fn handle_no_result(
     })
 }
 
+/// Check signatures of local .deb packages according to `[verify] deb_signature`
+pub(crate) fn check_local_debs(
+    config: &Config,
+    sysroot: &Path,
+    local_debs: &[String],
+) -> Result<(), OutputError> {
+    let policy = config.deb_signature();
+
+    if policy == DebSignaturePolicy::Off {
+        return Ok(());
+    }
+
+    let signature_policy = config.signature_policy();
+
+    for deb in local_debs {
+        match verify_deb(deb, sysroot, signature_policy.default_policy()) {
+            Ok(signers) => info!(
+                "{}",
+                fl!(
+                    "deb-signature-verified",
+                    path = deb.as_str(),
+                    fingerprint = signers
+                        .iter()
+                        .map(|x| x.fingerprint.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            ),
+            Err(e) if policy == DebSignaturePolicy::Require => return Err(e.into()),
+            Err(e) => warn!("{}", OutputError::from(e).description),
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct LockError {
     source: io::Error,
@@ -193,34 +244,49 @@ pub struct Refresh<'a> {
     dry_run: bool,
     #[builder(default)]
     no_progress: bool,
-    #[builder(default = 4)]
-    network_thread: usize,
     #[builder(default = "/")]
     sysroot: &'a str,
     #[builder(default = true)]
     refresh_topics: bool,
-    config: &'a AptConfig,
+    /// Network and verification settings are taken from here
+    config: &'a Config,
+    apt_config: &'a AptConfig,
     auth_config: &'a AuthConfig,
+    #[builder(default)]
+    allow_release_rollback: bool,
+    #[builder(default)]
+    sources: Vec<String>,
+    #[builder(default)]
+    report: bool,
+    /// Resolve against this archive snapshot
+    snapshot: Option<Snapshot>,
+    snapshot_url: Option<String>,
 }
 
 impl Refresh<'_> {
-    pub(crate) fn run(self) -> Result<(), OutputError> {
+    /// Refresh sources, returns what changed if `report` is enabled
+    pub(crate) fn run(self) -> Result<Option<Vec<SourceReport>>, OutputError> {
         let Refresh {
             client,
             dry_run,
             no_progress,
-            network_thread,
             sysroot,
             refresh_topics,
             config,
+            apt_config,
             auth_config,
+            allow_release_rollback,
+            sources,
+            report,
+            snapshot,
+            snapshot_url,
         } = self;
 
         #[cfg(not(feature = "aosc"))]
         let _ = refresh_topics;
 
         if dry_run {
-            return Ok(());
+            return Ok(None);
         }
 
         info!("{}", fl!("refreshing-repo-metadata"));
@@ -231,14 +297,47 @@ impl Refresh<'_> {
 
         let arch = dpkg_arch(&sysroot)?;
 
+        let tls_clients = tls_clients(apt_config, auth_config)?;
+
+        match snapshot {
+            Some(snapshot) => info!(
+                "{}",
+                fl!("refresh-using-snapshot", time = snapshot.to_string())
+            ),
+            // 记录的快照只用于 oma refresh，其他命令按原样刷新
+            None => {
+                if let Some((snapshot, _)) = RT.block_on(Snapshot::load(&sysroot)) {
+                    warn!(
+                        "{}",
+                        fl!("refresh-snapshot-not-applied", time = snapshot.to_string())
+                    );
+                }
+            }
+        }
+
         let refresh = OmaRefresh::builder()
             .download_dir(sysroot.join("var/lib/apt/lists"))
             .source(sysroot)
-            .threads(network_thread)
+            .threads(config.network_thread())
             .arch(arch)
-            .apt_config(config)
+            .apt_config(apt_config)
             .client(client)
             .auth_config(auth_config)
+            .maybe_limit_rate(config.limit_rate())
+            .maybe_low_speed_limit(config.low_speed_limit())
+            .maybe_adaptive_threads(config.adaptive_threads())
+            .max_per_host(config.max_connections_per_host())
+            .retry_policy(config.retry_policy())
+            .tls_clients(&tls_clients)
+            .allow_release_rollback(allow_release_rollback)
+            .source_filter(sources)
+            .report(report)
+            .maybe_snapshot(snapshot)
+            .maybe_snapshot_url(snapshot_url)
+            .snapshot_archives(config.snapshot_archives())
+            .signature_policy(config.signature_policy())
+            .maybe_key_expiry_warning(config.key_expiry_warning())
+            .appstream(config.appstream())
             .topic_msg(&msg);
 
         #[cfg(feature = "aosc")]
@@ -250,7 +349,9 @@ impl Refresh<'_> {
         let (tx, rx) = unbounded();
 
         thread::spawn(move || {
-            let mut pb: Box<dyn RenderRefreshProgress> = if no_progress || !is_terminal() {
+            let mut pb: Box<dyn RenderRefreshProgress> = if json_progress() {
+                Box::new(JsonProgress::default())
+            } else if no_progress || !is_terminal() {
                 Box::new(NoProgressBar::default())
             } else {
                 Box::new(OmaMultiProgressBar::default())
@@ -258,7 +359,7 @@ impl Refresh<'_> {
             pb.render_refresh_progress(&rx);
         });
 
-        RT.block_on(async move {
+        let report = RT.block_on(async move {
             refresh
                 .start(|event| async {
                     if let Err(e) = tx.send_async(event).await {
@@ -268,7 +369,7 @@ impl Refresh<'_> {
                 .await
         })?;
 
-        Ok(())
+        Ok(report)
     }
 }
 
@@ -295,7 +396,7 @@ pub(crate) struct CommitChanges<'a> {
     #[builder(default)]
     autoremove: bool,
     auth_config: &'a AuthConfig,
-    network_thread: usize,
+    network: NetworkOptions,
     #[builder(default)]
     check_update: bool,
 }
@@ -315,10 +416,14 @@ impl CommitChanges<'_> {
             remove_config,
             autoremove,
             auth_config,
-            network_thread,
+            network,
             check_update,
         } = self;
 
+        // 使用已应用 --apt-options 的配置构建客户端，使代理设置生效
+        let client = http_client_builder(&apt.config).build()?;
+        let tls_clients = tls_clients(&apt.config, auth_config)?;
+
         let pb = if !no_progress {
             OmaProgressBar::new_spinner(Some(fl!("resolving-dependencies"))).into()
         } else {
@@ -409,11 +514,14 @@ impl CommitChanges<'_> {
         }
 
         let start_time = Local::now().timestamp();
+        let mirror_lists = mirror_lists(&sysroot);
 
         let (tx, rx) = unbounded();
 
         thread::spawn(move || {
-            let mut pb: Box<dyn RenderDownloadProgress> = if no_progress || !is_terminal() {
+            let mut pb: Box<dyn RenderDownloadProgress> = if json_progress() {
+                Box::new(JsonProgress::default())
+            } else if no_progress || !is_terminal() {
                 Box::new(NoProgressBar::default())
             } else {
                 Box::new(OmaMultiProgressBar::default())
@@ -422,15 +530,19 @@ impl CommitChanges<'_> {
         });
 
         let res = apt.commit(
-            if no_progress || !is_terminal() {
+            if json_progress() {
+                Box::new(JsonInstallProgressManager)
+            } else if no_progress || !is_terminal() {
                 Box::new(NoInstallProgressManager)
             } else {
                 Box::new(OmaInstallProgressManager::new(yes))
             },
             &op,
-            &HTTP_CLIENT,
+            &client,
             CommitNetworkConfig {
-                network_thread: Some(network_thread),
+                network,
+                tls_clients: Some(&tls_clients),
+                mirror_lists: Some(&mirror_lists),
                 auth_config,
             },
             |event| async {
@@ -487,6 +599,73 @@ impl CommitChanges<'_> {
     }
 }
 
+/// Build clients for hosts that require a TLS client certificate
+pub fn tls_clients(
+    apt_config: &AptConfig,
+    auth_config: &AuthConfig,
+) -> Result<TlsClients, OutputError> {
+    let mut clients = TlsClients::new();
+
+    for entry in &auth_config.inner {
+        let Some(cert) = &entry.client_cert else {
+            continue;
+        };
+
+        let client = http_client_builder(apt_config)
+            .identity(load_identity(cert)?)
+            .build()?;
+
+        clients.insert(entry.host.clone(), client);
+    }
+
+    Ok(clients)
+}
+
+/// Read mirror lists used by `mirror+` sources
+/// Mirror lists of `mirror+` sources, only fetched if such a source is configured
+///
+/// A failure is not fatal: packages from other sources can still be downloaded.
+pub fn mirror_lists(sysroot: impl AsRef<Path>) -> MirrorLists {
+    RT.block_on(refresh_mirror_lists(&HTTP_CLIENT, sysroot))
+        .unwrap_or_else(|e| {
+            warn!(
+                "{}",
+                fl!(
+                    "mirror-lists-unavailable",
+                    reason = OutputError::from(e).description
+                )
+            );
+            MirrorLists::new()
+        })
+}
+
+fn load_identity(cert: &ClientCert) -> Result<Identity, OutputError> {
+    let read = |path: &Path| {
+        fs::read(path).map_err(|e| OutputError {
+            description: fl!(
+                "failed-to-load-client-cert",
+                path = path.display().to_string()
+            ),
+            source: Some(Box::new(e)),
+        })
+    };
+
+    let pem = read(&cert.cert)?;
+
+    #[cfg(feature = "rustls")]
+    let identity = if cert.key == cert.cert {
+        Identity::from_pem(&pem)?
+    } else {
+        // 证书文件末尾可能没有换行，避免与私钥的 BEGIN 行连在一起
+        Identity::from_pem(&[pem, b"\n".to_vec(), read(&cert.key)?].concat())?
+    };
+
+    #[cfg(not(feature = "rustls"))]
+    let identity = Identity::from_pkcs8_pem(&pem, &read(&cert.key)?)?;
+
+    Ok(identity)
+}
+
 pub fn autoremovable_tips(count: u64, total_size: u64) -> Result<(), OutputError> {
     if count == 0 {
         return Ok(());
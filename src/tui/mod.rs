@@ -149,9 +149,9 @@ impl CliExecuter for Tui {
                 .client(&HTTP_CLIENT)
                 .dry_run(false)
                 .no_progress(no_progress)
-                .network_thread(config.network_thread())
                 .sysroot(&sysroot)
-                .config(&apt_config)
+                .config(config)
+                .apt_config(&apt_config)
                 .auth_config(&auth_config);
 
             #[cfg(feature = "aosc")]
@@ -262,7 +262,7 @@ impl CliExecuter for Tui {
                 .remove_config(remove_config)
                 .autoremove(autoremove)
                 .auth_config(&auth_config)
-                .network_thread(config.network_thread())
+                .network(config.network_options())
                 .check_update(upgrade)
                 .build()
                 .run()?;
@@ -215,6 +215,25 @@ impl Display for SearchResultDisplay<'_> {
             },
         );
 
+        for app in &i.apps {
+            let mut line = app.name.clone();
+
+            if !app.categories.is_empty() {
+                line.push_str(&format!(" [{}]", app.categories.join(",")));
+            }
+
+            if let Some(desktop_id) = app.desktop_ids.first() {
+                line.push_str(&format!(" ({desktop_id})"));
+            }
+
+            writeln!(
+                f,
+                "{}{}",
+                gen_prefix("", 10),
+                color_formatter().color_str(line, Action::Note)
+            )?;
+        }
+
         Ok(())
     }
 }